@@ -0,0 +1,32 @@
+-- Add migration script here
+
+CREATE TYPE recurrence_frequency AS ENUM ('daily', 'weekly', 'monthly', 'yearly');
+
+CREATE TABLE recurring_expense (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES category(id) ON DELETE CASCADE,
+    amount NUMERIC NOT NULL,
+    description TEXT NOT NULL,
+    payment_method VARCHAR(50),
+    tags VARCHAR(100)[],
+    frequency recurrence_frequency NOT NULL,
+    repeat_interval INTEGER NOT NULL DEFAULT 1 CHECK (repeat_interval > 0),
+    day_of_month SMALLINT CHECK (day_of_month BETWEEN 1 AND 31),
+    start_date DATE NOT NULL,
+    end_date DATE,
+    occurrence_count INTEGER NOT NULL DEFAULT 0,
+    -- NULL once the schedule is past its end date
+    next_occurrence DATE,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_recurring_expense_user_id ON recurring_expense(user_id);
+CREATE INDEX idx_recurring_expense_next_occurrence ON recurring_expense(next_occurrence);
+
+ALTER TABLE expense ADD COLUMN recurring_id UUID REFERENCES recurring_expense(id) ON DELETE SET NULL;
+
+-- one generated expense per schedule and date, so the worker can safely re-run
+CREATE UNIQUE INDEX idx_unique_expense_recurring_date ON expense (recurring_id, date)
+    WHERE recurring_id IS NOT NULL;
@@ -0,0 +1,24 @@
+-- Add migration script here
+
+CREATE TYPE import_sign_convention AS ENUM ('negative_expense', 'positive_expense');
+
+CREATE TABLE import_profile (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(50) NOT NULL,
+    date_column TEXT NOT NULL,
+    amount_column TEXT NOT NULL,
+    description_column TEXT NOT NULL,
+    category_column TEXT,
+    tags_column TEXT,
+    date_format TEXT NOT NULL DEFAULT '%Y-%m-%d',
+    decimal_separator VARCHAR(1) NOT NULL DEFAULT '.',
+    delimiter VARCHAR(1) NOT NULL DEFAULT ',',
+    sign_convention import_sign_convention NOT NULL DEFAULT 'negative_expense',
+    has_header BOOLEAN NOT NULL DEFAULT true,
+    default_category_id UUID REFERENCES category(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_unique_import_profile_per_user ON import_profile (user_id, LOWER(name));
@@ -0,0 +1,6 @@
+-- Add migration script here
+
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+-- accounts created before verification existed are trusted as they are
+UPDATE users SET email_verified_at = NOW();
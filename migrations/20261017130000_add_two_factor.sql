@@ -0,0 +1,16 @@
+-- Add migration script here
+
+-- the secret is kept while enrollment is pending, totp_enabled_at marks it confirmed
+ALTER TABLE users
+    ADD COLUMN totp_secret TEXT,
+    ADD COLUMN totp_enabled_at TIMESTAMPTZ;
+
+CREATE TABLE recovery_code (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX recovery_code_user_code_hash_idx ON recovery_code (user_id, code_hash);
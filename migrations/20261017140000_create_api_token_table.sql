@@ -0,0 +1,15 @@
+-- Add migration script here
+
+CREATE TYPE api_token_scope AS ENUM ('read_only', 'read_write');
+
+CREATE TABLE api_token (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    scope api_token_scope NOT NULL,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX api_token_user_name_idx ON api_token (user_id, name);
@@ -0,0 +1,15 @@
+-- Add migration script here
+
+CREATE TYPE data_export_status AS ENUM ('pending', 'running', 'completed', 'failed');
+
+CREATE TABLE data_export (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    status data_export_status NOT NULL DEFAULT 'pending',
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX data_export_user_id_idx ON data_export (user_id);
@@ -0,0 +1,12 @@
+-- Add migration script here
+
+ALTER TABLE expense ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE category ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- a trashed category shouldn't block reusing its name
+DROP INDEX idx_unique_category_per_user;
+CREATE UNIQUE INDEX idx_unique_category_per_user ON category (user_id, LOWER(name))
+    WHERE deleted_at IS NULL;
+
+CREATE INDEX idx_expense_deleted_at ON expense(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_category_deleted_at ON category(deleted_at) WHERE deleted_at IS NOT NULL;
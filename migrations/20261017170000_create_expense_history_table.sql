@@ -0,0 +1,28 @@
+-- Add migration script here
+
+CREATE TYPE expense_history_action AS ENUM ('created', 'updated', 'deleted', 'restored');
+
+-- no foreign keys on expense_id and actor_id, the trail outlives purged expenses
+-- and removed accounts, only the owner's account deletion takes it away
+CREATE TABLE expense_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    expense_id UUID NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    actor_id UUID NOT NULL,
+    action expense_history_action NOT NULL,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_expense_history_expense_id ON expense_history(expense_id, created_at);
+
+CREATE FUNCTION expense_history_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'expense_history is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER expense_history_no_update
+    BEFORE UPDATE ON expense_history
+    FOR EACH ROW EXECUTE FUNCTION expense_history_append_only();
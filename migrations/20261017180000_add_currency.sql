@@ -0,0 +1,64 @@
+-- Add migration script here
+
+ALTER TABLE users ADD COLUMN base_currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+
+-- amounts entered before currencies existed are taken as euros, the default base
+ALTER TABLE expense ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+ALTER TABLE expense ALTER COLUMN currency DROP DEFAULT;
+
+-- NULL follows the user's base currency at the time each expense is generated
+ALTER TABLE recurring_expense ADD COLUMN currency VARCHAR(3);
+
+-- units of currency per euro, the way the ECB quotes them; user_id is NULL for
+-- published rates and set for the ones a user entered by hand
+CREATE TABLE exchange_rate (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    currency VARCHAR(3) NOT NULL,
+    date DATE NOT NULL,
+    rate NUMERIC NOT NULL CHECK (rate > 0),
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_unique_published_exchange_rate ON exchange_rate (currency, date)
+    WHERE user_id IS NULL;
+CREATE UNIQUE INDEX idx_unique_manual_exchange_rate ON exchange_rate (user_id, currency, date)
+    WHERE user_id IS NOT NULL;
+
+-- a user's own rate wins over the published one for the same day, and the latest
+-- earlier rate covers weekends and bank holidays
+CREATE FUNCTION eur_rate(p_user_id UUID, p_currency VARCHAR, p_date DATE)
+RETURNS NUMERIC AS $$
+DECLARE
+    v_rate NUMERIC;
+BEGIN
+    IF p_currency = 'EUR' THEN
+        RETURN 1;
+    END IF;
+
+    SELECT rate INTO v_rate FROM exchange_rate
+    WHERE currency = p_currency AND date <= p_date
+        AND (user_id IS NULL OR user_id = p_user_id)
+    ORDER BY date DESC, user_id NULLS LAST
+    LIMIT 1;
+
+    -- a silently skipped amount would make the total wrong, so fail loudly
+    IF v_rate IS NULL THEN
+        RAISE EXCEPTION 'no exchange rate for % on or before %', p_currency, p_date
+            USING ERRCODE = 'XR001';
+    END IF;
+
+    RETURN v_rate;
+END;
+$$ LANGUAGE plpgsql STABLE;
+
+CREATE FUNCTION convert_amount(
+    p_user_id UUID, p_amount NUMERIC, p_from VARCHAR, p_to VARCHAR, p_date DATE
+)
+RETURNS NUMERIC AS $$
+    SELECT CASE
+        WHEN p_from = p_to THEN p_amount
+        ELSE p_amount / eur_rate(p_user_id, p_from, p_date) * eur_rate(p_user_id, p_to, p_date)
+    END
+$$ LANGUAGE sql STABLE;
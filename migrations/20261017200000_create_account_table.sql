@@ -0,0 +1,97 @@
+-- Add migration script here
+
+CREATE TYPE account_type AS ENUM ('cash', 'checking', 'savings', 'credit_card', 'other');
+
+CREATE TABLE account (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    type account_type NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    -- the balance before the first entry, in the account currency
+    opening_balance NUMERIC NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_unique_account_per_user ON account (user_id, LOWER(name));
+
+-- every payment method in use becomes an account the user can rename and reconcile
+INSERT INTO account (user_id, name, type, currency)
+SELECT p.user_id, MIN(p.name), 'other', u.base_currency
+FROM (
+    SELECT user_id, TRIM(payment_method) AS name FROM expense
+    UNION ALL
+    SELECT user_id, TRIM(payment_method) FROM recurring_expense
+) p
+JOIN users u ON u.id = p.user_id
+WHERE p.name <> ''
+GROUP BY p.user_id, LOWER(p.name), u.base_currency;
+
+ALTER TABLE expense ADD COLUMN account_id UUID REFERENCES account(id) ON DELETE RESTRICT;
+ALTER TABLE recurring_expense ADD COLUMN account_id UUID REFERENCES account(id) ON DELETE RESTRICT;
+ALTER TABLE income ADD COLUMN account_id UUID REFERENCES account(id) ON DELETE RESTRICT;
+
+UPDATE expense e SET account_id = a.id
+FROM account a
+WHERE a.user_id = e.user_id AND LOWER(a.name) = LOWER(TRIM(e.payment_method));
+
+UPDATE recurring_expense r SET account_id = a.id
+FROM account a
+WHERE a.user_id = r.user_id AND LOWER(a.name) = LOWER(TRIM(r.payment_method));
+
+ALTER TABLE expense DROP COLUMN payment_method;
+ALTER TABLE recurring_expense DROP COLUMN payment_method;
+
+CREATE INDEX idx_expense_account ON expense(account_id) WHERE account_id IS NOT NULL;
+CREATE INDEX idx_income_account ON income(account_id) WHERE account_id IS NOT NULL;
+
+-- amount leaves the source account in its currency, to_amount arrives in the target's
+CREATE TABLE transfer (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    from_account_id UUID NOT NULL REFERENCES account(id) ON DELETE RESTRICT,
+    to_account_id UUID NOT NULL REFERENCES account(id) ON DELETE RESTRICT,
+    amount NUMERIC NOT NULL CHECK (amount > 0),
+    to_amount NUMERIC NOT NULL CHECK (to_amount > 0),
+    description VARCHAR(255),
+    date DATE NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    CHECK (from_account_id <> to_account_id)
+);
+
+CREATE INDEX idx_transfer_from_account ON transfer(from_account_id);
+CREATE INDEX idx_transfer_to_account ON transfer(to_account_id);
+
+CREATE TYPE account_movement_kind AS ENUM ('expense', 'income', 'transfer_in', 'transfer_out');
+
+-- every entry that moves money in or out of an account, in the account currency
+CREATE VIEW account_movement AS
+SELECT e.account_id, 'expense'::account_movement_kind AS kind, e.id, e.date, e.created_at, e.description,
+    -convert_amount(e.user_id, e.amount, e.currency, a.currency, e.date) AS amount
+FROM expense e
+JOIN account a ON a.id = e.account_id
+WHERE e.deleted_at IS NULL
+UNION ALL
+SELECT i.account_id, 'income', i.id, i.date, i.created_at, i.description,
+    convert_amount(i.user_id, i.amount, i.currency, a.currency, i.date)
+FROM income i
+JOIN account a ON a.id = i.account_id
+UNION ALL
+SELECT from_account_id, 'transfer_out', id, date, created_at, COALESCE(description, ''), -amount
+FROM transfer
+UNION ALL
+SELECT to_account_id, 'transfer_in', id, date, created_at, COALESCE(description, ''), to_amount
+FROM transfer;
+
+-- balance at the start of p_before, or after every entry when it's NULL
+CREATE FUNCTION account_balance(p_account_id UUID, p_before DATE)
+RETURNS NUMERIC AS $$
+    SELECT a.opening_balance + COALESCE((
+        SELECT SUM(m.amount) FROM account_movement m
+        WHERE m.account_id = a.id AND (p_before IS NULL OR m.date < p_before)
+    ), 0)
+    FROM account a
+    WHERE a.id = p_account_id
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,23 @@
+-- Add migration script here
+
+-- an expense without split lines counts whole against its own category
+CREATE TABLE expense_split (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    expense_id UUID NOT NULL REFERENCES expense(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES category(id) ON DELETE CASCADE,
+    amount NUMERIC NOT NULL CHECK (amount > 0),
+    tags VARCHAR(100)[],
+    position SMALLINT NOT NULL
+);
+
+CREATE INDEX idx_expense_split_expense_id ON expense_split(expense_id);
+CREATE INDEX idx_expense_split_category_id ON expense_split(category_id);
+
+-- what each expense adds to each category, the split lines when it has them
+CREATE VIEW expense_portion AS
+SELECT e.id AS expense_id, e.user_id,
+    COALESCE(s.category_id, e.category_id) AS category_id,
+    COALESCE(s.amount, e.amount) AS amount,
+    e.currency, e.date, e.deleted_at
+FROM expense e
+LEFT JOIN expense_split s ON s.expense_id = e.id;
@@ -0,0 +1,120 @@
+-- Add migration script here
+
+CREATE TYPE ledger_role AS ENUM ('owner', 'editor', 'viewer');
+CREATE TYPE ledger_invitation_status AS ENUM ('pending', 'accepted', 'declined');
+
+CREATE TABLE ledger (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE TABLE ledger_member (
+    ledger_id UUID NOT NULL REFERENCES ledger(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role ledger_role NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (ledger_id, user_id)
+);
+
+CREATE INDEX idx_ledger_member_user_id ON ledger_member(user_id);
+
+-- the token is only kept hashed, the mail carries the plain one
+CREATE TABLE ledger_invitation (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ledger_id UUID NOT NULL REFERENCES ledger(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    role ledger_role NOT NULL,
+    invited_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    status ledger_invitation_status NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    responded_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX idx_unique_pending_invitation ON ledger_invitation (ledger_id, LOWER(email))
+    WHERE status = 'pending';
+
+-- requests without a ledger go to this one
+ALTER TABLE users ADD COLUMN default_ledger_id UUID REFERENCES ledger(id) ON DELETE SET NULL;
+
+-- every existing user starts with a personal ledger holding what they have so far
+CREATE TEMPORARY TABLE personal_ledger AS
+SELECT id AS user_id, gen_random_uuid() AS ledger_id FROM users;
+
+INSERT INTO ledger (id, name) SELECT ledger_id, 'Personal' FROM personal_ledger;
+
+INSERT INTO ledger_member (ledger_id, user_id, role)
+SELECT ledger_id, user_id, 'owner' FROM personal_ledger;
+
+UPDATE users u SET default_ledger_id = p.ledger_id
+FROM personal_ledger p
+WHERE p.user_id = u.id;
+
+ALTER TABLE category ADD COLUMN ledger_id UUID REFERENCES ledger(id) ON DELETE CASCADE;
+ALTER TABLE expense ADD COLUMN ledger_id UUID REFERENCES ledger(id) ON DELETE CASCADE;
+ALTER TABLE expense_history ADD COLUMN ledger_id UUID;
+
+UPDATE category c SET ledger_id = p.ledger_id
+FROM personal_ledger p
+WHERE p.user_id = c.user_id;
+
+UPDATE expense e SET ledger_id = p.ledger_id
+FROM personal_ledger p
+WHERE p.user_id = e.user_id;
+
+-- the trail is append-only, this backfill is the one exception
+ALTER TABLE expense_history DISABLE TRIGGER expense_history_no_update;
+
+UPDATE expense_history h SET ledger_id = p.ledger_id
+FROM personal_ledger p
+WHERE p.user_id = h.user_id;
+
+ALTER TABLE expense_history ENABLE TRIGGER expense_history_no_update;
+
+ALTER TABLE category ALTER COLUMN ledger_id SET NOT NULL;
+ALTER TABLE expense ALTER COLUMN ledger_id SET NOT NULL;
+ALTER TABLE expense_history ALTER COLUMN ledger_id SET NOT NULL;
+
+DROP TABLE personal_ledger;
+
+-- category names are shared by everyone in the ledger
+DROP INDEX idx_unique_category_per_user;
+CREATE UNIQUE INDEX idx_unique_category_per_ledger ON category (ledger_id, LOWER(name))
+    WHERE deleted_at IS NULL;
+
+CREATE INDEX idx_category_ledger_id ON category(ledger_id);
+CREATE INDEX idx_expense_ledger_id ON expense(ledger_id);
+CREATE INDEX idx_expense_history_ledger_id ON expense_history(ledger_id);
+
+CREATE FUNCTION create_personal_ledger() RETURNS trigger AS $$
+DECLARE
+    new_ledger_id UUID;
+BEGIN
+    INSERT INTO ledger (name) VALUES ('Personal') RETURNING id INTO new_ledger_id;
+    INSERT INTO ledger_member (ledger_id, user_id, role) VALUES (new_ledger_id, NEW.id, 'owner');
+    UPDATE users SET default_ledger_id = new_ledger_id WHERE id = NEW.id;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_personal_ledger
+    AFTER INSERT ON users
+    FOR EACH ROW EXECUTE FUNCTION create_personal_ledger();
+
+-- a ledger nobody belongs to anymore goes away with its categories and expenses
+CREATE FUNCTION drop_abandoned_ledger() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM ledger l
+    WHERE l.id = OLD.ledger_id
+        AND NOT EXISTS (SELECT 1 FROM ledger_member m WHERE m.ledger_id = l.id);
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER ledger_member_abandoned
+    AFTER DELETE ON ledger_member
+    FOR EACH ROW EXECUTE FUNCTION drop_abandoned_ledger();
@@ -0,0 +1,4 @@
+-- Add migration script here
+
+-- split lines copied onto every generated expense, same shape as the expense splits json
+ALTER TABLE recurring_expense ADD COLUMN splits JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,56 @@
+-- Add migration script here
+
+-- the trail of a shared ledger stays with the ledger, it goes away when the ledger does
+ALTER TABLE expense_history DROP CONSTRAINT expense_history_user_id_fkey;
+
+-- whatever a deleted user wrote into a shared ledger is taken over by whoever is left there,
+-- an owner first. their personal accounts go away with them, so expenses lose the reference
+CREATE FUNCTION hand_over_shared_rows() RETURNS trigger AS $$
+BEGIN
+    CREATE TEMPORARY TABLE ledger_heir ON COMMIT DROP AS
+    SELECT DISTINCT ON (m.ledger_id) m.ledger_id, m.user_id
+    FROM ledger_member m
+    JOIN ledger_member leaving ON leaving.ledger_id = m.ledger_id AND leaving.user_id = OLD.id
+    WHERE m.user_id <> OLD.id
+    ORDER BY m.ledger_id, m.role = 'owner' DESC, m.created_at;
+
+    -- a ledger is never left without an owner
+    UPDATE ledger_member m SET role = 'owner'
+    FROM ledger_heir h
+    WHERE m.ledger_id = h.ledger_id AND m.user_id = h.user_id
+        AND NOT EXISTS (
+            SELECT 1 FROM ledger_member o
+            WHERE o.ledger_id = h.ledger_id AND o.user_id <> OLD.id AND o.role = 'owner'
+        );
+
+    UPDATE category c SET user_id = h.user_id
+    FROM ledger_heir h
+    WHERE c.ledger_id = h.ledger_id AND c.user_id = OLD.id;
+
+    UPDATE expense e SET user_id = h.user_id, account_id = NULL
+    FROM ledger_heir h
+    WHERE e.ledger_id = h.ledger_id AND e.user_id = OLD.id;
+
+    DROP TABLE ledger_heir;
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_hand_over_shared_rows
+    BEFORE DELETE ON users
+    FOR EACH ROW EXECUTE FUNCTION hand_over_shared_rows();
+
+CREATE OR REPLACE FUNCTION drop_abandoned_ledger() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM ledger l
+    WHERE l.id = OLD.ledger_id
+        AND NOT EXISTS (SELECT 1 FROM ledger_member m WHERE m.ledger_id = l.id);
+
+    IF NOT EXISTS (SELECT 1 FROM ledger WHERE id = OLD.ledger_id) THEN
+        DELETE FROM expense_history WHERE ledger_id = OLD.ledger_id;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,49 @@
+-- Add migration script here
+
+-- a budget belongs to the ledger of its category, every member works on the same one.
+-- where members set their own, the most recently changed wins
+DELETE FROM budget b
+USING budget newer
+WHERE newer.category_id = b.category_id AND newer.month = b.month
+    AND (COALESCE(newer.updated_at, newer.created_at, 'epoch'), newer.id)
+        > (COALESCE(b.updated_at, b.created_at, 'epoch'), b.id);
+
+DROP INDEX idx_unique_budget_per_category_month;
+CREATE UNIQUE INDEX idx_unique_budget_per_category_month ON budget (category_id, month);
+
+-- shared budgets are handed over along with the rest of the ledger
+CREATE OR REPLACE FUNCTION hand_over_shared_rows() RETURNS trigger AS $$
+BEGIN
+    CREATE TEMPORARY TABLE ledger_heir ON COMMIT DROP AS
+    SELECT DISTINCT ON (m.ledger_id) m.ledger_id, m.user_id
+    FROM ledger_member m
+    JOIN ledger_member leaving ON leaving.ledger_id = m.ledger_id AND leaving.user_id = OLD.id
+    WHERE m.user_id <> OLD.id
+    ORDER BY m.ledger_id, m.role = 'owner' DESC, m.created_at;
+
+    -- a ledger is never left without an owner
+    UPDATE ledger_member m SET role = 'owner'
+    FROM ledger_heir h
+    WHERE m.ledger_id = h.ledger_id AND m.user_id = h.user_id
+        AND NOT EXISTS (
+            SELECT 1 FROM ledger_member o
+            WHERE o.ledger_id = h.ledger_id AND o.user_id <> OLD.id AND o.role = 'owner'
+        );
+
+    UPDATE category c SET user_id = h.user_id
+    FROM ledger_heir h
+    WHERE c.ledger_id = h.ledger_id AND c.user_id = OLD.id;
+
+    UPDATE expense e SET user_id = h.user_id, account_id = NULL
+    FROM ledger_heir h
+    WHERE e.ledger_id = h.ledger_id AND e.user_id = OLD.id;
+
+    UPDATE budget b SET user_id = h.user_id
+    FROM ledger_heir h, category c
+    WHERE c.id = b.category_id AND c.ledger_id = h.ledger_id AND b.user_id = OLD.id;
+
+    DROP TABLE ledger_heir;
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
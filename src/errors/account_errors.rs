@@ -0,0 +1,97 @@
+use actix_web::{HttpResponse, http::StatusCode};
+
+use crate::utils::utils::missing_exchange_rate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AccountError {
+    #[error("account still has entries")]
+    AccountInUse,
+
+    #[error("account not found")]
+    AccountNotFound,
+
+    #[error("description too long")]
+    DescriptionTooLong,
+
+    #[error("internal server error")]
+    Internal(#[from] anyhow::Error),
+
+    #[error("amount must be greater than zero")]
+    InvalidAmountValue,
+
+    #[error("invalid currency code")]
+    InvalidCurrency,
+
+    #[error("invalid date range")]
+    InvalidRange,
+
+    #[error("{0}")]
+    MissingExchangeRate(String),
+
+    #[error("name already existing")]
+    NameExisting,
+
+    #[error("name required")]
+    NameRequired,
+
+    #[error("name too long")]
+    NameTooLong,
+
+    #[error("cannot transfer to the same account")]
+    SameAccount,
+
+    #[error("transfer not found")]
+    TransferNotFound,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl actix_web::ResponseError for AccountError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AccountError::AccountInUse | AccountError::NameExisting => StatusCode::CONFLICT,
+            AccountError::AccountNotFound | AccountError::TransferNotFound => StatusCode::NOT_FOUND,
+            AccountError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AccountError::MissingExchangeRate(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        tracing::error!(error = ?self.to_string());
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            message: self.to_string(),
+        })
+    }
+}
+
+impl AccountError {
+    pub fn internal(e: impl Into<anyhow::Error>) -> Self {
+        AccountError::Internal(e.into())
+    }
+
+    // unique names per user, and accounts can't be dropped from under their entries
+    pub fn constraint(e: sqlx::Error) -> Self {
+        if let sqlx::Error::Database(db_err) = &e {
+            match db_err.code().as_deref() {
+                Some("23505") => return AccountError::NameExisting,
+                Some("23503") => return AccountError::AccountInUse,
+                _ => {}
+            }
+        }
+
+        AccountError::internal(e)
+    }
+
+    // for queries converting amounts between account currencies
+    pub fn conversion(e: sqlx::Error) -> Self {
+        match missing_exchange_rate(&e) {
+            Some(message) => AccountError::MissingExchangeRate(message),
+            None => AccountError::internal(e),
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use actix_web::{HttpResponse, http::StatusCode};
+use actix_web::{
+    HttpResponse,
+    http::{StatusCode, header::RETRY_AFTER},
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ValidationError {
@@ -20,6 +23,9 @@ pub enum ValidationError {
     #[error("password too long")]
     PasswordTooLong,
 
+    #[error("new password must be different")]
+    PasswordUnchanged,
+
     #[error("weak password")]
     WeakPassword,
 }
@@ -35,11 +41,53 @@ pub enum AuthError {
     #[error("invalid credentials")]
     InvalidCredentials,
 
+    #[error("account temporarily locked, try again later")]
+    AccountLocked(u64),
+
     #[error("duplicate email")]
     DuplicateEmail,
 
+    #[error("email already verified")]
+    EmailAlreadyVerified,
+
+    #[error("email not verified")]
+    EmailNotVerified,
+
+    #[error("invalid or expired token")]
+    InvalidToken,
+
+    #[error("invalid two-factor code")]
+    InvalidTwoFactorCode,
+
+    #[error("refresh token reused")]
+    RefreshTokenReused,
+
+    #[error("session not found")]
+    SessionNotFound,
+
+    #[error("token name already exists")]
+    TokenNameExisting,
+
+    #[error("token not found")]
+    TokenNotFound,
+
+    #[error("too many requests, try again later")]
+    TooManyRequests(u64),
+
+    #[error("two-factor authentication already enabled")]
+    TwoFactorAlreadyEnabled,
+
+    #[error("two-factor authentication not enabled")]
+    TwoFactorNotEnabled,
+
+    #[error("two-factor setup not started")]
+    TwoFactorNotSetUp,
+
     #[error("unauthorized")]
     Unauthorized,
+
+    #[error("verification email recently sent, try again later")]
+    VerificationRecentlySent,
 }
 
 #[derive(serde::Serialize)]
@@ -50,15 +98,33 @@ pub struct ErrorResponse {
 impl actix_web::ResponseError for AuthError {
     fn status_code(&self) -> StatusCode {
         match self {
-            AuthError::DuplicateEmail => StatusCode::CONFLICT,
-            AuthError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AuthError::DuplicateEmail
+            | AuthError::TokenNameExisting
+            | AuthError::TwoFactorAlreadyEnabled => StatusCode::CONFLICT,
+            AuthError::EmailNotVerified => StatusCode::FORBIDDEN,
+            AuthError::SessionNotFound | AuthError::TokenNotFound => StatusCode::NOT_FOUND,
+            AuthError::AccountLocked(_)
+            | AuthError::TooManyRequests(_)
+            | AuthError::VerificationRecentlySent => StatusCode::TOO_MANY_REQUESTS,
+            AuthError::InvalidTwoFactorCode
+            | AuthError::RefreshTokenReused
+            | AuthError::Unauthorized => StatusCode::UNAUTHORIZED,
             AuthError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             _ => StatusCode::BAD_REQUEST,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code()).json(ErrorResponse {
+        let mut response = HttpResponse::build(self.status_code());
+
+        // seconds until the limiter lets the client through again
+        if let AuthError::AccountLocked(retry_after) | AuthError::TooManyRequests(retry_after) =
+            self
+        {
+            response.insert_header((RETRY_AFTER, retry_after.to_string()));
+        }
+
+        response.json(ErrorResponse {
             message: self.to_string(),
         })
     }
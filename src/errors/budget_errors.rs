@@ -0,0 +1,66 @@
+use actix_web::{HttpResponse, http::StatusCode};
+
+use crate::utils::utils::missing_exchange_rate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BudgetError {
+    #[error("budget already existing")]
+    BudgetExisting,
+
+    #[error("budget not found")]
+    BudgetNotFound,
+
+    #[error("category id required")]
+    CategoryIDRequired,
+
+    #[error("foreign key not found")]
+    ForeignKeyNotFound,
+
+    #[error("internal server error")]
+    Internal(#[from] anyhow::Error),
+
+    #[error("invalid amount value")]
+    InvalidAmountValue,
+
+    #[error("{0}")]
+    MissingExchangeRate(String),
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl actix_web::ResponseError for BudgetError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            BudgetError::BudgetExisting => StatusCode::CONFLICT,
+            BudgetError::BudgetNotFound => StatusCode::NOT_FOUND,
+            BudgetError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BudgetError::MissingExchangeRate(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        tracing::error!(error = ?self.to_string());
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            message: self.to_string(),
+        })
+    }
+}
+
+impl BudgetError {
+    pub fn internal(e: impl Into<anyhow::Error>) -> Self {
+        BudgetError::Internal(e.into())
+    }
+
+    // for queries converting amounts to the base currency
+    pub fn conversion(e: sqlx::Error) -> Self {
+        match missing_exchange_rate(&e) {
+            Some(message) => BudgetError::MissingExchangeRate(message),
+            None => BudgetError::internal(e),
+        }
+    }
+}
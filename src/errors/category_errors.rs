@@ -2,6 +2,15 @@ use actix_web::{HttpResponse, http::StatusCode};
 
 #[derive(Debug, thiserror::Error)]
 pub enum CategoryError {
+    #[error("category not found")]
+    CategoryNotFound,
+
+    #[error("delete expenses or pick a category to reassign them to")]
+    DeleteTargetRequired,
+
+    #[error("description too long")]
+    DescriptionTooLong,
+
     #[error("internal server error")]
     Internal(#[from] anyhow::Error),
 
@@ -16,6 +25,9 @@ pub enum CategoryError {
 
     #[error("name too short")]
     NameTooShort,
+
+    #[error("invalid reassign category")]
+    ReassignTargetInvalid,
 }
 
 #[derive(serde::Serialize)]
@@ -26,6 +38,7 @@ pub struct ErrorResponse {
 impl actix_web::ResponseError for CategoryError {
     fn status_code(&self) -> StatusCode {
         match self {
+            CategoryError::CategoryNotFound => StatusCode::NOT_FOUND,
             CategoryError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             CategoryError::NameExisting => StatusCode::CONFLICT,
             _ => StatusCode::BAD_REQUEST,
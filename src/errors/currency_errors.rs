@@ -0,0 +1,65 @@
+use actix_web::{HttpResponse, http::StatusCode};
+
+use crate::utils::utils::missing_exchange_rate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CurrencyError {
+    #[error("internal server error")]
+    Internal(#[from] anyhow::Error),
+
+    #[error("invalid currency code")]
+    InvalidCurrency,
+
+    #[error("invalid date range")]
+    InvalidRange,
+
+    #[error("rate must be greater than zero")]
+    InvalidRate,
+
+    #[error("{0}")]
+    MissingExchangeRate(String),
+
+    #[error("exchange rate not found")]
+    RateNotFound,
+
+    #[error("currency and base must differ")]
+    SameCurrency,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl actix_web::ResponseError for CurrencyError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            CurrencyError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            CurrencyError::MissingExchangeRate(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            CurrencyError::RateNotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        tracing::error!(error = ?self.to_string());
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            message: self.to_string(),
+        })
+    }
+}
+
+impl CurrencyError {
+    pub fn internal(e: impl Into<anyhow::Error>) -> Self {
+        CurrencyError::Internal(e.into())
+    }
+
+    // for queries converting amounts to the base currency
+    pub fn conversion(e: sqlx::Error) -> Self {
+        match missing_exchange_rate(&e) {
+            Some(message) => CurrencyError::MissingExchangeRate(message),
+            None => CurrencyError::internal(e),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use actix_web::{HttpResponse, http::StatusCode};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DataExportError {
+    #[error("internal server error")]
+    Internal(#[from] anyhow::Error),
+
+    #[error("export expired, request a new one")]
+    ExportExpired,
+
+    #[error("export not found")]
+    ExportNotFound,
+
+    #[error("export not ready")]
+    ExportNotReady,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl actix_web::ResponseError for DataExportError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            DataExportError::ExportExpired => StatusCode::GONE,
+            DataExportError::ExportNotFound => StatusCode::NOT_FOUND,
+            DataExportError::ExportNotReady => StatusCode::CONFLICT,
+            DataExportError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        tracing::error!(error = ?self.to_string());
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            message: self.to_string(),
+        })
+    }
+}
+
+impl DataExportError {
+    pub fn internal(e: impl Into<anyhow::Error>) -> Self {
+        DataExportError::Internal(e.into())
+    }
+}
@@ -1,10 +1,15 @@
 use actix_web::{HttpResponse, http::StatusCode};
 
+use crate::utils::utils::missing_exchange_rate;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ExpenseError {
     #[error("category id required")]
     CategoryIDRequired,
 
+    #[error("category is in the trash, restore it first")]
+    CategoryTrashed,
+
     #[error("description required")]
     DescriptionRequired,
 
@@ -23,8 +28,29 @@ pub enum ExpenseError {
     #[error("invalid amount value")]
     InvalidAmountValue,
 
+    #[error("invalid currency code")]
+    InvalidCurrency,
+
+    #[error("invalid filter")]
+    InvalidFilter,
+
+    #[error("invalid schedule")]
+    InvalidSchedule,
+
+    #[error("{0}")]
+    MissingExchangeRate(String),
+
+    #[error("recurring expense not found")]
+    RecurringNotFound,
+
     #[error("required field missing")]
     RequiredFieldMissing,
+
+    #[error("category must be one of the split categories")]
+    SplitCategoryMissing,
+
+    #[error("split amounts must add up to the expense amount")]
+    SplitTotalMismatch,
 }
 
 #[derive(serde::Serialize)]
@@ -35,8 +61,12 @@ struct ErrorResponse {
 impl actix_web::ResponseError for ExpenseError {
     fn status_code(&self) -> StatusCode {
         match self {
-            ExpenseError::ExpenseNotFound => StatusCode::NOT_FOUND,
+            ExpenseError::ExpenseNotFound | ExpenseError::RecurringNotFound => {
+                StatusCode::NOT_FOUND
+            }
+            ExpenseError::CategoryTrashed => StatusCode::CONFLICT,
             ExpenseError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ExpenseError::MissingExchangeRate(_) => StatusCode::UNPROCESSABLE_ENTITY,
             _ => StatusCode::BAD_REQUEST,
         }
     }
@@ -54,4 +84,12 @@ impl ExpenseError {
     pub fn internal(e: impl Into<anyhow::Error>) -> Self {
         ExpenseError::Internal(e.into())
     }
+
+    // for queries converting amounts to the base currency
+    pub fn conversion(e: sqlx::Error) -> Self {
+        match missing_exchange_rate(&e) {
+            Some(message) => ExpenseError::MissingExchangeRate(message),
+            None => ExpenseError::internal(e),
+        }
+    }
 }
@@ -0,0 +1,87 @@
+use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+
+use crate::errors::expense_errors::ExpenseError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("column not found: {0}")]
+    ColumnNotFound(String),
+
+    #[error(transparent)]
+    Expense(#[from] ExpenseError),
+
+    #[error("foreign key not found")]
+    ForeignKeyNotFound,
+
+    #[error("internal server error")]
+    Internal(#[from] anyhow::Error),
+
+    #[error("invalid csv")]
+    InvalidCsv,
+
+    #[error("invalid profile")]
+    InvalidProfile,
+
+    #[error("profile name already existing")]
+    NameExisting,
+
+    #[error("profile not found")]
+    ProfileNotFound,
+
+    #[error("too many rows")]
+    TooManyRows,
+}
+
+// reported per row, a bad line never fails the whole import
+#[derive(Debug, thiserror::Error)]
+pub enum RowError {
+    #[error(transparent)]
+    Expense(#[from] ExpenseError),
+
+    #[error("invalid amount")]
+    InvalidAmount,
+
+    #[error("invalid date")]
+    InvalidDate,
+
+    #[error("missing value: {0}")]
+    MissingValue(&'static str),
+
+    #[error("not an expense")]
+    NotAnExpense,
+
+    #[error("unknown category")]
+    UnknownCategory,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl ResponseError for ImportError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ImportError::Expense(e) => e.status_code(),
+            ImportError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ImportError::NameExisting => StatusCode::CONFLICT,
+            ImportError::ProfileNotFound => StatusCode::NOT_FOUND,
+            ImportError::TooManyRows => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        tracing::error!(error = ?self.to_string());
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            message: self.to_string(),
+        })
+    }
+}
+
+impl ImportError {
+    pub fn internal(e: impl Into<anyhow::Error>) -> Self {
+        ImportError::Internal(e.into())
+    }
+}
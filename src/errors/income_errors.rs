@@ -0,0 +1,92 @@
+use actix_web::{HttpResponse, http::StatusCode};
+
+#[derive(Debug, thiserror::Error)]
+pub enum IncomeError {
+    #[error("category id required")]
+    CategoryIDRequired,
+
+    #[error("category still has income entries")]
+    CategoryInUse,
+
+    #[error("category not found")]
+    CategoryNotFound,
+
+    #[error("description required")]
+    DescriptionRequired,
+
+    #[error("description too long")]
+    DescriptionTooLong,
+
+    #[error("foreign key not found")]
+    ForeignKeyNotFound,
+
+    #[error("income not found")]
+    IncomeNotFound,
+
+    #[error("internal server error")]
+    Internal(#[from] anyhow::Error),
+
+    #[error("amount must be greater than zero")]
+    InvalidAmountValue,
+
+    #[error("invalid currency code")]
+    InvalidCurrency,
+
+    #[error("invalid date range")]
+    InvalidRange,
+
+    #[error("name already existing")]
+    NameExisting,
+
+    #[error("name required")]
+    NameRequired,
+
+    #[error("name too long")]
+    NameTooLong,
+
+    #[error("name too short")]
+    NameTooShort,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl actix_web::ResponseError for IncomeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            IncomeError::CategoryInUse | IncomeError::NameExisting => StatusCode::CONFLICT,
+            IncomeError::CategoryNotFound | IncomeError::IncomeNotFound => StatusCode::NOT_FOUND,
+            IncomeError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        tracing::error!(error = ?self.to_string());
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            message: self.to_string(),
+        })
+    }
+}
+
+impl IncomeError {
+    pub fn internal(e: impl Into<anyhow::Error>) -> Self {
+        IncomeError::Internal(e.into())
+    }
+
+    // unique names per user, and categories can't be dropped from under their entries
+    pub fn constraint(e: sqlx::Error) -> Self {
+        if let sqlx::Error::Database(db_err) = &e {
+            match db_err.code().as_deref() {
+                Some("23505") => return IncomeError::NameExisting,
+                Some("23503") => return IncomeError::CategoryInUse,
+                _ => {}
+            }
+        }
+
+        IncomeError::internal(e)
+    }
+}
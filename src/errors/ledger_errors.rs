@@ -0,0 +1,72 @@
+use actix_web::{HttpResponse, http::StatusCode};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LedgerError {
+    #[error("already a member of this ledger")]
+    AlreadyMember,
+
+    #[error("not allowed for your role in this ledger")]
+    Forbidden,
+
+    #[error("internal server error")]
+    Internal(#[from] anyhow::Error),
+
+    #[error("invalid email")]
+    InvalidEmail,
+
+    #[error("this address already has a pending invitation")]
+    InvitationExisting,
+
+    #[error("invitation not found")]
+    InvitationNotFound,
+
+    #[error("a ledger needs at least one owner")]
+    LastOwner,
+
+    #[error("ledger not found")]
+    LedgerNotFound,
+
+    #[error("member not found")]
+    MemberNotFound,
+
+    #[error("name required")]
+    NameRequired,
+
+    #[error("name too long")]
+    NameTooLong,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl actix_web::ResponseError for LedgerError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            LedgerError::AlreadyMember
+            | LedgerError::InvitationExisting
+            | LedgerError::LastOwner => StatusCode::CONFLICT,
+            LedgerError::Forbidden => StatusCode::FORBIDDEN,
+            LedgerError::InvitationNotFound
+            | LedgerError::LedgerNotFound
+            | LedgerError::MemberNotFound => StatusCode::NOT_FOUND,
+            LedgerError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        tracing::error!(error = ?self.to_string());
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            message: self.to_string(),
+        })
+    }
+}
+
+impl LedgerError {
+    pub fn internal(e: impl Into<anyhow::Error>) -> Self {
+        LedgerError::Internal(e.into())
+    }
+}
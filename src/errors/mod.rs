@@ -1,3 +1,11 @@
+pub mod account_errors;
 pub mod auth_errors;
+pub mod budget_errors;
 pub mod category_errors;
+pub mod currency_errors;
+pub mod data_export_errors;
 pub mod expense_errors;
+pub mod import_errors;
+pub mod income_errors;
+pub mod ledger_errors;
+pub mod report_errors;
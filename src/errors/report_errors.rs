@@ -0,0 +1,52 @@
+use actix_web::{HttpResponse, http::StatusCode};
+
+use crate::utils::utils::missing_exchange_rate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("internal server error")]
+    Internal(#[from] anyhow::Error),
+
+    #[error("invalid date range")]
+    InvalidRange,
+
+    #[error("{0}")]
+    MissingExchangeRate(String),
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl actix_web::ResponseError for ReportError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ReportError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ReportError::MissingExchangeRate(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        tracing::error!(error = ?self.to_string());
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            message: self.to_string(),
+        })
+    }
+}
+
+impl ReportError {
+    pub fn internal(e: impl Into<anyhow::Error>) -> Self {
+        ReportError::Internal(e.into())
+    }
+
+    // for queries converting amounts to the base currency
+    pub fn conversion(e: sqlx::Error) -> Self {
+        match missing_exchange_rate(&e) {
+            Some(message) => ReportError::MissingExchangeRate(message),
+            None => ReportError::internal(e),
+        }
+    }
+}
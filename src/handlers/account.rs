@@ -0,0 +1,117 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Data, Json, Path, Query},
+};
+
+use crate::{
+    middleware::auth::AuthMiddleware,
+    models::account_models::{
+        AccountPath, AccountRequest, LedgerParams, TransferParams, TransferPath, TransferRequest,
+    },
+    services::account_services::AccountService,
+};
+
+pub async fn add_account(
+    auth: AuthMiddleware,
+    body: Json<AccountRequest>,
+    service: Data<AccountService>,
+) -> impl Responder {
+    match service.add_account(body.into_inner(), auth.user_id).await {
+        Ok(account) => HttpResponse::Created().json(account),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_accounts(auth: AuthMiddleware, service: Data<AccountService>) -> impl Responder {
+    match service.get_accounts(auth.user_id).await {
+        Ok(accounts) => HttpResponse::Ok().json(accounts),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_account_ledger(
+    auth: AuthMiddleware,
+    path: Path<AccountPath>,
+    params: Query<LedgerParams>,
+    service: Data<AccountService>,
+) -> impl Responder {
+    match service
+        .get_ledger(path.into_inner(), params.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(ledger) => HttpResponse::Ok().json(ledger),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn edit_account(
+    auth: AuthMiddleware,
+    body: Json<AccountRequest>,
+    path: Path<AccountPath>,
+    service: Data<AccountService>,
+) -> impl Responder {
+    match service
+        .edit_account(body.into_inner(), path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(account) => HttpResponse::Ok().json(account),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_account(
+    auth: AuthMiddleware,
+    path: Path<AccountPath>,
+    service: Data<AccountService>,
+) -> impl Responder {
+    match service
+        .delete_account(path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Account deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn add_transfer(
+    auth: AuthMiddleware,
+    body: Json<TransferRequest>,
+    service: Data<AccountService>,
+) -> impl Responder {
+    match service.add_transfer(body.into_inner(), auth.user_id).await {
+        Ok(transfer) => HttpResponse::Created().json(transfer),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_transfers(
+    auth: AuthMiddleware,
+    params: Query<TransferParams>,
+    service: Data<AccountService>,
+) -> impl Responder {
+    match service
+        .get_transfers(params.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(transfers) => HttpResponse::Ok().json(transfers),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_transfer(
+    auth: AuthMiddleware,
+    path: Path<TransferPath>,
+    service: Data<AccountService>,
+) -> impl Responder {
+    match service
+        .delete_transfer(path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Transfer deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
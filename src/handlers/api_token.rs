@@ -0,0 +1,45 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Data, Json, Path},
+};
+use serde_json::json;
+
+use crate::{
+    middleware::auth::SessionMiddleware,
+    models::auth_models::{ApiTokenPath, ApiTokenRequest},
+    services::api_token_services::ApiTokenService,
+};
+
+pub async fn add_api_token(
+    auth: SessionMiddleware,
+    body: Json<ApiTokenRequest>,
+    service: Data<ApiTokenService>,
+) -> impl Responder {
+    match service.add_token(body.into_inner(), auth.user_id).await {
+        Ok(token) => HttpResponse::Created().json(token),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_api_tokens(
+    auth: SessionMiddleware,
+    service: Data<ApiTokenService>,
+) -> impl Responder {
+    match service.get_user_tokens(auth.user_id).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_api_token(
+    auth: SessionMiddleware,
+    path: Path<ApiTokenPath>,
+    service: Data<ApiTokenService>,
+) -> impl Responder {
+    match service.delete_token(path.into_inner(), auth.user_id).await {
+        Ok(v) => HttpResponse::Ok().json(json!({
+            "message": &format!("API token revoked: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
@@ -1,25 +1,45 @@
 use actix_web::{
     HttpRequest, HttpResponse, Responder, ResponseError,
     cookie::{Cookie, SameSite, time::Duration},
-    web::{Data, Json},
+    http::header::{CACHE_CONTROL, USER_AGENT, X_FORWARDED_FOR},
+    web::{Data, Json, Path},
 };
 use serde_json::json;
+use std::net::IpAddr;
 
 use crate::{
-    models::auth_models::{LoginRequest, RegisterRequest},
+    middleware::{
+        auth::{AuthMiddleware, SessionMiddleware, request_token},
+        rate_limit::TrustedProxies,
+    },
+    models::auth_models::{
+        ChangePasswordRequest, ClientInfo, DeleteAccountRequest, DisableTwoFactorRequest,
+        ForgotPasswordRequest, LoginOutcome, LoginRequest, RegisterRequest, ResetPasswordRequest,
+        SessionPath, TwoFactorCodeRequest, TwoFactorLoginRequest, UpdateProfileRequest,
+        VerifyEmailRequest,
+    },
     services::{
-        auth_services::AuthService, jwt_services::JwtService, redis_services::RedisService,
+        auth_services::AuthService, jwt_services::JwtService, mailer_services::MailerService,
+        redis_services::RedisService,
     },
 };
 
 pub async fn register(
+    req: HttpRequest,
     new_user_body: Json<RegisterRequest>,
     auth: Data<AuthService>,
     jwt: Data<JwtService>,
+    mailer: Data<MailerService>,
     redis: Data<RedisService>,
 ) -> impl Responder {
     let response = match auth
-        .register(new_user_body.into_inner(), &jwt, &redis)
+        .register(
+            new_user_body.into_inner(),
+            client_info(&req),
+            &jwt,
+            &mailer,
+            &redis,
+        )
         .await
     {
         Ok(user) => user,
@@ -35,12 +55,45 @@ pub async fn register(
 }
 
 pub async fn login(
+    req: HttpRequest,
     user: Json<LoginRequest>,
     auth: Data<AuthService>,
     jwt: Data<JwtService>,
     redis: Data<RedisService>,
 ) -> impl Responder {
-    let response = match auth.login(user.into_inner(), &jwt, &redis).await {
+    let response = match auth
+        .login(user.into_inner(), client_info(&req), &jwt, &redis)
+        .await
+    {
+        Ok(LoginOutcome::Authenticated(u)) => u,
+        Ok(LoginOutcome::ChallengeRequired(challenge_token)) => {
+            return HttpResponse::Ok().json(json!({
+                "two_factor_required": true,
+                "challenge_token": challenge_token
+            }));
+        }
+        Err(e) => return e.error_response(),
+    };
+
+    HttpResponse::Ok()
+        .cookie(set_cookie_token(response.token))
+        .cookie(set_cookie_refresh_token(response.refresh_token))
+        .json(json!({
+            "email": response.email,
+        }))
+}
+
+pub async fn login_two_factor(
+    req: HttpRequest,
+    body: Json<TwoFactorLoginRequest>,
+    auth: Data<AuthService>,
+    jwt: Data<JwtService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    let response = match auth
+        .login_two_factor(body.into_inner(), client_info(&req), &jwt, &redis)
+        .await
+    {
         Ok(u) => u,
         Err(e) => return e.error_response(),
     };
@@ -68,7 +121,10 @@ pub async fn refresh(
         }
     };
 
-    let response = match auth.refresh(cookie.value(), &jwt, &redis).await {
+    let response = match auth
+        .refresh(cookie.value(), client_info(&req), &jwt, &redis)
+        .await
+    {
         Ok(u) => u,
         Err(e) => return e.error_response(),
     };
@@ -81,6 +137,299 @@ pub async fn refresh(
         }))
 }
 
+pub async fn logout(
+    req: HttpRequest,
+    auth: Data<AuthService>,
+    jwt: Data<JwtService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    // bearer clients log out too, their access token has to be denied as well
+    let access_token = request_token(&req);
+    let refresh_token = req.cookie("refresh_token");
+
+    if let Err(e) = auth
+        .logout(
+            access_token.as_deref(),
+            refresh_token.as_ref().map(|c| c.value()),
+            &jwt,
+            &redis,
+        )
+        .await
+    {
+        return e.error_response();
+    }
+
+    HttpResponse::Ok()
+        .cookie(clear_cookie_token())
+        .cookie(clear_cookie_refresh_token())
+        .json(json!({
+            "message": "Logged out."
+        }))
+}
+
+pub async fn logout_all(
+    auth_user: SessionMiddleware,
+    auth: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    let revoked = match auth.logout_all(auth_user.user_id, &redis).await {
+        Ok(r) => r,
+        Err(e) => return e.error_response(),
+    };
+
+    HttpResponse::Ok()
+        .cookie(clear_cookie_token())
+        .cookie(clear_cookie_refresh_token())
+        .json(json!({
+            "message": "Logged out everywhere.",
+            "sessions_revoked": revoked
+        }))
+}
+
+pub async fn get_sessions(
+    auth_user: SessionMiddleware,
+    auth: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    match auth
+        .get_sessions(auth_user.user_id, &auth_user.session_id, &redis)
+        .await
+    {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn revoke_session(
+    auth_user: SessionMiddleware,
+    path: Path<SessionPath>,
+    auth: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    let path = path.into_inner();
+    let session_id = path.session_id.clone();
+
+    match auth.revoke_session(auth_user.user_id, path, &redis).await {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "message": &format!("Session revoked: {session_id}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_profile(auth_user: AuthMiddleware, auth: Data<AuthService>) -> impl Responder {
+    match auth.get_profile(auth_user.user_id).await {
+        Ok(profile) => HttpResponse::Ok().json(profile),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn update_profile(
+    auth_user: SessionMiddleware,
+    body: Json<UpdateProfileRequest>,
+    auth: Data<AuthService>,
+    jwt: Data<JwtService>,
+    mailer: Data<MailerService>,
+) -> impl Responder {
+    match auth
+        .update_profile(auth_user.user_id, body.into_inner(), &jwt, &mailer)
+        .await
+    {
+        Ok(profile) => HttpResponse::Ok().json(profile),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_account(
+    auth_user: SessionMiddleware,
+    body: Json<DeleteAccountRequest>,
+    auth: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    if let Err(e) = auth
+        .delete_account(auth_user.user_id, body.into_inner(), &redis)
+        .await
+    {
+        return e.error_response();
+    }
+
+    HttpResponse::Ok()
+        .cookie(clear_cookie_token())
+        .cookie(clear_cookie_refresh_token())
+        .json(json!({
+            "message": "Account deleted."
+        }))
+}
+
+pub async fn verify_email(
+    body: Json<VerifyEmailRequest>,
+    auth: Data<AuthService>,
+    jwt: Data<JwtService>,
+) -> impl Responder {
+    match auth.verify_email(body.into_inner(), &jwt).await {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "message": "Email verified."
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn resend_verification(
+    auth_user: SessionMiddleware,
+    auth: Data<AuthService>,
+    jwt: Data<JwtService>,
+    mailer: Data<MailerService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    match auth
+        .resend_verification(auth_user.user_id, &jwt, &mailer, &redis)
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "message": "Verification email sent."
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn setup_two_factor(
+    auth_user: SessionMiddleware,
+    auth: Data<AuthService>,
+) -> impl Responder {
+    match auth.setup_two_factor(auth_user.user_id).await {
+        Ok(setup) => HttpResponse::Ok().json(setup),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn confirm_two_factor(
+    auth_user: SessionMiddleware,
+    body: Json<TwoFactorCodeRequest>,
+    auth: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    match auth
+        .confirm_two_factor(auth_user.user_id, body.into_inner(), &redis)
+        .await
+    {
+        Ok(codes) => HttpResponse::Ok().json(codes),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn disable_two_factor(
+    auth_user: SessionMiddleware,
+    body: Json<DisableTwoFactorRequest>,
+    auth: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    match auth
+        .disable_two_factor(auth_user.user_id, body.into_inner(), &redis)
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "message": "Two-factor authentication disabled."
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn change_password(
+    auth_user: SessionMiddleware,
+    body: Json<ChangePasswordRequest>,
+    auth: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    if let Err(e) = auth
+        .change_password(auth_user.user_id, body.into_inner(), &redis)
+        .await
+    {
+        return e.error_response();
+    }
+
+    HttpResponse::Ok()
+        .cookie(clear_cookie_token())
+        .cookie(clear_cookie_refresh_token())
+        .json(json!({
+            "message": "Password changed, log in again."
+        }))
+}
+
+pub async fn forgot_password(
+    body: Json<ForgotPasswordRequest>,
+    auth: Data<AuthService>,
+    mailer: Data<MailerService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    match auth
+        .forgot_password(body.into_inner(), &mailer, &redis)
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "message": "If the email is registered, a reset link was sent."
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn reset_password(
+    body: Json<ResetPasswordRequest>,
+    auth: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    match auth.reset_password(body.into_inner(), &redis).await {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "message": "Password reset, log in with the new password."
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn jwks(jwt: Data<JwtService>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "public, max-age=300"))
+        .json(jwt.jwks())
+}
+
+fn client_info(req: &HttpRequest) -> ClientInfo {
+    ClientInfo {
+        user_agent: req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.chars().take(255).collect()),
+        ip: client_ip(req).map(|ip| ip.to_string()),
+    }
+}
+
+// the forwarded header is client input unless the connection comes from one of our proxies,
+// then the rightmost address that isn't a proxy is the one that connected to them
+fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+
+    let Some(proxies) = req.app_data::<Data<TrustedProxies>>() else {
+        return Some(peer);
+    };
+
+    if !proxies.contains(&peer) {
+        return Some(peer);
+    }
+
+    let forwarded = req
+        .headers()
+        .get(X_FORWARDED_FOR)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    let client = forwarded
+        .rsplit(',')
+        .map_while(|v| v.trim().parse::<IpAddr>().ok())
+        .find(|ip| !proxies.contains(ip))
+        .unwrap_or(peer);
+
+    Some(client)
+}
+
 fn set_cookie_token<'l>(token: String) -> Cookie<'l> {
     Cookie::build("token", token)
         .http_only(true)
@@ -95,9 +444,22 @@ fn set_cookie_token<'l>(token: String) -> Cookie<'l> {
 fn set_cookie_refresh_token<'l>(refresh_token: String) -> Cookie<'l> {
     Cookie::build("refresh_token", refresh_token)
         .http_only(true)
-        .path("/api/user/refresh")
+        // logout needs the refresh token too, not only /refresh
+        .path("/api/user")
         .same_site(SameSite::Strict)
         .secure(true)
         .max_age(Duration::days(7))
         .finish()
 }
+
+fn clear_cookie_token<'l>() -> Cookie<'l> {
+    let mut cookie = set_cookie_token(String::new());
+    cookie.make_removal();
+    cookie
+}
+
+fn clear_cookie_refresh_token<'l>() -> Cookie<'l> {
+    let mut cookie = set_cookie_refresh_token(String::new());
+    cookie.make_removal();
+    cookie
+}
@@ -0,0 +1,73 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Data, Json, Path, Query},
+};
+
+use crate::{
+    middleware::auth::LedgerMiddleware,
+    models::budget_models::{BudgetPath, BudgetPeriodParams, BudgetRequest},
+    services::{budget_services::BudgetService, redis_services::RedisService},
+};
+
+pub async fn set_budget(
+    auth: LedgerMiddleware,
+    body: Json<BudgetRequest>,
+    redis: Data<RedisService>,
+    service: Data<BudgetService>,
+) -> impl Responder {
+    match service
+        .set_budget(body.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(budget) => HttpResponse::Created().json(budget),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_budget_status(
+    auth: LedgerMiddleware,
+    params: Query<BudgetPeriodParams>,
+    redis: Data<RedisService>,
+    service: Data<BudgetService>,
+) -> impl Responder {
+    match service
+        .get_budget_status(params.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(status) => HttpResponse::Ok().json(status),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn edit_budget(
+    auth: LedgerMiddleware,
+    body: Json<BudgetRequest>,
+    path: Path<BudgetPath>,
+    redis: Data<RedisService>,
+    service: Data<BudgetService>,
+) -> impl Responder {
+    match service
+        .edit_budget(body.into_inner(), path.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(budget) => HttpResponse::Ok().json(budget),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_budget(
+    auth: LedgerMiddleware,
+    path: Path<BudgetPath>,
+    redis: Data<RedisService>,
+    service: Data<BudgetService>,
+) -> impl Responder {
+    match service
+        .delete_budget(path.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Budget deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
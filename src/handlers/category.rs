@@ -1,22 +1,22 @@
 use actix_web::{
     HttpResponse, Responder, ResponseError,
-    web::{Data, Json, Query},
+    web::{Data, Json, Path, Query},
 };
 
 use crate::{
-    middleware::auth::AuthMiddleware,
-    models::category_models::{Category, CategoryPagination},
+    middleware::auth::LedgerMiddleware,
+    models::category_models::{Category, CategoryPagination, CategoryPath, DeleteCategoryParams},
     services::{category_services::CategoryService, redis_services::RedisService},
 };
 
 pub async fn add_category(
-    auth: AuthMiddleware,
+    auth: LedgerMiddleware,
     body: Json<Category>,
     redis: Data<RedisService>,
     service: Data<CategoryService>,
 ) -> impl Responder {
     match service
-        .add_category(body.into_inner(), &redis, auth.user_id)
+        .add_category(body.into_inner(), &redis, &auth.access)
         .await
     {
         Ok(category) => HttpResponse::Created().json(category),
@@ -25,16 +25,92 @@ pub async fn add_category(
 }
 
 pub async fn get_user_categories(
-    auth: AuthMiddleware,
+    auth: LedgerMiddleware,
     params: Query<CategoryPagination>,
     redis: Data<RedisService>,
     service: Data<CategoryService>,
 ) -> impl Responder {
     match service
-        .get_user_categories(params.into_inner(), &redis, auth.user_id)
+        .get_user_categories(params.into_inner(), &redis, &auth.access)
         .await
     {
         Ok(category) => HttpResponse::Ok().json(category),
         Err(e) => e.error_response(),
     }
 }
+
+pub async fn get_single_category(
+    auth: LedgerMiddleware,
+    path: Path<CategoryPath>,
+    redis: Data<RedisService>,
+    service: Data<CategoryService>,
+) -> impl Responder {
+    match service
+        .get_single_category(path.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(category) => HttpResponse::Ok().json(category),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn edit_category(
+    auth: LedgerMiddleware,
+    body: Json<Category>,
+    path: Path<CategoryPath>,
+    redis: Data<RedisService>,
+    service: Data<CategoryService>,
+) -> impl Responder {
+    match service
+        .edit_category(body.into_inner(), path.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(category) => HttpResponse::Ok().json(category),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_category(
+    auth: LedgerMiddleware,
+    params: Query<DeleteCategoryParams>,
+    path: Path<CategoryPath>,
+    redis: Data<RedisService>,
+    service: Data<CategoryService>,
+) -> impl Responder {
+    match service
+        .delete_category(params.into_inner(), path.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(deleted) => HttpResponse::Ok().json(deleted),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_trashed_categories(
+    auth: LedgerMiddleware,
+    params: Query<CategoryPagination>,
+    service: Data<CategoryService>,
+) -> impl Responder {
+    match service
+        .get_trashed_categories(params.into_inner(), &auth.access)
+        .await
+    {
+        Ok(categories) => HttpResponse::Ok().json(categories),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn restore_category(
+    auth: LedgerMiddleware,
+    path: Path<CategoryPath>,
+    redis: Data<RedisService>,
+    service: Data<CategoryService>,
+) -> impl Responder {
+    match service
+        .restore_category(path.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(restored) => HttpResponse::Ok().json(restored),
+        Err(e) => e.error_response(),
+    }
+}
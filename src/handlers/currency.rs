@@ -0,0 +1,70 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Data, Json, Path, Query},
+};
+
+use crate::{
+    middleware::auth::AuthMiddleware,
+    models::currency_models::{
+        BaseCurrencyRequest, ExchangeRateParams, ExchangeRatePath, ManualRateRequest,
+    },
+    services::{currency_services::CurrencyService, redis_services::RedisService},
+};
+
+pub async fn set_base_currency(
+    auth: AuthMiddleware,
+    body: Json<BaseCurrencyRequest>,
+    redis: Data<RedisService>,
+    service: Data<CurrencyService>,
+) -> impl Responder {
+    match service
+        .set_base_currency(body.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(base) => HttpResponse::Ok().json(base),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn add_manual_rate(
+    auth: AuthMiddleware,
+    body: Json<ManualRateRequest>,
+    redis: Data<RedisService>,
+    service: Data<CurrencyService>,
+) -> impl Responder {
+    match service
+        .add_manual_rate(body.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(rate) => HttpResponse::Created().json(rate),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_rates(
+    auth: AuthMiddleware,
+    params: Query<ExchangeRateParams>,
+    service: Data<CurrencyService>,
+) -> impl Responder {
+    match service.get_rates(params.into_inner(), auth.user_id).await {
+        Ok(rates) => HttpResponse::Ok().json(rates),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_rate(
+    auth: AuthMiddleware,
+    path: Path<ExchangeRatePath>,
+    redis: Data<RedisService>,
+    service: Data<CurrencyService>,
+) -> impl Responder {
+    match service
+        .delete_rate(path.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Exchange rate deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,61 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{Data, Path},
+};
+
+use crate::{
+    middleware::auth::{LedgerMiddleware, SessionMiddleware},
+    models::data_export_models::DataExportPath,
+    services::{
+        auth_services::AuthService, data_export_services::DataExportService,
+        redis_services::RedisService,
+    },
+};
+
+pub async fn request_data_export(
+    _session: SessionMiddleware,
+    auth: LedgerMiddleware,
+    service: Data<DataExportService>,
+    auth_service: Data<AuthService>,
+    redis: Data<RedisService>,
+) -> impl Responder {
+    match service
+        .request_export(&auth_service, &redis, &auth.access)
+        .await
+    {
+        Ok(export) => HttpResponse::Accepted().json(export),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_data_export(
+    auth: SessionMiddleware,
+    path: Path<DataExportPath>,
+    service: Data<DataExportService>,
+) -> impl Responder {
+    match service.get_export(path.into_inner(), auth.user_id).await {
+        Ok(export) => HttpResponse::Ok().json(export),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn download_data_export(
+    auth: SessionMiddleware,
+    path: Path<DataExportPath>,
+    service: Data<DataExportService>,
+) -> impl Responder {
+    match service
+        .download_export(path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(archive) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename("data-export.zip".to_owned())],
+            })
+            .body(archive),
+        Err(e) => e.error_response(),
+    }
+}
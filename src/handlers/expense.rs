@@ -1,22 +1,30 @@
 use crate::{
-    middleware::auth::AuthMiddleware,
-    models::expense_model::{CategoryIdPath, ExpensePath, ExpenseRequest, PageParams},
-    services::{expense_services::ExpenseServices, redis_services::RedisService},
+    middleware::auth::{LedgerMiddleware, VerifiedMiddleware},
+    models::expense_model::{
+        CategoryIdPath, ExpenseFilterParams, ExpensePath, ExpenseRequest, ExportFormat,
+        ExportParams, TrashPagination,
+    },
+    services::{
+        expense_services::ExpenseServices, export_services::ExportService,
+        redis_services::RedisService,
+    },
 };
 
 use actix_web::{
     HttpResponse, Responder, ResponseError,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
     web::{Data, Json, Path, Query},
 };
 
 pub async fn add_expense(
-    auth: AuthMiddleware,
+    _verified: VerifiedMiddleware,
+    auth: LedgerMiddleware,
     body: Json<ExpenseRequest>,
     redis: Data<RedisService>,
     service: Data<ExpenseServices>,
 ) -> impl Responder {
     let service = match service
-        .add_expense(body.into_inner(), &redis, auth.user_id)
+        .add_expense(body.into_inner(), &redis, &auth.access)
         .await
     {
         Ok(s) => s,
@@ -27,13 +35,13 @@ pub async fn add_expense(
 }
 
 pub async fn get_user_expenses(
-    auth: AuthMiddleware,
-    params: Query<PageParams>,
+    auth: LedgerMiddleware,
+    params: Query<ExpenseFilterParams>,
     redis: Data<RedisService>,
     service: Data<ExpenseServices>,
 ) -> impl Responder {
     let expenses_with_total = match service
-        .get_user_expenses(params.into_inner(), &redis, auth.user_id)
+        .get_user_expenses(params.into_inner(), &redis, &auth.access)
         .await
     {
         Ok(e) => e,
@@ -44,13 +52,13 @@ pub async fn get_user_expenses(
 }
 
 pub async fn get_single_expense_per_user(
-    auth: AuthMiddleware,
+    auth: LedgerMiddleware,
     params: Path<ExpensePath>,
     service: Data<ExpenseServices>,
     redis: Data<RedisService>,
 ) -> impl Responder {
     match service
-        .get_single_expense_per_user(params.into_inner(), &redis, auth.user_id)
+        .get_single_expense_per_user(params.into_inner(), &redis, &auth.access)
         .await
     {
         Ok(expense) => HttpResponse::Ok().json(expense),
@@ -59,14 +67,14 @@ pub async fn get_single_expense_per_user(
 }
 
 pub async fn edit_expense_per_user(
-    auth: AuthMiddleware,
+    auth: LedgerMiddleware,
     body: Json<ExpenseRequest>,
     path: Path<ExpensePath>,
     redis: Data<RedisService>,
     service: Data<ExpenseServices>,
 ) -> impl Responder {
     match service
-        .edit_expense_per_user(body.into_inner(), path.into_inner(), &redis, auth.user_id)
+        .edit_expense_per_user(body.into_inner(), path.into_inner(), &redis, &auth.access)
         .await
     {
         Ok(expense) => HttpResponse::Ok().json(expense),
@@ -75,13 +83,13 @@ pub async fn edit_expense_per_user(
 }
 
 pub async fn delete_expense_per_user(
-    auth: AuthMiddleware,
+    auth: LedgerMiddleware,
     path: Path<ExpensePath>,
     redis: Data<RedisService>,
     service: Data<ExpenseServices>,
 ) -> impl Responder {
     match service
-        .delete_expense_per_user(path.into_inner(), &redis, auth.user_id)
+        .delete_expense_per_user(path.into_inner(), &redis, &auth.access)
         .await
     {
         Ok(v) => HttpResponse::Ok().json(serde_json::json!({
@@ -91,13 +99,56 @@ pub async fn delete_expense_per_user(
     }
 }
 
+pub async fn get_expense_history(
+    auth: LedgerMiddleware,
+    path: Path<ExpensePath>,
+    service: Data<ExpenseServices>,
+) -> impl Responder {
+    match service
+        .get_expense_history(path.into_inner(), &auth.access)
+        .await
+    {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_trashed_expenses(
+    auth: LedgerMiddleware,
+    params: Query<TrashPagination>,
+    service: Data<ExpenseServices>,
+) -> impl Responder {
+    match service
+        .get_trashed_expenses(params.into_inner(), &auth.access)
+        .await
+    {
+        Ok(expenses) => HttpResponse::Ok().json(expenses),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn restore_expense_per_user(
+    auth: LedgerMiddleware,
+    path: Path<ExpensePath>,
+    redis: Data<RedisService>,
+    service: Data<ExpenseServices>,
+) -> impl Responder {
+    match service
+        .restore_expense_per_user(path.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(expense) => HttpResponse::Ok().json(expense),
+        Err(e) => e.error_response(),
+    }
+}
+
 pub async fn get_total_of_all_expenses(
-    auth: AuthMiddleware,
+    auth: LedgerMiddleware,
     redis: Data<RedisService>,
     service: Data<ExpenseServices>,
 ) -> impl Responder {
     match service
-        .get_total_of_all_expenses(&redis, auth.user_id)
+        .get_total_of_all_expenses(&redis, &auth.access)
         .await
     {
         Ok(v) => HttpResponse::Ok().json(serde_json::json!({
@@ -108,8 +159,8 @@ pub async fn get_total_of_all_expenses(
 }
 
 pub async fn filter_expense_by_category_per_user(
-    auth: AuthMiddleware,
-    params: Query<PageParams>,
+    auth: LedgerMiddleware,
+    params: Query<ExpenseFilterParams>,
     path: Path<CategoryIdPath>,
     redis: Data<RedisService>,
     service: Data<ExpenseServices>,
@@ -119,7 +170,7 @@ pub async fn filter_expense_by_category_per_user(
             params.into_inner(),
             path.into_inner(),
             &redis,
-            auth.user_id,
+            &auth.access,
         )
         .await
     {
@@ -127,3 +178,38 @@ pub async fn filter_expense_by_category_per_user(
         Err(e) => e.error_response(),
     }
 }
+
+pub async fn export_expenses(
+    auth: LedgerMiddleware,
+    params: Query<ExportParams>,
+    service: Data<ExportService>,
+) -> impl Responder {
+    let params = params.into_inner();
+
+    if let Err(e) = params.filter().validate() {
+        return e.error_response();
+    }
+
+    let format = params.format;
+    let disposition = ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(format!(
+            "expenses.{}",
+            format.extension()
+        ))],
+    };
+
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(format.content_type())
+        .insert_header(disposition);
+
+    if format == ExportFormat::Xlsx {
+        return match service.export_xlsx(params, &auth.access).await {
+            Ok(file) => response.body(file),
+            Err(e) => e.error_response(),
+        };
+    }
+
+    response.streaming(service.stream_expenses(params, auth.access))
+}
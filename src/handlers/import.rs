@@ -0,0 +1,69 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Bytes, Data, Json, Path, Query},
+};
+
+use crate::{
+    middleware::auth::{AuthMiddleware, LedgerMiddleware, VerifiedMiddleware},
+    models::import_models::{ImportParams, ImportProfilePath, ImportProfileRequest},
+    services::{
+        expense_services::ExpenseServices, import_services::ImportService,
+        redis_services::RedisService,
+    },
+};
+
+pub async fn add_import_profile(
+    auth: AuthMiddleware,
+    body: Json<ImportProfileRequest>,
+    service: Data<ImportService>,
+) -> impl Responder {
+    match service.add_profile(body.into_inner(), auth.user_id).await {
+        Ok(profile) => HttpResponse::Created().json(profile),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_import_profiles(
+    auth: AuthMiddleware,
+    service: Data<ImportService>,
+) -> impl Responder {
+    match service.get_user_profiles(auth.user_id).await {
+        Ok(profiles) => HttpResponse::Ok().json(profiles),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_import_profile(
+    auth: AuthMiddleware,
+    path: Path<ImportProfilePath>,
+    service: Data<ImportService>,
+) -> impl Responder {
+    match service
+        .delete_profile(path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Import profile deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+// the body is the raw csv file, sent as text/csv
+pub async fn import_expenses(
+    _verified: VerifiedMiddleware,
+    auth: LedgerMiddleware,
+    body: Bytes,
+    params: Query<ImportParams>,
+    expenses: Data<ExpenseServices>,
+    redis: Data<RedisService>,
+    service: Data<ImportService>,
+) -> impl Responder {
+    match service
+        .import_csv(params.into_inner(), &body, &expenses, &redis, &auth.access)
+        .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => e.error_response(),
+    }
+}
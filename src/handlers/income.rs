@@ -0,0 +1,140 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Data, Json, Path, Query},
+};
+
+use crate::{
+    middleware::auth::AuthMiddleware,
+    models::income_models::{
+        IncomeCategoryPath, IncomeCategoryRequest, IncomeFilterParams, IncomePath, IncomeRequest,
+    },
+    services::{income_services::IncomeService, redis_services::RedisService},
+};
+
+pub async fn add_income_category(
+    auth: AuthMiddleware,
+    body: Json<IncomeCategoryRequest>,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service.add_category(body.into_inner(), auth.user_id).await {
+        Ok(category) => HttpResponse::Created().json(category),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_income_categories(
+    auth: AuthMiddleware,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service.get_categories(auth.user_id).await {
+        Ok(categories) => HttpResponse::Ok().json(categories),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn edit_income_category(
+    auth: AuthMiddleware,
+    body: Json<IncomeCategoryRequest>,
+    path: Path<IncomeCategoryPath>,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service
+        .edit_category(body.into_inner(), path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(category) => HttpResponse::Ok().json(category),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_income_category(
+    auth: AuthMiddleware,
+    path: Path<IncomeCategoryPath>,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service
+        .delete_category(path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Income category deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn add_income(
+    auth: AuthMiddleware,
+    body: Json<IncomeRequest>,
+    redis: Data<RedisService>,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service
+        .add_income(body.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(income) => HttpResponse::Created().json(income),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_user_income(
+    auth: AuthMiddleware,
+    params: Query<IncomeFilterParams>,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service
+        .get_user_income(params.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(income) => HttpResponse::Ok().json(income),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_single_income(
+    auth: AuthMiddleware,
+    path: Path<IncomePath>,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service
+        .get_single_income(path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(income) => HttpResponse::Ok().json(income),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn edit_income(
+    auth: AuthMiddleware,
+    body: Json<IncomeRequest>,
+    path: Path<IncomePath>,
+    redis: Data<RedisService>,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service
+        .edit_income(body.into_inner(), path.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(income) => HttpResponse::Ok().json(income),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_income(
+    auth: AuthMiddleware,
+    path: Path<IncomePath>,
+    redis: Data<RedisService>,
+    service: Data<IncomeService>,
+) -> impl Responder {
+    match service
+        .delete_income(path.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Income deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
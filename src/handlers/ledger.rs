@@ -0,0 +1,188 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Data, Json, Path},
+};
+
+use crate::{
+    middleware::auth::{AuthMiddleware, VerifiedMiddleware},
+    models::ledger_models::{
+        InvitationPath, InvitationRequest, InvitationTokenRequest, LedgerPath, LedgerRequest,
+        MemberPath, MemberRoleRequest,
+    },
+    services::{
+        ledger_services::LedgerService, mailer_services::MailerService,
+        redis_services::RedisService,
+    },
+};
+
+pub async fn add_ledger(
+    auth: AuthMiddleware,
+    body: Json<LedgerRequest>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service.add_ledger(body.into_inner(), auth.user_id).await {
+        Ok(ledger) => HttpResponse::Created().json(ledger),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_ledgers(auth: AuthMiddleware, service: Data<LedgerService>) -> impl Responder {
+    match service.get_ledgers(auth.user_id).await {
+        Ok(ledgers) => HttpResponse::Ok().json(ledgers),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn edit_ledger(
+    auth: AuthMiddleware,
+    body: Json<LedgerRequest>,
+    path: Path<LedgerPath>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .edit_ledger(body.into_inner(), path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(ledger) => HttpResponse::Ok().json(ledger),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_ledger(
+    auth: AuthMiddleware,
+    path: Path<LedgerPath>,
+    redis: Data<RedisService>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .delete_ledger(path.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Ledger deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_members(
+    auth: AuthMiddleware,
+    path: Path<LedgerPath>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service.get_members(path.into_inner(), auth.user_id).await {
+        Ok(members) => HttpResponse::Ok().json(members),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn edit_member(
+    auth: AuthMiddleware,
+    body: Json<MemberRoleRequest>,
+    path: Path<MemberPath>,
+    redis: Data<RedisService>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .edit_member(body.into_inner(), path.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(member) => HttpResponse::Ok().json(member),
+        Err(e) => e.error_response(),
+    }
+}
+
+// members leave by removing themselves
+pub async fn remove_member(
+    auth: AuthMiddleware,
+    path: Path<MemberPath>,
+    redis: Data<RedisService>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .remove_member(path.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Member removed: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn invite_member(
+    auth: AuthMiddleware,
+    body: Json<InvitationRequest>,
+    path: Path<LedgerPath>,
+    mailer: Data<MailerService>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .invite(body.into_inner(), path.into_inner(), &mailer, auth.user_id)
+        .await
+    {
+        Ok(invitation) => HttpResponse::Created().json(invitation),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_invitations(
+    auth: AuthMiddleware,
+    path: Path<LedgerPath>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .get_invitations(path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(invitations) => HttpResponse::Ok().json(invitations),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn revoke_invitation(
+    auth: AuthMiddleware,
+    path: Path<InvitationPath>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .revoke_invitation(path.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Invitation revoked: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+// the invitation goes to an address, only a verified owner of it can answer
+pub async fn accept_invitation(
+    auth: VerifiedMiddleware,
+    body: Json<InvitationTokenRequest>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .accept_invitation(body.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(ledger) => HttpResponse::Ok().json(ledger),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn decline_invitation(
+    auth: VerifiedMiddleware,
+    body: Json<InvitationTokenRequest>,
+    service: Data<LedgerService>,
+) -> impl Responder {
+    match service
+        .decline_invitation(body.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Invitation declined: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
@@ -1,3 +1,13 @@
+pub mod account;
+pub mod api_token;
 pub mod auth;
+pub mod budget;
 pub mod category;
+pub mod currency;
+pub mod data_export;
 pub mod expense;
+pub mod import;
+pub mod income;
+pub mod ledger;
+pub mod recurring;
+pub mod report;
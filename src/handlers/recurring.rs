@@ -0,0 +1,100 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Data, Json, Path, Query},
+};
+
+use crate::{
+    middleware::auth::{AuthMiddleware, VerifiedMiddleware},
+    models::recurring_models::{
+        RecurringPagination, RecurringPath, RecurringRequest, UpcomingParams,
+    },
+    services::{
+        expense_services::ExpenseServices, recurring_services::RecurringService,
+        redis_services::RedisService,
+    },
+};
+
+pub async fn add_recurring(
+    auth: VerifiedMiddleware,
+    body: Json<RecurringRequest>,
+    expenses: Data<ExpenseServices>,
+    redis: Data<RedisService>,
+    service: Data<RecurringService>,
+) -> impl Responder {
+    match service
+        .add_recurring(body.into_inner(), &expenses, &redis, auth.user_id)
+        .await
+    {
+        Ok(recurring) => HttpResponse::Created().json(recurring),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn edit_recurring(
+    auth: VerifiedMiddleware,
+    body: Json<RecurringRequest>,
+    path: Path<RecurringPath>,
+    expenses: Data<ExpenseServices>,
+    redis: Data<RedisService>,
+    service: Data<RecurringService>,
+) -> impl Responder {
+    match service
+        .edit_recurring(
+            body.into_inner(),
+            path.into_inner(),
+            &expenses,
+            &redis,
+            auth.user_id,
+        )
+        .await
+    {
+        Ok(recurring) => HttpResponse::Ok().json(recurring),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_user_recurring(
+    auth: AuthMiddleware,
+    params: Query<RecurringPagination>,
+    redis: Data<RedisService>,
+    service: Data<RecurringService>,
+) -> impl Responder {
+    match service
+        .get_user_recurring(params.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(recurring) => HttpResponse::Ok().json(recurring),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_recurring(
+    auth: AuthMiddleware,
+    path: Path<RecurringPath>,
+    redis: Data<RedisService>,
+    service: Data<RecurringService>,
+) -> impl Responder {
+    match service
+        .delete_recurring(path.into_inner(), &redis, auth.user_id)
+        .await
+    {
+        Ok(v) => HttpResponse::Ok().json(serde_json::json!({
+            "message": &format!("Recurring expense deleted: {v}")
+        })),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_upcoming(
+    auth: AuthMiddleware,
+    params: Query<UpcomingParams>,
+    service: Data<RecurringService>,
+) -> impl Responder {
+    match service
+        .get_upcoming(params.into_inner(), auth.user_id)
+        .await
+    {
+        Ok(upcoming) => HttpResponse::Ok().json(upcoming),
+        Err(e) => e.error_response(),
+    }
+}
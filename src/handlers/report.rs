@@ -0,0 +1,40 @@
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{Data, Query},
+};
+
+use crate::{
+    middleware::auth::LedgerMiddleware,
+    models::report_models::ReportParams,
+    services::{redis_services::RedisService, report_services::ReportService},
+};
+
+pub async fn get_spending_report(
+    auth: LedgerMiddleware,
+    params: Query<ReportParams>,
+    redis: Data<RedisService>,
+    service: Data<ReportService>,
+) -> impl Responder {
+    match service
+        .get_spending_report(params.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_cash_flow(
+    auth: LedgerMiddleware,
+    params: Query<ReportParams>,
+    redis: Data<RedisService>,
+    service: Data<ReportService>,
+) -> impl Responder {
+    match service
+        .get_cash_flow(params.into_inner(), &redis, &auth.access)
+        .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => e.error_response(),
+    }
+}
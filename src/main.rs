@@ -10,16 +10,27 @@ use actix_web::{App, HttpServer, Responder, get, web::Data};
 use sqlx::postgres::PgPoolOptions;
 use std::env::var;
 use std::io::Result;
+use std::time::Duration;
 
 use tracing::info;
 use tracing_actix_web::TracingLogger;
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
-    routes::{auth_routes, category_routes, expense_routes},
+    middleware::rate_limit::{RateLimitConfig, TrustedProxies},
+    routes::{
+        account_routes, auth_routes, budget_routes, category_routes, currency_routes,
+        expense_routes, income_routes, ledger_routes, report_routes,
+    },
     services::{
-        auth_services::AuthService, category_services::CategoryService,
-        expense_services::ExpenseServices, jwt_services::JwtService, redis_services::RedisService,
+        account_services::AccountService, api_token_services::ApiTokenService,
+        auth_services::AuthService, budget_services::BudgetService,
+        category_services::CategoryService, currency_services::CurrencyService,
+        data_export_services::DataExportService, expense_services::ExpenseServices,
+        export_services::ExportService, import_services::ImportService,
+        income_services::IncomeService, jwt_services::JwtService, ledger_services::LedgerService,
+        mailer_services::MailerService, recurring_services::RecurringService,
+        redis_services::RedisService, report_services::ReportService, trash_services::TrashService,
     },
 };
 
@@ -40,8 +51,49 @@ async fn main() -> Result<()> {
     info!("Starting server...");
 
     let database_url = var("DATABASE_URL").expect("DATABASE_URL must be set.");
-    let jwt_secret = var("JWT_SECRET").expect("JWT_SECRET must be set.");
     let redis_url = var("REDIS_URL").expect("REDIS_URL must be set");
+    let recurring_interval = var("RECURRING_WORKER_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60 * 60);
+    let app_url = var("APP_URL").unwrap_or_else(|_| "http://127.0.0.1:3000".to_owned());
+    let rate_limit_config = RateLimitConfig {
+        limit: var("USER_RATE_LIMIT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(120),
+        window_secs: var("USER_RATE_LIMIT_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60),
+    };
+    // comma separated, X-Forwarded-For is ignored unless the peer is one of these
+    let trusted_proxies = TrustedProxies(
+        var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse()
+                    .expect("TRUSTED_PROXIES must be a list of IP addresses.")
+            })
+            .collect(),
+    );
+    let require_verified_email = var("REQUIRE_VERIFIED_EMAIL")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    let mail_from = var("MAIL_FROM").unwrap_or_else(|_| "no-reply@localhost".to_owned());
+    let data_export_dir = var("DATA_EXPORT_DIR").unwrap_or_else(|_| "exports".to_owned());
+    let trash_retention_days = var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    let exchange_rates_file = var("EXCHANGE_RATES_FILE").ok();
+    let exchange_rates_reload_secs = var("EXCHANGE_RATES_RELOAD_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(24 * 60 * 60);
 
     let pool = PgPoolOptions::new()
         .max_connections(5)
@@ -50,25 +102,92 @@ async fn main() -> Result<()> {
         .expect("Failed to create pool");
 
     // services
-    let auth_service = AuthService::new(pool.clone());
+    let account_service = AccountService::new(pool.clone());
+    let api_token_service = ApiTokenService::new(pool.clone());
+    let auth_service = AuthService::new(pool.clone(), require_verified_email);
+    let budget_service = BudgetService::new(pool.clone());
     let category_service = CategoryService::new(pool.clone());
+    let currency_service = CurrencyService::new(pool.clone());
+    let data_export_service = DataExportService::new(pool.clone(), data_export_dir.into());
     let expense_service = ExpenseServices::new(pool.clone());
+    let export_service = ExportService::new(pool.clone());
+    let import_service = ImportService::new(pool.clone());
+    let income_service = IncomeService::new(pool.clone());
+    let ledger_service = LedgerService::new(pool.clone());
+    let recurring_service = RecurringService::new(pool.clone());
+    let report_service = ReportService::new(pool.clone());
+    let trash_service = TrashService::new(pool.clone(), trash_retention_days);
 
     // configs
-    let jwt_service = JwtService::new(jwt_secret);
+    // asymmetric keys when JWT_KEYS is set, the shared secret otherwise
+    let jwt_service = match var("JWT_KEYS") {
+        Ok(keys) => JwtService::from_pem_keys(&keys, var("JWT_SIGNING_KID").ok().as_deref())
+            .expect("Failed to load JWT_KEYS"),
+        Err(_) => JwtService::new(var("JWT_SECRET").expect("JWT_SECRET or JWT_KEYS must be set.")),
+    };
     let redis_service = RedisService::new(redis_url.as_str()).expect("Failed to connect to Redis");
+    let mailer_service = match var("MAILER").as_deref() {
+        Ok("smtp") => MailerService::smtp(
+            &var("SMTP_URL").expect("SMTP_URL must be set when MAILER=smtp."),
+            mail_from,
+            app_url,
+        )
+        .expect("Failed to create SMTP mailer"),
+        Ok("file") => MailerService::file(
+            var("MAIL_DIR").unwrap_or_else(|_| "mail".to_owned()).into(),
+            mail_from,
+            app_url,
+        ),
+        _ => MailerService::log(mail_from, app_url),
+    };
+
+    // workers
+    recurring_service.clone().spawn_worker(
+        expense_service.clone(),
+        redis_service.clone(),
+        Duration::from_secs(recurring_interval),
+    );
+    data_export_service
+        .clone()
+        .spawn_worker(Duration::from_secs(60 * 60));
+    trash_service.spawn_worker(Duration::from_secs(60 * 60));
+    if let Some(file) = exchange_rates_file {
+        currency_service
+            .clone()
+            .spawn_loader(file.into(), Duration::from_secs(exchange_rates_reload_secs));
+    }
 
     HttpServer::new(move || {
         App::new()
             .wrap(TracingLogger::default())
+            .app_data(Data::new(account_service.clone()))
+            .app_data(Data::new(api_token_service.clone()))
             .app_data(Data::new(auth_service.clone()))
+            .app_data(Data::new(budget_service.clone()))
             .app_data(Data::new(category_service.clone()))
+            .app_data(Data::new(currency_service.clone()))
+            .app_data(Data::new(data_export_service.clone()))
             .app_data(Data::new(expense_service.clone()))
+            .app_data(Data::new(export_service.clone()))
+            .app_data(Data::new(import_service.clone()))
+            .app_data(Data::new(income_service.clone()))
             .app_data(Data::new(jwt_service.clone()))
+            .app_data(Data::new(ledger_service.clone()))
+            .app_data(Data::new(mailer_service.clone()))
+            .app_data(Data::new(rate_limit_config.clone()))
+            .app_data(Data::new(recurring_service.clone()))
             .app_data(Data::new(redis_service.clone()))
+            .app_data(Data::new(report_service.clone()))
+            .app_data(Data::new(trusted_proxies.clone()))
+            .configure(account_routes::route)
             .configure(auth_routes::route)
+            .configure(budget_routes::route)
             .configure(category_routes::route)
+            .configure(currency_routes::route)
             .configure(expense_routes::route)
+            .configure(income_routes::route)
+            .configure(ledger_routes::route)
+            .configure(report_routes::route)
             .service(health)
     })
     .bind(("127.0.0.1", 3000))?
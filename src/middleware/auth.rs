@@ -1,35 +1,204 @@
-use actix_web::{Error, FromRequest, HttpRequest, dev::Payload, error::ErrorUnauthorized, web};
-use std::future::{Ready, ready};
+use actix_web::{
+    Error, FromRequest, HttpRequest,
+    dev::Payload,
+    error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+    http::header::AUTHORIZATION,
+    web,
+};
+use futures::future::LocalBoxFuture;
 
-use crate::services::jwt_services::JwtService;
+use crate::{
+    models::{
+        auth_models::ApiTokenScope,
+        ledger_models::{LedgerAccess, LedgerRole},
+    },
+    services::{
+        api_token_services::{API_TOKEN_PREFIX, ApiTokenService},
+        auth_services::AuthService,
+        jwt_services::JwtService,
+        ledger_services::LedgerService,
+        redis_services::RedisService,
+    },
+    utils::utils::{access_revoked_at_key, denied_access_token_key, session_key},
+};
 
+// session_id is None for personal API tokens
 pub struct AuthMiddleware {
     pub user_id: uuid::Uuid,
+    pub session_id: Option<String>,
+}
+
+// the Authorization header wins over the cookie, scripts don't keep cookies
+pub fn request_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_owned())
+        .or_else(|| req.cookie("token").map(|c| c.value().to_owned()))
 }
 
 impl FromRequest for AuthMiddleware {
     type Error = Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let result = || -> Result<Self, Error> {
+        let req = req.clone();
+
+        Box::pin(async move {
             let jwt = req
                 .app_data::<web::Data<JwtService>>()
                 .ok_or_else(|| ErrorUnauthorized("Jwt Service not configured."))?;
 
-            let cookie = req
-                .cookie("token")
-                .ok_or_else(|| ErrorUnauthorized("Missing token"))?;
+            let redis = req
+                .app_data::<web::Data<RedisService>>()
+                .ok_or_else(|| ErrorInternalServerError("Redis Service not configured."))?;
+
+            let token = request_token(&req).ok_or_else(|| ErrorUnauthorized("Missing token"))?;
+
+            if token.starts_with(API_TOKEN_PREFIX) {
+                let tokens = req
+                    .app_data::<web::Data<ApiTokenService>>()
+                    .ok_or_else(|| ErrorInternalServerError("API Token Service not configured."))?;
+
+                let (user_id, scope) = tokens
+                    .authenticate(&token)
+                    .await?
+                    .ok_or_else(|| ErrorUnauthorized("Invalid token"))?;
+
+                if scope == ApiTokenScope::ReadOnly && !req.method().is_safe() {
+                    return Err(ErrorForbidden("Read-only token"));
+                }
+
+                return Ok(AuthMiddleware {
+                    user_id,
+                    session_id: None,
+                });
+            }
 
             let claims = jwt
-                .validate_token(cookie.value())
+                .validate_token(&token)
                 .map_err(|_| ErrorUnauthorized("Invalid token"))?;
 
+            let (denied, revoked_at, session): (bool, Option<i64>, bool) = redis
+                .pipeline(|pipe| {
+                    pipe.exists(denied_access_token_key(claims.sub, &claims.jti))
+                        .get(access_revoked_at_key(claims.sub))
+                        .exists(session_key(claims.sub, &claims.sid));
+                })
+                .await
+                .map_err(ErrorInternalServerError)?;
+
+            if denied || !session || revoked_at.is_some_and(|at| claims.iat < at) {
+                return Err(ErrorUnauthorized("Revoked token"));
+            }
+
             Ok(AuthMiddleware {
                 user_id: claims.sub,
+                session_id: Some(claims.sid),
+            })
+        })
+    }
+}
+
+// for routes that create expenses, unverified users are turned away when configured
+pub struct VerifiedMiddleware {
+    pub user_id: uuid::Uuid,
+}
+
+impl FromRequest for VerifiedMiddleware {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let authenticated = AuthMiddleware::from_request(&req, payload);
+
+        Box::pin(async move {
+            let auth_user = authenticated.await?;
+
+            let auth = req
+                .app_data::<web::Data<AuthService>>()
+                .ok_or_else(|| ErrorInternalServerError("Auth Service not configured."))?;
+
+            auth.ensure_verified(auth_user.user_id).await?;
+
+            Ok(VerifiedMiddleware {
+                user_id: auth_user.user_id,
+            })
+        })
+    }
+}
+
+// account management needs a logged in session, personal API tokens are turned away
+pub struct SessionMiddleware {
+    pub user_id: uuid::Uuid,
+    pub session_id: String,
+}
+
+impl FromRequest for SessionMiddleware {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let authenticated = AuthMiddleware::from_request(req, payload);
+
+        Box::pin(async move {
+            let auth_user = authenticated.await?;
+
+            let session_id = auth_user
+                .session_id
+                .ok_or_else(|| ErrorForbidden("Not allowed with an API token"))?;
+
+            Ok(SessionMiddleware {
+                user_id: auth_user.user_id,
+                session_id,
             })
-        };
+        })
+    }
+}
+
+// header naming the ledger a request works on, the user's default ledger when missing
+pub const LEDGER_HEADER: &str = "X-Ledger-Id";
+
+// for expense and category routes, membership is checked and viewers only get to read
+pub struct LedgerMiddleware {
+    pub access: LedgerAccess,
+}
+
+impl FromRequest for LedgerMiddleware {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let authenticated = AuthMiddleware::from_request(&req, payload);
+
+        Box::pin(async move {
+            let auth_user = authenticated.await?;
+
+            let ledgers = req
+                .app_data::<web::Data<LedgerService>>()
+                .ok_or_else(|| ErrorInternalServerError("Ledger Service not configured."))?;
+
+            let ledger_id = req
+                .headers()
+                .get(LEDGER_HEADER)
+                .map(|v| {
+                    v.to_str()
+                        .ok()
+                        .and_then(|v| uuid::Uuid::parse_str(v.trim()).ok())
+                        .ok_or_else(|| ErrorBadRequest("Invalid ledger id"))
+                })
+                .transpose()?;
+
+            let access = ledgers.authorize(ledger_id, auth_user.user_id).await?;
+
+            if access.role == LedgerRole::Viewer && !req.method().is_safe() {
+                return Err(ErrorForbidden("Read-only ledger membership"));
+            }
 
-        ready(result())
+            Ok(LedgerMiddleware { access })
+        })
     }
 }
@@ -0,0 +1,66 @@
+use actix_web::{
+    Error,
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    middleware::Next,
+    web::Data,
+};
+use std::net::IpAddr;
+
+use crate::{
+    errors::auth_errors::AuthError,
+    middleware::auth::request_token,
+    services::api_token_services::API_TOKEN_PREFIX,
+    services::{jwt_services::JwtService, redis_services::RedisService},
+    utils::utils::{api_token_rate_limit_key, hash_token, user_rate_limit_key},
+};
+
+// limit of 0 turns the limiter off
+#[derive(Clone)]
+pub struct RateLimitConfig {
+    pub limit: u64,
+    pub window_secs: u64,
+}
+
+// addresses of the reverse proxies in front of the app, only they may set X-Forwarded-For
+#[derive(Clone, Default)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+impl TrustedProxies {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.contains(ip)
+    }
+}
+
+// keyed by the authenticated user, anonymous requests are left to the auth extractor.
+// API tokens are keyed by the token itself to avoid a database lookup per request.
+pub async fn user_rate_limit(
+    config: Data<RateLimitConfig>,
+    jwt: Data<JwtService>,
+    redis: Data<RedisService>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let key = request_token(req.request()).and_then(|token| {
+        if token.starts_with(API_TOKEN_PREFIX) {
+            return Some(api_token_rate_limit_key(&hash_token(&token)));
+        }
+
+        jwt.validate_token(&token)
+            .ok()
+            .map(|claims| user_rate_limit_key(claims.sub))
+    });
+
+    if config.limit > 0
+        && let Some(key) = key
+        && let Some(retry_after) = redis
+            .sliding_window(&key, config.limit, config.window_secs)
+            .await
+            .map_err(ErrorInternalServerError)?
+    {
+        return Err(AuthError::TooManyRequests(retry_after).into());
+    }
+
+    next.call(req).await
+}
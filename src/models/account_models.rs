@@ -0,0 +1,204 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::{errors::account_errors::AccountError, utils::utils::is_currency_code};
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "account_type", rename_all = "snake_case")]
+pub enum AccountType {
+    Cash,
+    Checking,
+    Savings,
+    CreditCard,
+    Other,
+}
+
+#[derive(Deserialize)]
+pub struct AccountRequest {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub account_type: AccountType,
+    // only read on create, defaults to the base currency and balances are kept in it
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub opening_balance: Decimal,
+}
+
+impl AccountRequest {
+    pub fn validate(&self) -> Result<(), AccountError> {
+        if self.name.trim().is_empty() {
+            return Err(AccountError::NameRequired);
+        }
+
+        if self.name.len() > 100 {
+            return Err(AccountError::NameTooLong);
+        }
+
+        if let Some(currency) = &self.currency
+            && !is_currency_code(currency)
+        {
+            return Err(AccountError::InvalidCurrency);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct AccountResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    #[serde(rename = "type")]
+    #[sqlx(rename = "type")]
+    pub account_type: AccountType,
+    pub currency: String,
+    pub opening_balance: Decimal,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct AccountBalanceResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    #[serde(rename = "type")]
+    #[sqlx(rename = "type")]
+    pub account_type: AccountType,
+    pub currency: String,
+    pub opening_balance: Decimal,
+    pub balance: Decimal,
+}
+
+#[derive(Deserialize)]
+pub struct AccountPath {
+    pub account_id: Uuid,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "account_movement_kind", rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    Expense,
+    Income,
+    TransferIn,
+    TransferOut,
+}
+
+// amount is signed and in the account currency, balance is the running total after it
+#[derive(FromRow, Serialize)]
+pub struct LedgerEntry {
+    pub kind: LedgerEntryKind,
+    pub id: Uuid,
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: Decimal,
+    pub balance: Decimal,
+}
+
+#[derive(Deserialize)]
+pub struct LedgerParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl LedgerParams {
+    pub fn validate(&self) -> Result<(), AccountError> {
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from > to
+        {
+            return Err(AccountError::InvalidRange);
+        }
+
+        Ok(())
+    }
+}
+
+// starting_balance is the balance before the first entry in range, what a statement calls
+// the previous balance
+#[derive(Serialize)]
+pub struct AccountLedger {
+    pub account: AccountResponse,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub starting_balance: Decimal,
+    pub ending_balance: Decimal,
+    pub entries: Vec<LedgerEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct TransferRequest {
+    pub from_account_id: Uuid,
+    pub to_account_id: Uuid,
+    pub amount: Decimal,
+    // what arrived, when the accounts use different currencies; converted at the
+    // date's rate when left out
+    pub to_amount: Option<Decimal>,
+    pub description: Option<String>,
+    pub date: NaiveDate,
+}
+
+impl TransferRequest {
+    pub fn validate(&self) -> Result<(), AccountError> {
+        if self.amount <= Decimal::ZERO || self.to_amount.is_some_and(|a| a <= Decimal::ZERO) {
+            return Err(AccountError::InvalidAmountValue);
+        }
+
+        if self.from_account_id == self.to_account_id {
+            return Err(AccountError::SameAccount);
+        }
+
+        if let Some(description) = &self.description
+            && description.len() > 255
+        {
+            return Err(AccountError::DescriptionTooLong);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct TransferResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub from_account_id: Uuid,
+    pub to_account_id: Uuid,
+    pub amount: Decimal,
+    pub to_amount: Decimal,
+    pub description: Option<String>,
+    pub date: NaiveDate,
+}
+
+#[derive(Deserialize)]
+pub struct TransferParams {
+    #[serde(default = "default_page")]
+    pub page: i64,
+    pub account_id: Option<Uuid>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl TransferParams {
+    pub fn validate(&self) -> Result<(), AccountError> {
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from > to
+        {
+            return Err(AccountError::InvalidRange);
+        }
+
+        Ok(())
+    }
+}
+
+fn default_page() -> i64 {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct TransferPath {
+    pub transfer_id: Uuid,
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::result::Result;
@@ -29,35 +30,50 @@ pub struct RegisterRequest {
 
 impl RegisterRequest {
     pub fn validate(&self) -> Result<(), ValidationError> {
-        if self.name.is_empty() {
-            return Err(ValidationError::NameRequired);
-        }
+        validate_name(&self.name)?;
+        validate_email(&self.email)?;
 
-        if self.name.len() < 3 {
-            return Err(ValidationError::NameTooShort);
-        }
+        validate_password(&self.password, &[self.email.as_str(), self.name.as_str()])
+    }
+}
 
-        if self.name.len() > 100 {
-            return Err(ValidationError::NameTooLong);
-        }
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.is_empty() {
+        return Err(ValidationError::NameRequired);
+    }
 
-        if !self.email.validate_email() || self.email.len() > 254 {
-            return Err(ValidationError::InvalidEmail);
-        }
+    if name.len() < 3 {
+        return Err(ValidationError::NameTooShort);
+    }
 
-        if self.password.len() > 72 {
-            return Err(ValidationError::PasswordTooLong);
-        }
+    if name.len() > 100 {
+        return Err(ValidationError::NameTooLong);
+    }
 
-        let user_inputs = &[self.email.as_str(), self.name.as_str()];
-        let estimate = zxcvbn(&self.password, user_inputs);
+    Ok(())
+}
 
-        if estimate.score() < Score::Three {
-            return Err(ValidationError::WeakPassword);
-        }
+fn validate_email(email: &str) -> Result<(), ValidationError> {
+    if !email.validate_email() || email.len() > 254 {
+        return Err(ValidationError::InvalidEmail);
+    }
 
-        Ok(())
+    Ok(())
+}
+
+// user_inputs keeps zxcvbn from accepting passwords built from the user's own details
+pub fn validate_password(password: &str, user_inputs: &[&str]) -> Result<(), ValidationError> {
+    if password.len() > 72 {
+        return Err(ValidationError::PasswordTooLong);
+    }
+
+    let estimate = zxcvbn(password, user_inputs);
+
+    if estimate.score() < Score::Three {
+        return Err(ValidationError::WeakPassword);
     }
+
+    Ok(())
 }
 
 #[derive(FromRow)]
@@ -65,6 +81,43 @@ pub struct LoginQuery {
     pub email: String,
     pub id: Uuid,
     pub password: String,
+    pub totp_enabled: bool,
+}
+
+// with 2FA enabled the password alone only earns a challenge token
+pub enum LoginOutcome {
+    Authenticated(AuthResponse),
+    ChallengeRequired(String),
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub qr_svg: String,
+}
+
+// shown once, only hashes are stored
+#[derive(Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -86,3 +139,193 @@ impl LoginRequest {
         Ok(())
     }
 }
+
+#[derive(FromRow, Serialize)]
+pub struct ProfileResponse {
+    pub id: Uuid,
+    pub email: String,
+    pub name: Option<String>,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
+    pub base_currency: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+// changing the email needs the password, it is the account's recovery channel
+#[derive(Deserialize)]
+pub struct UpdateProfileRequest {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub password: Option<String>,
+}
+
+impl UpdateProfileRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(name) = &self.name {
+            validate_name(name)?;
+        }
+
+        if let Some(email) = &self.email {
+            validate_email(email)?;
+
+            if self.password.as_deref().is_none_or(str::is_empty) {
+                return Err(ValidationError::PasswordRequired);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+#[derive(FromRow)]
+pub struct PasswordQuery {
+    pub email: String,
+    pub name: Option<String>,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+impl ChangePasswordRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.current_password.is_empty() || self.new_password.is_empty() {
+            return Err(ValidationError::PasswordRequired);
+        }
+
+        if self.current_password == self.new_password {
+            return Err(ValidationError::PasswordUnchanged);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+impl ForgotPasswordRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if !self.email.validate_email() {
+            return Err(ValidationError::InvalidEmail);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+impl ResetPasswordRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.new_password.is_empty() {
+            return Err(ValidationError::PasswordRequired);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+// stored in redis as json, a session is also the rotation family of its refresh tokens.
+// refresh_jti is the only valid token, previous_jti is kept to tell races from replays
+#[derive(Deserialize, Serialize)]
+pub struct SessionMeta {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_refresh: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub refresh_jti: String,
+    #[serde(default)]
+    pub previous_jti: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_refresh: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub current: bool,
+}
+
+#[derive(Deserialize)]
+pub struct SessionPath {
+    pub session_id: String,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "api_token_scope", rename_all = "snake_case")]
+pub enum ApiTokenScope {
+    // GET and HEAD only
+    ReadOnly,
+    ReadWrite,
+}
+
+#[derive(Deserialize)]
+pub struct ApiTokenRequest {
+    pub name: String,
+    pub scope: ApiTokenScope,
+}
+
+impl ApiTokenRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.name.trim().is_empty() {
+            return Err(ValidationError::NameRequired);
+        }
+
+        if self.name.len() > 100 {
+            return Err(ValidationError::NameTooLong);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(FromRow, Serialize)]
+pub struct ApiTokenResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub scope: ApiTokenScope,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// the plain token is only ever returned here
+#[derive(Serialize)]
+pub struct ApiTokenCreated {
+    #[serde(flatten)]
+    pub api_token: ApiTokenResponse,
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ApiTokenPath {
+    pub token_id: Uuid,
+}
@@ -0,0 +1,82 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::errors::budget_errors::BudgetError;
+
+#[derive(Deserialize)]
+pub struct BudgetRequest {
+    pub category_id: Uuid,
+    pub amount: Decimal,
+    // any day works, budgets are stored against the first day of the month
+    pub month: NaiveDate,
+    #[serde(default)]
+    pub rollover: bool,
+}
+
+impl BudgetRequest {
+    pub fn validate(&self) -> Result<(), BudgetError> {
+        if self.amount <= Decimal::ZERO {
+            return Err(BudgetError::InvalidAmountValue);
+        }
+
+        if self.category_id.is_nil() {
+            return Err(BudgetError::CategoryIDRequired);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct BudgetResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub category_id: Uuid,
+    pub month: NaiveDate,
+    pub amount: Decimal,
+    pub rollover: bool,
+}
+
+#[derive(FromRow)]
+pub struct BudgetSpentQuery {
+    pub id: Uuid,
+    pub category_id: Uuid,
+    pub category_name: String,
+    pub month: NaiveDate,
+    pub amount: Decimal,
+    pub rollover: bool,
+    pub spent: Decimal,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct BudgetStatus {
+    pub budget_id: Uuid,
+    pub category_id: Uuid,
+    pub category_name: String,
+    pub month: NaiveDate,
+    pub amount: Decimal,
+    pub rolled_over: Decimal,
+    pub available: Decimal,
+    pub spent: Decimal,
+    pub remaining: Decimal,
+    pub over_budget: bool,
+}
+
+#[derive(Serialize)]
+pub struct BudgetStatusCached {
+    pub cached: bool,
+    pub budgets: Vec<BudgetStatus>,
+}
+
+#[derive(Deserialize)]
+pub struct BudgetPeriodParams {
+    pub month: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+pub struct BudgetPath {
+    pub budget_id: Uuid,
+}
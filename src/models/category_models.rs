@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -7,6 +8,7 @@ use crate::errors::category_errors::CategoryError;
 #[derive(Deserialize)]
 pub struct Category {
     pub name: String,
+    pub description: Option<String>,
 }
 
 impl Category {
@@ -23,6 +25,12 @@ impl Category {
             return Err(CategoryError::NameTooLong);
         }
 
+        if let Some(description) = &self.description
+            && description.len() > 255
+        {
+            return Err(CategoryError::DescriptionTooLong);
+        }
+
         Ok(())
     }
 }
@@ -35,6 +43,21 @@ pub struct CategoryResponse {
     user_id: Uuid,
 }
 
+#[derive(FromRow, Serialize)]
+pub struct TrashedCategoryResponse {
+    id: Uuid,
+    description: Option<String>,
+    name: String,
+    user_id: Uuid,
+    deleted_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct CategoryRestored {
+    pub category: CategoryResponse,
+    pub expenses_restored: usize,
+}
+
 // for dev mode only
 #[derive(Serialize)]
 pub struct CategoriesCached {
@@ -42,6 +65,32 @@ pub struct CategoriesCached {
     pub categories: Vec<CategoryResponse>,
 }
 
+#[derive(Serialize)]
+pub struct CategoryCached {
+    pub cached: bool,
+    pub category: CategoryResponse,
+}
+
+#[derive(Deserialize)]
+pub struct CategoryPath {
+    pub category_id: Uuid,
+}
+
+// expenses can't stay in a trashed category, so deleting requires an explicit choice
+#[derive(Deserialize)]
+pub struct DeleteCategoryParams {
+    pub reassign_to: Option<Uuid>,
+    #[serde(default)]
+    pub delete_expenses: bool,
+}
+
+#[derive(Serialize)]
+pub struct CategoryDeleted {
+    pub id: Uuid,
+    pub reassigned_to: Option<Uuid>,
+    pub expenses_affected: usize,
+}
+
 #[derive(Deserialize)]
 pub struct CategoryPagination {
     #[serde(default = "default_page")]
@@ -0,0 +1,105 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::{errors::currency_errors::CurrencyError, utils::utils::is_currency_code};
+
+#[derive(Deserialize)]
+pub struct BaseCurrencyRequest {
+    pub currency: String,
+}
+
+impl BaseCurrencyRequest {
+    pub fn validate(&self) -> Result<(), CurrencyError> {
+        if !is_currency_code(&self.currency) {
+            return Err(CurrencyError::InvalidCurrency);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct BaseCurrencyResponse {
+    pub currency: String,
+}
+
+// rate is how many units of currency one unit of base buys on that date
+#[derive(Deserialize)]
+pub struct ManualRateRequest {
+    pub currency: String,
+    #[serde(default = "default_base")]
+    pub base: String,
+    pub date: NaiveDate,
+    pub rate: Decimal,
+}
+
+fn default_base() -> String {
+    "EUR".to_owned()
+}
+
+impl ManualRateRequest {
+    pub fn validate(&self) -> Result<(), CurrencyError> {
+        if !is_currency_code(&self.currency) || !is_currency_code(&self.base) {
+            return Err(CurrencyError::InvalidCurrency);
+        }
+
+        if self.currency == self.base {
+            return Err(CurrencyError::SameCurrency);
+        }
+
+        if self.rate <= Decimal::ZERO {
+            return Err(CurrencyError::InvalidRate);
+        }
+
+        Ok(())
+    }
+}
+
+// stored rates are always per euro, manual ones are converted on the way in
+#[derive(FromRow, Serialize)]
+pub struct ExchangeRateResponse {
+    pub id: Uuid,
+    pub currency: String,
+    pub date: NaiveDate,
+    pub rate: Decimal,
+    pub manual: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ExchangeRateParams {
+    pub currency: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    #[serde(default = "default_page")]
+    pub page: i64,
+}
+
+impl ExchangeRateParams {
+    pub fn validate(&self) -> Result<(), CurrencyError> {
+        if let Some(currency) = &self.currency
+            && !is_currency_code(currency)
+        {
+            return Err(CurrencyError::InvalidCurrency);
+        }
+
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from > to
+        {
+            return Err(CurrencyError::InvalidRange);
+        }
+
+        Ok(())
+    }
+}
+
+fn default_page() -> i64 {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct ExchangeRatePath {
+    pub rate_id: Uuid,
+}
@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "data_export_status", rename_all = "lowercase")]
+pub enum DataExportStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct DataExportResponse {
+    pub id: Uuid,
+    pub status: DataExportStatus,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct DataExportPath {
+    pub export_id: Uuid,
+}
@@ -1,20 +1,25 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use serde_json::Value;
+use sqlx::{FromRow, types::Json};
 use uuid::Uuid;
 
-use crate::errors::expense_errors::ExpenseError;
+use crate::{errors::expense_errors::ExpenseError, utils::utils::is_currency_code};
 
 #[derive(Deserialize)]
 pub struct ExpenseRequest {
     pub amount: Decimal,
+    // defaults to the user's base currency, editing without one keeps the current
+    pub currency: Option<String>,
     pub description: String,
     pub category_id: Uuid,
     pub date: NaiveDate,
-    pub payment_method: Option<String>,
+    pub account_id: Option<Uuid>,
     pub is_recurring: bool,
     pub tags: Option<Vec<String>>,
+    // when given the lines must add up to amount, and category_id must be one of theirs
+    pub splits: Option<Vec<ExpenseSplitRequest>>,
 }
 
 impl ExpenseRequest {
@@ -23,6 +28,12 @@ impl ExpenseRequest {
             return Err(ExpenseError::InvalidAmountValue);
         }
 
+        if let Some(currency) = &self.currency
+            && !is_currency_code(currency)
+        {
+            return Err(ExpenseError::InvalidCurrency);
+        }
+
         if self.description.is_empty() {
             return Err(ExpenseError::DescriptionRequired);
         }
@@ -35,22 +46,157 @@ impl ExpenseRequest {
             return Err(ExpenseError::CategoryIDRequired);
         }
 
+        validate_splits(self.split_lines(), self.amount, self.category_id)
+    }
+
+    pub fn split_lines(&self) -> &[ExpenseSplitRequest] {
+        self.splits.as_deref().unwrap_or_default()
+    }
+}
+
+// no lines is a plain expense, otherwise they must add up and include the main category
+pub fn validate_splits(
+    splits: &[ExpenseSplitRequest],
+    amount: Decimal,
+    category_id: Uuid,
+) -> Result<(), ExpenseError> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+
+    for split in splits {
+        split.validate()?;
+    }
+
+    if splits.iter().map(|s| s.amount).sum::<Decimal>() != amount {
+        return Err(ExpenseError::SplitTotalMismatch);
+    }
+
+    if !splits.iter().any(|s| s.category_id == category_id) {
+        return Err(ExpenseError::SplitCategoryMissing);
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ExpenseSplitRequest {
+    pub category_id: Uuid,
+    pub amount: Decimal,
+    pub tags: Option<Vec<String>>,
+}
+
+impl ExpenseSplitRequest {
+    pub fn validate(&self) -> Result<(), ExpenseError> {
+        if self.amount <= Decimal::ZERO {
+            return Err(ExpenseError::InvalidAmountValue);
+        }
+
+        if self.category_id.is_nil() {
+            return Err(ExpenseError::CategoryIDRequired);
+        }
+
         Ok(())
     }
 }
 
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ExpenseSplit {
+    pub category_id: Uuid,
+    pub amount: Decimal,
+    pub tags: Option<Vec<String>>,
+}
+
+impl From<&ExpenseSplitRequest> for ExpenseSplit {
+    fn from(split: &ExpenseSplitRequest) -> Self {
+        Self {
+            category_id: split.category_id,
+            amount: split.amount,
+            tags: split.tags.clone(),
+        }
+    }
+}
+
+impl From<&ExpenseSplit> for ExpenseSplitRequest {
+    fn from(split: &ExpenseSplit) -> Self {
+        Self {
+            category_id: split.category_id,
+            amount: split.amount,
+            tags: split.tags.clone(),
+        }
+    }
+}
+
 // properties are not reusable, sqlx only accepts flat structs
-#[derive(Deserialize, FromRow, Serialize)]
+#[derive(Clone, Deserialize, FromRow, Serialize)]
 pub struct ExpenseResponse {
     pub id: Uuid,
     pub amount: Decimal,
+    pub currency: String,
+    pub description: String,
+    pub user_id: Uuid,
+    pub category_id: Uuid,
+    pub date: NaiveDate,
+    pub account_id: Option<Uuid>,
+    pub is_recurring: bool,
+    pub tags: Option<Vec<String>>,
+    #[sqlx(default)]
+    pub splits: Json<Vec<ExpenseSplit>>,
+}
+
+impl ExpenseResponse {
+    // every category the expense counts against, its own first
+    pub fn category_ids(&self) -> Vec<Uuid> {
+        let mut ids = vec![self.category_id];
+
+        for split in self.splits.iter() {
+            if !ids.contains(&split.category_id) {
+                ids.push(split.category_id);
+            }
+        }
+
+        ids
+    }
+}
+
+#[derive(FromRow, Serialize)]
+pub struct TrashedExpenseResponse {
+    pub id: Uuid,
+    pub amount: Decimal,
+    pub currency: String,
     pub description: String,
     pub user_id: Uuid,
     pub category_id: Uuid,
     pub date: NaiveDate,
-    pub payment_method: Option<String>,
+    pub account_id: Option<Uuid>,
     pub is_recurring: bool,
     pub tags: Option<Vec<String>>,
+    #[sqlx(default)]
+    pub splits: Json<Vec<ExpenseSplit>>,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "expense_history_action", rename_all = "lowercase")]
+pub enum ExpenseHistoryAction {
+    Created,
+    Updated,
+    Deleted,
+    Restored,
+}
+
+// before and after hold the expense as the API returns it, before is null on
+// create and restore, after on delete
+#[derive(FromRow, Serialize)]
+pub struct ExpenseHistoryResponse {
+    pub id: Uuid,
+    pub expense_id: Uuid,
+    pub actor_id: Uuid,
+    pub action: ExpenseHistoryAction,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -65,16 +211,111 @@ pub struct ExpensesTotalCached {
     pub cached: bool,
 }
 
-#[derive(Deserialize)]
-pub struct PageParams {
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+// query strings can't carry arrays, so `tags` is a comma separated list
+#[derive(Default, Deserialize)]
+pub struct ExpenseFilterParams {
     #[serde(default = "default_page")]
     pub page: i64,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub category_id: Option<Uuid>,
+    pub tags: Option<String>,
+    #[serde(default)]
+    pub tags_match: TagMatch,
+    pub account_id: Option<Uuid>,
+    pub is_recurring: Option<bool>,
+}
+
+impl ExpenseFilterParams {
+    pub fn validate(&self) -> Result<(), ExpenseError> {
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from > to
+        {
+            return Err(ExpenseError::InvalidFilter);
+        }
+
+        if let (Some(min), Some(max)) = (self.min_amount, self.max_amount)
+            && min > max
+        {
+            return Err(ExpenseError::InvalidFilter);
+        }
+
+        Ok(())
+    }
+
+    pub fn tag_list(&self) -> Option<Vec<String>> {
+        let mut tags: Vec<String> = self
+            .tags
+            .as_deref()?
+            .split(',')
+            .map(|t| t.trim().to_owned())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        if tags.is_empty() {
+            return None;
+        }
+
+        tags.sort();
+        tags.dedup();
+
+        Some(tags)
+    }
+
+    // category_id is left out, it has its own cache version key
+    pub fn has_filters(&self) -> bool {
+        self.from.is_some()
+            || self.to.is_some()
+            || self.min_amount.is_some()
+            || self.max_amount.is_some()
+            || self.tag_list().is_some()
+            || self.account_id.is_some()
+            || self.is_recurring.is_some()
+    }
+
+    // normalized so equivalent filter sets share the same cached page
+    pub fn cache_key(&self) -> String {
+        let opt = |v: Option<String>| v.unwrap_or_default();
+
+        format!(
+            "from={};to={};min={};max={};cat={};tags={};match={};acc={};rec={}",
+            opt(self.from.map(|d| d.to_string())),
+            opt(self.to.map(|d| d.to_string())),
+            opt(self.min_amount.map(|d| d.normalize().to_string())),
+            opt(self.max_amount.map(|d| d.normalize().to_string())),
+            opt(self.category_id.map(|c| c.to_string())),
+            opt(self.tag_list().map(|t| t.join(","))),
+            if self.tags_match == TagMatch::All {
+                "all"
+            } else {
+                "any"
+            },
+            opt(self.account_id.map(|a| a.to_string())),
+            opt(self.is_recurring.map(|r| r.to_string())),
+        )
+    }
 }
 
 fn default_page() -> i64 {
     1
 }
 
+#[derive(Deserialize)]
+pub struct TrashPagination {
+    #[serde(default = "default_page")]
+    pub page: i64,
+}
+
 #[derive(Deserialize)]
 pub struct ExpensePath {
     pub expense_id: Uuid,
@@ -90,3 +331,164 @@ pub struct ExpenseCached {
     pub cached: bool,
     pub expense: ExpenseResponse,
 }
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/x-ndjson",
+            ExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "ndjson",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub category_id: Option<Uuid>,
+}
+
+impl ExportParams {
+    pub fn filter(&self) -> ExpenseFilterParams {
+        ExpenseFilterParams {
+            from: self.from,
+            to: self.to,
+            category_id: self.category_id,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(FromRow, Serialize)]
+pub struct ExportRow {
+    pub id: Uuid,
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    pub currency: String,
+    pub description: String,
+    pub category: String,
+    pub account: Option<String>,
+    pub is_recurring: bool,
+    pub tags: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(amount: i64, splits: Option<Vec<ExpenseSplitRequest>>) -> ExpenseRequest {
+        ExpenseRequest {
+            amount: Decimal::new(amount, 2),
+            currency: None,
+            description: "groceries".to_owned(),
+            category_id: Uuid::from_u128(1),
+            date: NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(),
+            account_id: None,
+            is_recurring: false,
+            tags: None,
+            splits,
+        }
+    }
+
+    fn split(category: u128, amount: i64) -> ExpenseSplitRequest {
+        ExpenseSplitRequest {
+            category_id: Uuid::from_u128(category),
+            amount: Decimal::new(amount, 2),
+            tags: None,
+        }
+    }
+
+    #[test]
+    fn accepts_an_expense_without_splits() {
+        assert!(request(1250, None).validate().is_ok());
+        assert!(request(1250, Some(Vec::new())).validate().is_ok());
+    }
+
+    #[test]
+    fn accepts_splits_that_add_up() {
+        let splits = vec![split(1, 1000), split(2, 250)];
+
+        assert!(request(1250, Some(splits)).validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_splits_that_dont_add_up() {
+        let short = vec![split(1, 1000), split(2, 249)];
+        let over = vec![split(1, 1000), split(2, 251)];
+
+        assert!(matches!(
+            request(1250, Some(short)).validate(),
+            Err(ExpenseError::SplitTotalMismatch)
+        ));
+        assert!(matches!(
+            request(1250, Some(over)).validate(),
+            Err(ExpenseError::SplitTotalMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_splits_without_the_main_category() {
+        let splits = vec![split(2, 1000), split(3, 250)];
+
+        assert!(matches!(
+            request(1250, Some(splits)).validate(),
+            Err(ExpenseError::SplitCategoryMissing)
+        ));
+    }
+
+    #[test]
+    fn rejects_non_positive_split_lines() {
+        let splits = vec![split(1, 1250), split(2, 0)];
+
+        assert!(matches!(
+            request(1250, Some(splits)).validate(),
+            Err(ExpenseError::InvalidAmountValue)
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_expense_fields() {
+        assert!(matches!(
+            request(0, None).validate(),
+            Err(ExpenseError::InvalidAmountValue)
+        ));
+
+        let mut no_description = request(1250, None);
+        no_description.description.clear();
+
+        assert!(matches!(
+            no_description.validate(),
+            Err(ExpenseError::DescriptionRequired)
+        ));
+
+        let mut bad_currency = request(1250, None);
+        bad_currency.currency = Some("euro".to_owned());
+
+        assert!(matches!(
+            bad_currency.validate(),
+            Err(ExpenseError::InvalidCurrency)
+        ));
+    }
+}
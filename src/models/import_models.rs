@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::errors::import_errors::ImportError;
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "import_sign_convention", rename_all = "snake_case")]
+pub enum SignConvention {
+    // bank style, debits are negative and credits are skipped
+    #[default]
+    NegativeExpense,
+    PositiveExpense,
+}
+
+// columns are header names, or zero based indexes when the file has no header
+#[derive(Deserialize)]
+pub struct ImportProfileRequest {
+    pub name: String,
+    pub date_column: String,
+    pub amount_column: String,
+    pub description_column: String,
+    pub category_column: Option<String>,
+    pub tags_column: Option<String>,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: String,
+    #[serde(default)]
+    pub sign_convention: SignConvention,
+    #[serde(default = "default_has_header")]
+    pub has_header: bool,
+    pub default_category_id: Option<Uuid>,
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_owned()
+}
+
+fn default_decimal_separator() -> String {
+    ".".to_owned()
+}
+
+fn default_delimiter() -> String {
+    ",".to_owned()
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+impl ImportProfileRequest {
+    pub fn validate(&self) -> Result<(), ImportError> {
+        if self.name.is_empty() || self.name.len() > 50 {
+            return Err(ImportError::InvalidProfile);
+        }
+
+        let columns = [
+            Some(&self.date_column),
+            Some(&self.amount_column),
+            Some(&self.description_column),
+            self.category_column.as_ref(),
+            self.tags_column.as_ref(),
+        ];
+
+        if columns.into_iter().flatten().any(|c| c.trim().is_empty()) {
+            return Err(ImportError::InvalidProfile);
+        }
+
+        if self.date_format.is_empty() {
+            return Err(ImportError::InvalidProfile);
+        }
+
+        if !matches!(self.decimal_separator.as_str(), "." | ",") {
+            return Err(ImportError::InvalidProfile);
+        }
+
+        if !matches!(self.delimiter.as_str(), "," | ";" | "\t" | "|") {
+            return Err(ImportError::InvalidProfile);
+        }
+
+        if self.delimiter == self.decimal_separator {
+            return Err(ImportError::InvalidProfile);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct ImportProfileResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub date_column: String,
+    pub amount_column: String,
+    pub description_column: String,
+    pub category_column: Option<String>,
+    pub tags_column: Option<String>,
+    pub date_format: String,
+    pub decimal_separator: String,
+    pub delimiter: String,
+    pub sign_convention: SignConvention,
+    pub has_header: bool,
+    pub default_category_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportParams {
+    pub profile_id: Uuid,
+    // the statement's account, every imported row is booked against it
+    pub account_id: Option<Uuid>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ImportProfilePath {
+    pub profile_id: Uuid,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Serialize)]
+pub struct ImportRowReport {
+    pub row: usize,
+    pub status: RowStatus,
+    pub expense_id: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub rows: Vec<ImportRowReport>,
+}
@@ -0,0 +1,131 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::{errors::income_errors::IncomeError, utils::utils::is_currency_code};
+
+#[derive(Deserialize)]
+pub struct IncomeCategoryRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+impl IncomeCategoryRequest {
+    pub fn validate(&self) -> Result<(), IncomeError> {
+        if self.name.is_empty() {
+            return Err(IncomeError::NameRequired);
+        }
+
+        if self.name.len() < 3 {
+            return Err(IncomeError::NameTooShort);
+        }
+
+        if self.name.len() > 30 {
+            return Err(IncomeError::NameTooLong);
+        }
+
+        if let Some(description) = &self.description
+            && description.len() > 255
+        {
+            return Err(IncomeError::DescriptionTooLong);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct IncomeCategoryResponse {
+    pub id: Uuid,
+    pub description: Option<String>,
+    pub name: String,
+    pub user_id: Uuid,
+}
+
+#[derive(Deserialize)]
+pub struct IncomeCategoryPath {
+    pub category_id: Uuid,
+}
+
+#[derive(Deserialize)]
+pub struct IncomeRequest {
+    pub amount: Decimal,
+    // defaults to the user's base currency, editing without one keeps the current
+    pub currency: Option<String>,
+    pub description: String,
+    pub category_id: Uuid,
+    pub date: NaiveDate,
+    pub account_id: Option<Uuid>,
+}
+
+impl IncomeRequest {
+    pub fn validate(&self) -> Result<(), IncomeError> {
+        if self.amount <= Decimal::ZERO {
+            return Err(IncomeError::InvalidAmountValue);
+        }
+
+        if let Some(currency) = &self.currency
+            && !is_currency_code(currency)
+        {
+            return Err(IncomeError::InvalidCurrency);
+        }
+
+        if self.description.is_empty() {
+            return Err(IncomeError::DescriptionRequired);
+        }
+
+        if self.description.len() > 255 {
+            return Err(IncomeError::DescriptionTooLong);
+        }
+
+        if self.category_id.is_nil() {
+            return Err(IncomeError::CategoryIDRequired);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct IncomeResponse {
+    pub id: Uuid,
+    pub amount: Decimal,
+    pub currency: String,
+    pub description: String,
+    pub user_id: Uuid,
+    pub category_id: Uuid,
+    pub date: NaiveDate,
+    pub account_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct IncomePath {
+    pub income_id: Uuid,
+}
+
+#[derive(Deserialize)]
+pub struct IncomeFilterParams {
+    #[serde(default = "default_page")]
+    pub page: i64,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub category_id: Option<Uuid>,
+}
+
+impl IncomeFilterParams {
+    pub fn validate(&self) -> Result<(), IncomeError> {
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from > to
+        {
+            return Err(IncomeError::InvalidRange);
+        }
+
+        Ok(())
+    }
+}
+
+fn default_page() -> i64 {
+    1
+}
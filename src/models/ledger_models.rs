@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::errors::ledger_errors::LedgerError;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "ledger_role", rename_all = "lowercase")]
+pub enum LedgerRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "ledger_invitation_status", rename_all = "lowercase")]
+pub enum InvitationStatus {
+    Pending,
+    Accepted,
+    Declined,
+}
+
+// what the ledger extractor hands to the services once membership is checked
+#[derive(Clone, Copy, FromRow)]
+pub struct LedgerAccess {
+    pub ledger_id: Uuid,
+    pub user_id: Uuid,
+    pub role: LedgerRole,
+}
+
+#[derive(Deserialize)]
+pub struct LedgerRequest {
+    pub name: String,
+}
+
+impl LedgerRequest {
+    pub fn validate(&self) -> Result<(), LedgerError> {
+        if self.name.trim().is_empty() {
+            return Err(LedgerError::NameRequired);
+        }
+
+        if self.name.len() > 100 {
+            return Err(LedgerError::NameTooLong);
+        }
+
+        Ok(())
+    }
+}
+
+// role is the requesting user's own
+#[derive(FromRow, Serialize)]
+pub struct LedgerResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub role: LedgerRole,
+    pub is_default: bool,
+}
+
+#[derive(Deserialize)]
+pub struct LedgerPath {
+    pub ledger_id: Uuid,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct MemberResponse {
+    pub user_id: Uuid,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub role: LedgerRole,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct MemberPath {
+    pub ledger_id: Uuid,
+    pub user_id: Uuid,
+}
+
+#[derive(Deserialize)]
+pub struct MemberRoleRequest {
+    pub role: LedgerRole,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationRequest {
+    pub email: String,
+    pub role: LedgerRole,
+}
+
+impl InvitationRequest {
+    pub fn validate(&self) -> Result<(), LedgerError> {
+        let email = self.email.trim();
+
+        if email.is_empty() || email.len() > 255 || !email.contains('@') {
+            return Err(LedgerError::InvalidEmail);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(FromRow, Serialize)]
+pub struct InvitationResponse {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub email: String,
+    pub role: LedgerRole,
+    pub invited_by: Uuid,
+    pub status: InvitationStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationPath {
+    pub ledger_id: Uuid,
+    pub invitation_id: Uuid,
+}
+
+// the token from the invitation mail
+#[derive(Deserialize)]
+pub struct InvitationTokenRequest {
+    pub token: String,
+}
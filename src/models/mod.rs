@@ -1,3 +1,12 @@
+pub mod account_models;
 pub mod auth_models;
+pub mod budget_models;
 pub mod category_models;
+pub mod currency_models;
+pub mod data_export_models;
 pub mod expense_model;
+pub mod import_models;
+pub mod income_models;
+pub mod ledger_models;
+pub mod recurring_models;
+pub mod report_models;
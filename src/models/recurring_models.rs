@@ -0,0 +1,274 @@
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    errors::expense_errors::ExpenseError,
+    models::expense_model::{ExpenseSplit, ExpenseSplitRequest, validate_splits},
+    utils::utils::{first_day_of_month, is_currency_code},
+};
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "recurrence_frequency", rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Deserialize)]
+pub struct RecurringRequest {
+    pub amount: Decimal,
+    // generated expenses use the base currency at the time when this is left out
+    pub currency: Option<String>,
+    pub description: String,
+    pub category_id: Uuid,
+    pub account_id: Option<Uuid>,
+    pub tags: Option<Vec<String>>,
+    pub frequency: Frequency,
+    #[serde(default = "default_interval")]
+    pub interval: i32,
+    // monthly and yearly only, clamped to the last day of shorter months
+    pub day_of_month: Option<i16>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    // copied onto every generated expense, the same rules as an expense's split lines
+    pub splits: Option<Vec<ExpenseSplitRequest>>,
+}
+
+fn default_interval() -> i32 {
+    1
+}
+
+impl RecurringRequest {
+    pub fn validate(&self) -> Result<(), ExpenseError> {
+        if self.amount <= Decimal::ZERO {
+            return Err(ExpenseError::InvalidAmountValue);
+        }
+
+        if let Some(currency) = &self.currency
+            && !is_currency_code(currency)
+        {
+            return Err(ExpenseError::InvalidCurrency);
+        }
+
+        if self.description.is_empty() {
+            return Err(ExpenseError::DescriptionRequired);
+        }
+
+        if self.description.len() > 255 {
+            return Err(ExpenseError::DescriptionTooLong);
+        }
+
+        if self.category_id.is_nil() {
+            return Err(ExpenseError::CategoryIDRequired);
+        }
+
+        if !(1..=366).contains(&self.interval) {
+            return Err(ExpenseError::InvalidSchedule);
+        }
+
+        if let Some(day) = self.day_of_month
+            && (!(1..=31).contains(&day)
+                || matches!(self.frequency, Frequency::Daily | Frequency::Weekly))
+        {
+            return Err(ExpenseError::InvalidSchedule);
+        }
+
+        if let Some(end) = self.end_date
+            && end < self.start_date
+        {
+            return Err(ExpenseError::InvalidSchedule);
+        }
+
+        validate_splits(self.split_lines(), self.amount, self.category_id)
+    }
+
+    pub fn split_lines(&self) -> &[ExpenseSplitRequest] {
+        self.splits.as_deref().unwrap_or_default()
+    }
+
+    pub fn day_of_month(&self) -> Option<i16> {
+        match self.frequency {
+            Frequency::Monthly | Frequency::Yearly => {
+                self.day_of_month.or(Some(self.start_date.day() as i16))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct RecurringResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub category_id: Uuid,
+    pub amount: Decimal,
+    pub currency: Option<String>,
+    pub description: String,
+    pub account_id: Option<Uuid>,
+    pub tags: Option<Vec<String>>,
+    pub frequency: Frequency,
+    #[sqlx(rename = "repeat_interval")]
+    pub interval: i32,
+    pub day_of_month: Option<i16>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub occurrence_count: i32,
+    pub next_occurrence: Option<NaiveDate>,
+    pub splits: Json<Vec<ExpenseSplit>>,
+}
+
+impl RecurringResponse {
+    // occurrences are derived from the start date so clamping never drifts (31st -> 28th -> 31st)
+    pub fn occurrence(&self, n: i32) -> Option<NaiveDate> {
+        occurrence(
+            self.frequency,
+            self.start_date,
+            self.interval,
+            self.day_of_month,
+            n,
+        )
+        .filter(|date| self.end_date.is_none_or(|end| *date <= end))
+    }
+}
+
+pub fn occurrence(
+    frequency: Frequency,
+    start: NaiveDate,
+    interval: i32,
+    day_of_month: Option<i16>,
+    n: i32,
+) -> Option<NaiveDate> {
+    let step = u32::try_from(n.checked_mul(interval)?).ok()?;
+
+    match frequency {
+        Frequency::Daily => start.checked_add_days(Days::new(step.into())),
+        Frequency::Weekly => start.checked_add_days(Days::new(u64::from(step) * 7)),
+        Frequency::Monthly => add_months_clamped(start, step, day_of_month),
+        Frequency::Yearly => add_months_clamped(start, step.checked_mul(12)?, day_of_month),
+    }
+}
+
+fn add_months_clamped(start: NaiveDate, months: u32, day: Option<i16>) -> Option<NaiveDate> {
+    let month = first_day_of_month(start).checked_add_months(Months::new(months))?;
+    let last_day = month.checked_add_months(Months::new(1))?.pred_opt()?.day();
+    let day = day.map_or(start.day(), |d| d as u32);
+
+    month.with_day(day.min(last_day))
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct UpcomingOccurrence {
+    pub recurring_id: Uuid,
+    pub category_id: Uuid,
+    pub amount: Decimal,
+    pub currency: Option<String>,
+    pub description: String,
+    pub date: NaiveDate,
+}
+
+#[derive(Deserialize)]
+pub struct UpcomingParams {
+    pub until: Option<NaiveDate>,
+}
+
+#[derive(Serialize)]
+pub struct RecurringCached {
+    pub cached: bool,
+    pub recurring: Vec<RecurringResponse>,
+}
+
+#[derive(Deserialize)]
+pub struct RecurringPagination {
+    #[serde(default = "default_page")]
+    pub page: i64,
+}
+
+fn default_page() -> i64 {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct RecurringPath {
+    pub recurring_id: Uuid,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn daily_and_weekly_step_by_interval() {
+        let start = date(2026, 10, 17);
+
+        assert_eq!(occurrence(Frequency::Daily, start, 1, None, 0), Some(start));
+        assert_eq!(
+            occurrence(Frequency::Daily, start, 3, None, 5),
+            Some(date(2026, 11, 1))
+        );
+        assert_eq!(
+            occurrence(Frequency::Weekly, start, 2, None, 2),
+            Some(date(2026, 11, 14))
+        );
+    }
+
+    #[test]
+    fn monthly_clamps_without_drifting() {
+        let start = date(2026, 1, 31);
+        let dates: Vec<_> = (0..4)
+            .map(|n| occurrence(Frequency::Monthly, start, 1, Some(31), n))
+            .collect();
+
+        assert_eq!(
+            dates,
+            [
+                Some(date(2026, 1, 31)),
+                Some(date(2026, 2, 28)),
+                Some(date(2026, 3, 31)),
+                Some(date(2026, 4, 30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_uses_day_of_month_over_start_day() {
+        assert_eq!(
+            occurrence(Frequency::Monthly, date(2026, 10, 17), 2, Some(5), 1),
+            Some(date(2026, 12, 5))
+        );
+    }
+
+    #[test]
+    fn yearly_keeps_leap_days_when_it_can() {
+        let start = date(2024, 2, 29);
+
+        assert_eq!(
+            occurrence(Frequency::Yearly, start, 1, Some(29), 1),
+            Some(date(2025, 2, 28))
+        );
+        assert_eq!(
+            occurrence(Frequency::Yearly, start, 1, Some(29), 4),
+            Some(date(2028, 2, 29))
+        );
+    }
+
+    #[test]
+    fn out_of_range_occurrences_are_none() {
+        let start = date(2026, 10, 17);
+
+        assert_eq!(occurrence(Frequency::Daily, start, 1, None, -1), None);
+        assert_eq!(
+            occurrence(Frequency::Yearly, start, 366, None, i32::MAX),
+            None
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct ReportParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+// one row per grouping set, the grouped away columns come back as NULL
+#[derive(FromRow)]
+pub struct SpendingGroupQuery {
+    pub category_id: Option<Uuid>,
+    pub category_name: Option<String>,
+    pub month: Option<NaiveDate>,
+    pub total: Decimal,
+    pub count: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CategoryTotal {
+    pub category_id: Uuid,
+    pub category_name: String,
+    pub total: Decimal,
+    pub count: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MonthTotal {
+    pub month: NaiveDate,
+    pub total: Decimal,
+    pub count: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CategoryMonthTotal {
+    pub category_id: Uuid,
+    pub category_name: String,
+    pub month: NaiveDate,
+    pub total: Decimal,
+    pub count: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SpendingReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total: Decimal,
+    pub count: i64,
+    pub by_category: Vec<CategoryTotal>,
+    pub by_month: Vec<MonthTotal>,
+    pub by_category_month: Vec<CategoryMonthTotal>,
+}
+
+#[derive(Serialize)]
+pub struct SpendingReportCached {
+    pub cached: bool,
+    pub report: SpendingReport,
+}
+
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct CashFlowMonth {
+    pub month: NaiveDate,
+    pub income: Decimal,
+    pub expense: Decimal,
+    pub net: Decimal,
+}
+
+// months without any entries are included with zeros
+#[derive(Deserialize, Serialize)]
+pub struct CashFlowReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub income: Decimal,
+    pub expense: Decimal,
+    pub net: Decimal,
+    pub by_month: Vec<CashFlowMonth>,
+}
+
+#[derive(Serialize)]
+pub struct CashFlowReportCached {
+    pub cached: bool,
+    pub report: CashFlowReport,
+}
@@ -0,0 +1,27 @@
+use actix_web::{
+    middleware::from_fn,
+    web::{ServiceConfig, delete, get, post, put, scope},
+};
+
+use crate::{
+    handlers::account::{
+        add_account, add_transfer, delete_account, delete_transfer, edit_account,
+        get_account_ledger, get_accounts, get_transfers,
+    },
+    middleware::rate_limit::user_rate_limit,
+};
+
+pub fn route(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/api/account")
+            .wrap(from_fn(user_rate_limit))
+            .route("/", post().to(add_account))
+            .route("/", get().to(get_accounts))
+            .route("/transfer", post().to(add_transfer))
+            .route("/transfer", get().to(get_transfers))
+            .route("/transfer/{transfer_id}", delete().to(delete_transfer))
+            .route("/{account_id}/ledger", get().to(get_account_ledger))
+            .route("/{account_id}", put().to(edit_account))
+            .route("/{account_id}", delete().to(delete_account)),
+    );
+}
@@ -1,12 +1,48 @@
-use actix_web::web::{ServiceConfig, post, scope};
+use actix_web::web::{ServiceConfig, delete, get, post, put, scope};
 
-use crate::handlers::auth::{login, refresh, register};
+use crate::handlers::{
+    api_token::{add_api_token, delete_api_token, get_api_tokens},
+    auth::{
+        change_password, confirm_two_factor, delete_account, disable_two_factor, forgot_password,
+        get_profile, get_sessions, jwks, login, login_two_factor, logout, logout_all, refresh,
+        register, resend_verification, reset_password, revoke_session, setup_two_factor,
+        update_profile, verify_email,
+    },
+    data_export::{download_data_export, get_data_export, request_data_export},
+};
 
 pub fn route(cfg: &mut ServiceConfig) {
     cfg.service(
         scope("/api/user")
             .route("/register", post().to(register))
             .route("/login", post().to(login))
-            .route("/refresh", post().to(refresh)),
+            .route("/login/2fa", post().to(login_two_factor))
+            .route("/refresh", post().to(refresh))
+            .route("/me", get().to(get_profile))
+            .route("/me", put().to(update_profile))
+            .route("/me", delete().to(delete_account))
+            .route("/logout", post().to(logout))
+            .route("/logout/all", post().to(logout_all))
+            .route("/export", post().to(request_data_export))
+            .route("/export/{export_id}", get().to(get_data_export))
+            .route(
+                "/export/{export_id}/download",
+                get().to(download_data_export),
+            )
+            .route("/2fa/setup", post().to(setup_two_factor))
+            .route("/2fa/confirm", post().to(confirm_two_factor))
+            .route("/2fa/disable", post().to(disable_two_factor))
+            .route("/password", post().to(change_password))
+            .route("/password/forgot", post().to(forgot_password))
+            .route("/password/reset", post().to(reset_password))
+            .route("/sessions", get().to(get_sessions))
+            .route("/sessions/{session_id}", delete().to(revoke_session))
+            .route("/tokens", post().to(add_api_token))
+            .route("/tokens", get().to(get_api_tokens))
+            .route("/tokens/{token_id}", delete().to(delete_api_token))
+            .route("/verify", post().to(verify_email))
+            .route("/verify/resend", post().to(resend_verification)),
     );
+
+    cfg.route("/.well-known/jwks.json", get().to(jwks));
 }
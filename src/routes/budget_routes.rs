@@ -0,0 +1,13 @@
+use actix_web::web::{ServiceConfig, delete, get, post, put, scope};
+
+use crate::handlers::budget::{delete_budget, edit_budget, get_budget_status, set_budget};
+
+pub fn route(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/api/budget")
+            .route("/", post().to(set_budget))
+            .route("/status", get().to(get_budget_status))
+            .route("/{budget_id}", put().to(edit_budget))
+            .route("/{budget_id}", delete().to(delete_budget)),
+    );
+}
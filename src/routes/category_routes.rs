@@ -1,11 +1,26 @@
-use actix_web::web::{ServiceConfig, get, post, scope};
+use actix_web::{
+    middleware::from_fn,
+    web::{ServiceConfig, delete, get, post, put, scope},
+};
 
-use crate::handlers::category::{add_category, get_user_categories};
+use crate::{
+    handlers::category::{
+        add_category, delete_category, edit_category, get_single_category, get_trashed_categories,
+        get_user_categories, restore_category,
+    },
+    middleware::rate_limit::user_rate_limit,
+};
 
 pub fn route(cfg: &mut ServiceConfig) {
     cfg.service(
         scope("/api/category")
+            .wrap(from_fn(user_rate_limit))
             .route("/", post().to(add_category))
-            .route("/user", get().to(get_user_categories)),
+            .route("/user", get().to(get_user_categories))
+            .route("/trash", get().to(get_trashed_categories))
+            .route("/trash/{category_id}/restore", post().to(restore_category))
+            .route("/{category_id}", get().to(get_single_category))
+            .route("/{category_id}", put().to(edit_category))
+            .route("/{category_id}", delete().to(delete_category)),
     );
 }
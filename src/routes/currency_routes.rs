@@ -0,0 +1,13 @@
+use actix_web::web::{ServiceConfig, delete, get, post, put, scope};
+
+use crate::handlers::currency::{add_manual_rate, delete_rate, get_rates, set_base_currency};
+
+pub fn route(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/api/currency")
+            .route("/base", put().to(set_base_currency))
+            .route("/rates", get().to(get_rates))
+            .route("/rates", post().to(add_manual_rate))
+            .route("/rates/{rate_id}", delete().to(delete_rate)),
+    );
+}
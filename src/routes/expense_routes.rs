@@ -1,23 +1,60 @@
-use actix_web::web::{ServiceConfig, delete, get, post, put, scope};
+use actix_web::{
+    middleware::from_fn,
+    web::{PayloadConfig, ServiceConfig, delete, get, post, put, resource, scope},
+};
 
-use crate::handlers::expense::{
-    add_expense, delete_expense_per_user, edit_expense_per_user,
-    filter_expense_by_category_per_user, get_single_expense_per_user, get_total_of_all_expenses,
-    get_user_expenses,
+use crate::{
+    handlers::{
+        expense::{
+            add_expense, delete_expense_per_user, edit_expense_per_user, export_expenses,
+            filter_expense_by_category_per_user, get_expense_history, get_single_expense_per_user,
+            get_total_of_all_expenses, get_trashed_expenses, get_user_expenses,
+            restore_expense_per_user,
+        },
+        import::{add_import_profile, delete_import_profile, get_import_profiles, import_expenses},
+        recurring::{
+            add_recurring, delete_recurring, edit_recurring, get_upcoming, get_user_recurring,
+        },
+    },
+    middleware::rate_limit::user_rate_limit,
 };
 
 pub fn route(cfg: &mut ServiceConfig) {
     cfg.service(
         scope("/api/expense")
+            .wrap(from_fn(user_rate_limit))
             .route("/", post().to(add_expense))
             .route("/user", get().to(get_user_expenses))
             .route("/user/{expense_id}", get().to(get_single_expense_per_user))
             .route("/user/{expense_id}", put().to(edit_expense_per_user))
             .route("/user/{expense_id}", delete().to(delete_expense_per_user))
+            .route("/user/{expense_id}/history", get().to(get_expense_history))
+            .route("/trash", get().to(get_trashed_expenses))
+            .route(
+                "/trash/{expense_id}/restore",
+                post().to(restore_expense_per_user),
+            )
             .route("/total", get().to(get_total_of_all_expenses))
+            .route("/export", get().to(export_expenses))
             .route(
                 "/filter/category/{category_id}",
                 get().to(filter_expense_by_category_per_user),
+            )
+            .route("/recurring", post().to(add_recurring))
+            .route("/recurring", get().to(get_user_recurring))
+            .route("/recurring/upcoming", get().to(get_upcoming))
+            .route("/recurring/{recurring_id}", put().to(edit_recurring))
+            .route("/recurring/{recurring_id}", delete().to(delete_recurring))
+            .service(
+                resource("/import")
+                    .app_data(PayloadConfig::new(5 * 1024 * 1024))
+                    .route(post().to(import_expenses)),
+            )
+            .route("/import/profiles", post().to(add_import_profile))
+            .route("/import/profiles", get().to(get_import_profiles))
+            .route(
+                "/import/profiles/{profile_id}",
+                delete().to(delete_import_profile),
             ),
     );
 }
@@ -0,0 +1,31 @@
+use actix_web::{
+    middleware::from_fn,
+    web::{ServiceConfig, delete, get, post, put, scope},
+};
+
+use crate::{
+    handlers::income::{
+        add_income, add_income_category, delete_income, delete_income_category, edit_income,
+        edit_income_category, get_income_categories, get_single_income, get_user_income,
+    },
+    middleware::rate_limit::user_rate_limit,
+};
+
+pub fn route(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/api/income")
+            .wrap(from_fn(user_rate_limit))
+            .route("/", post().to(add_income))
+            .route("/user", get().to(get_user_income))
+            .route("/user/{income_id}", get().to(get_single_income))
+            .route("/user/{income_id}", put().to(edit_income))
+            .route("/user/{income_id}", delete().to(delete_income))
+            .route("/category", post().to(add_income_category))
+            .route("/category", get().to(get_income_categories))
+            .route("/category/{category_id}", put().to(edit_income_category))
+            .route(
+                "/category/{category_id}",
+                delete().to(delete_income_category),
+            ),
+    );
+}
@@ -0,0 +1,34 @@
+use actix_web::{
+    middleware::from_fn,
+    web::{ServiceConfig, delete, get, post, put, scope},
+};
+
+use crate::{
+    handlers::ledger::{
+        accept_invitation, add_ledger, decline_invitation, delete_ledger, edit_ledger, edit_member,
+        get_invitations, get_ledgers, get_members, invite_member, remove_member, revoke_invitation,
+    },
+    middleware::rate_limit::user_rate_limit,
+};
+
+pub fn route(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/api/ledger")
+            .wrap(from_fn(user_rate_limit))
+            .route("/", post().to(add_ledger))
+            .route("/", get().to(get_ledgers))
+            .route("/invitations/accept", post().to(accept_invitation))
+            .route("/invitations/decline", post().to(decline_invitation))
+            .route("/{ledger_id}", put().to(edit_ledger))
+            .route("/{ledger_id}", delete().to(delete_ledger))
+            .route("/{ledger_id}/members", get().to(get_members))
+            .route("/{ledger_id}/members/{user_id}", put().to(edit_member))
+            .route("/{ledger_id}/members/{user_id}", delete().to(remove_member))
+            .route("/{ledger_id}/invitations", post().to(invite_member))
+            .route("/{ledger_id}/invitations", get().to(get_invitations))
+            .route(
+                "/{ledger_id}/invitations/{invitation_id}",
+                delete().to(revoke_invitation),
+            ),
+    );
+}
@@ -1,3 +1,9 @@
+pub mod account_routes;
 pub mod auth_routes;
+pub mod budget_routes;
 pub mod category_routes;
+pub mod currency_routes;
 pub mod expense_routes;
+pub mod income_routes;
+pub mod ledger_routes;
+pub mod report_routes;
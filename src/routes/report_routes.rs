@@ -0,0 +1,11 @@
+use actix_web::web::{ServiceConfig, get, scope};
+
+use crate::handlers::report::{get_cash_flow, get_spending_report};
+
+pub fn route(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/api/report")
+            .route("/spending", get().to(get_spending_report))
+            .route("/cash-flow", get().to(get_cash_flow)),
+    );
+}
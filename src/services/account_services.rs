@@ -0,0 +1,278 @@
+use rust_decimal::Decimal;
+use sqlx::{PgPool, query_as, query_scalar};
+use uuid::Uuid;
+
+use crate::{
+    errors::account_errors::AccountError,
+    models::account_models::{
+        AccountBalanceResponse, AccountLedger, AccountPath, AccountRequest, AccountResponse,
+        LedgerEntry, LedgerParams, TransferParams, TransferPath, TransferRequest, TransferResponse,
+    },
+};
+
+#[derive(Clone)]
+pub struct AccountService {
+    pool: PgPool,
+}
+
+impl AccountService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn add_account(
+        &self,
+        body: AccountRequest,
+        user_id: Uuid,
+    ) -> Result<AccountResponse, AccountError> {
+        body.validate()?;
+
+        query_as::<_, AccountResponse>(
+            r#"
+                INSERT INTO account (user_id, name, type, currency, opening_balance)
+                SELECT id, $2, $3, COALESCE($4, base_currency), $5 FROM users
+                WHERE id = $1
+                RETURNING id, user_id, name, type, currency, opening_balance
+            "#,
+        )
+        .bind(user_id)
+        .bind(body.name.trim())
+        .bind(body.account_type)
+        .bind(body.currency)
+        .bind(body.opening_balance)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AccountError::constraint)
+    }
+
+    pub async fn get_accounts(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<AccountBalanceResponse>, AccountError> {
+        query_as::<_, AccountBalanceResponse>(
+            r#"
+                SELECT id, user_id, name, type, currency, opening_balance,
+                    account_balance(id, NULL) AS balance
+                FROM account
+                WHERE user_id = $1
+                ORDER BY name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AccountError::conversion)
+    }
+
+    async fn get_account(
+        &self,
+        account_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<AccountResponse, AccountError> {
+        query_as::<_, AccountResponse>(
+            r#"
+                SELECT id, user_id, name, type, currency, opening_balance FROM account
+                WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(account_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AccountError::internal)?
+        .ok_or(AccountError::AccountNotFound)
+    }
+
+    // expenses, income and transfers in date order with the balance after each of them
+    pub async fn get_ledger(
+        &self,
+        path: AccountPath,
+        params: LedgerParams,
+        user_id: Uuid,
+    ) -> Result<AccountLedger, AccountError> {
+        params.validate()?;
+
+        let account = self.get_account(path.account_id, user_id).await?;
+
+        let starting_balance: Decimal = match params.from {
+            Some(from) => query_scalar("SELECT account_balance($1, $2)")
+                .bind(account.id)
+                .bind(from)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(AccountError::conversion)?,
+            None => account.opening_balance,
+        };
+
+        let entries = query_as::<_, LedgerEntry>(
+            r#"
+                SELECT kind, id, date, description, amount,
+                    $4 + SUM(amount) OVER (ORDER BY date, created_at, id) AS balance
+                FROM account_movement
+                WHERE account_id = $1
+                    AND ($2::date IS NULL OR date >= $2)
+                    AND ($3::date IS NULL OR date <= $3)
+                ORDER BY date, created_at, id
+            "#,
+        )
+        .bind(account.id)
+        .bind(params.from)
+        .bind(params.to)
+        .bind(starting_balance)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AccountError::conversion)?;
+
+        let ending_balance = entries.last().map_or(starting_balance, |e| e.balance);
+
+        Ok(AccountLedger {
+            account,
+            from: params.from,
+            to: params.to,
+            starting_balance,
+            ending_balance,
+            entries,
+        })
+    }
+
+    pub async fn edit_account(
+        &self,
+        body: AccountRequest,
+        path: AccountPath,
+        user_id: Uuid,
+    ) -> Result<AccountResponse, AccountError> {
+        body.validate()?;
+
+        query_as::<_, AccountResponse>(
+            r#"
+                UPDATE account
+                SET name = $3, type = $4, opening_balance = $5, updated_at = NOW()
+                WHERE id = $1 AND user_id = $2
+                RETURNING id, user_id, name, type, currency, opening_balance
+            "#,
+        )
+        .bind(path.account_id)
+        .bind(user_id)
+        .bind(body.name.trim())
+        .bind(body.account_type)
+        .bind(body.opening_balance)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AccountError::constraint)?
+        .ok_or(AccountError::AccountNotFound)
+    }
+
+    // entries, trashed ones included, have to be moved or purged first
+    pub async fn delete_account(
+        &self,
+        path: AccountPath,
+        user_id: Uuid,
+    ) -> Result<String, AccountError> {
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM account
+                WHERE id = $1 AND user_id = $2
+                RETURNING id
+            "#,
+        )
+        .bind(path.account_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AccountError::constraint)?
+        .ok_or(AccountError::AccountNotFound)?;
+
+        Ok(id.to_string())
+    }
+
+    // transfers only move money between accounts, they never count as spending or income
+    pub async fn add_transfer(
+        &self,
+        body: TransferRequest,
+        user_id: Uuid,
+    ) -> Result<TransferResponse, AccountError> {
+        body.validate()?;
+
+        query_as::<_, TransferResponse>(
+            r#"
+                INSERT INTO transfer (user_id, from_account_id, to_account_id, amount,
+                    to_amount, description, date)
+                SELECT $1, f.id, t.id, $4,
+                    COALESCE($5, ROUND(convert_amount($1, $4, f.currency, t.currency, $7), 2)),
+                    $6, $7
+                FROM account f, account t
+                WHERE f.id = $2 AND f.user_id = $1 AND t.id = $3 AND t.user_id = $1
+                RETURNING id, user_id, from_account_id, to_account_id, amount, to_amount,
+                    description, date
+            "#,
+        )
+        .bind(user_id)
+        .bind(body.from_account_id)
+        .bind(body.to_account_id)
+        .bind(body.amount)
+        .bind(body.to_amount)
+        .bind(body.description)
+        .bind(body.date)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AccountError::conversion)?
+        .ok_or(AccountError::AccountNotFound)
+    }
+
+    pub async fn get_transfers(
+        &self,
+        params: TransferParams,
+        user_id: Uuid,
+    ) -> Result<Vec<TransferResponse>, AccountError> {
+        params.validate()?;
+
+        let limit: i64 = 10;
+        let page = params.page.max(1);
+        let offset = (page - 1) * limit;
+
+        query_as::<_, TransferResponse>(
+            r#"
+                SELECT id, user_id, from_account_id, to_account_id, amount, to_amount,
+                    description, date
+                FROM transfer
+                WHERE user_id = $1
+                    AND ($2::uuid IS NULL OR from_account_id = $2 OR to_account_id = $2)
+                    AND ($3::date IS NULL OR date >= $3)
+                    AND ($4::date IS NULL OR date <= $4)
+                ORDER BY date DESC, created_at DESC
+                LIMIT $5 OFFSET $6
+            "#,
+        )
+        .bind(user_id)
+        .bind(params.account_id)
+        .bind(params.from)
+        .bind(params.to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AccountError::internal)
+    }
+
+    pub async fn delete_transfer(
+        &self,
+        path: TransferPath,
+        user_id: Uuid,
+    ) -> Result<String, AccountError> {
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM transfer
+                WHERE id = $1 AND user_id = $2
+                RETURNING id
+            "#,
+        )
+        .bind(path.transfer_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AccountError::internal)?
+        .ok_or(AccountError::TransferNotFound)?;
+
+        Ok(id.to_string())
+    }
+}
@@ -0,0 +1,115 @@
+use sqlx::{PgPool, query_as, query_scalar};
+use uuid::Uuid;
+
+use crate::{
+    errors::auth_errors::AuthError,
+    models::auth_models::{
+        ApiTokenCreated, ApiTokenPath, ApiTokenRequest, ApiTokenResponse, ApiTokenScope,
+    },
+    utils::utils::{create_token, hash_token},
+};
+
+// tells personal tokens apart from JWTs before any lookup
+pub const API_TOKEN_PREFIX: &str = "etk_";
+
+#[derive(Clone)]
+pub struct ApiTokenService {
+    pool: PgPool,
+}
+
+impl ApiTokenService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn add_token(
+        &self,
+        body: ApiTokenRequest,
+        user_id: Uuid,
+    ) -> Result<ApiTokenCreated, AuthError> {
+        body.validate()?;
+
+        let token = format!("{API_TOKEN_PREFIX}{}", create_token());
+
+        let api_token = query_as::<_, ApiTokenResponse>(
+            r#"
+                INSERT INTO api_token (user_id, name, token_hash, scope)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, name, scope, last_used_at, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(body.name.trim())
+        .bind(hash_token(&token))
+        .bind(body.scope)
+        .fetch_one(&self.pool)
+        .await;
+
+        let api_token = api_token.map_err(|e| {
+            if let sqlx::Error::Database(db_err) = &e
+                && db_err.code().as_deref() == Some("23505")
+            {
+                return AuthError::TokenNameExisting;
+            }
+
+            AuthError::internal(e)
+        })?;
+
+        Ok(ApiTokenCreated { api_token, token })
+    }
+
+    pub async fn get_user_tokens(&self, user_id: Uuid) -> Result<Vec<ApiTokenResponse>, AuthError> {
+        query_as::<_, ApiTokenResponse>(
+            r#"
+                SELECT id, name, scope, last_used_at, created_at
+                FROM api_token
+                WHERE user_id = $1
+                ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthError::internal)
+    }
+
+    pub async fn delete_token(
+        &self,
+        path: ApiTokenPath,
+        user_id: Uuid,
+    ) -> Result<String, AuthError> {
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM api_token
+                WHERE id = $1 AND user_id = $2
+                RETURNING id
+            "#,
+        )
+        .bind(path.token_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::TokenNotFound)?;
+
+        Ok(id.to_string())
+    }
+
+    // the lookup doubles as the last used bookkeeping
+    pub async fn authenticate(
+        &self,
+        token: &str,
+    ) -> Result<Option<(Uuid, ApiTokenScope)>, AuthError> {
+        query_as(
+            r#"
+                UPDATE api_token SET last_used_at = NOW()
+                WHERE token_hash = $1
+                RETURNING user_id, scope
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)
+    }
+}
@@ -1,29 +1,58 @@
 use anyhow::Context;
 use bcrypt::{DEFAULT_COST, hash};
+use chrono::Utc;
+use qrcode::{QrCode, render::svg};
 use sqlx::PgPool;
-use std::result::Result;
+use std::{cmp::Reverse, result::Result};
+use totp_rs::{Builder, Secret, Totp};
+use tracing::{error, warn};
+use uuid::Uuid;
 
 use crate::{
     errors::auth_errors::AuthError,
-    models::auth_models::{AuthResponse, LoginQuery, LoginRequest, RegisterRequest, UserQuery},
-    services::{jwt_services::JwtService, redis_services::RedisService},
-    utils::utils::create_uuid,
+    models::auth_models::{
+        AuthResponse, ChangePasswordRequest, ClientInfo, DeleteAccountRequest,
+        DisableTwoFactorRequest, ForgotPasswordRequest, LoginOutcome, LoginQuery, LoginRequest,
+        PasswordQuery, ProfileResponse, RecoveryCodesResponse, RegisterRequest,
+        ResetPasswordRequest, SessionMeta, SessionPath, SessionResponse, TwoFactorCodeRequest,
+        TwoFactorLoginRequest, TwoFactorSetupResponse, UpdateProfileRequest, UserQuery,
+        VerifyEmailRequest, validate_password,
+    },
+    services::{
+        jwt_services::JwtService,
+        ledger_services::forget_cached,
+        mailer_services::{Mail, MailerService},
+        redis_services::RedisService,
+    },
+    utils::utils::{
+        access_revoked_at_key, create_token, create_uuid, denied_access_token_key, hash_token,
+        login_challenge_attempts_key, login_challenge_key, login_email_rate_key,
+        login_failures_key, login_ip_rate_key, login_lockout_key, password_reset_key,
+        refresh_token_key, refresh_tokens_pattern, session_key, sessions_pattern,
+        totp_last_step_key, user_keys_pattern, user_password_reset_key, verification_sent_key,
+    },
 };
 
 #[derive(Clone)]
 pub struct AuthService {
     pool: PgPool,
+    require_verified_email: bool,
 }
 
 impl AuthService {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, require_verified_email: bool) -> Self {
+        Self {
+            pool,
+            require_verified_email,
+        }
     }
 
     pub async fn register(
         &self,
         body: RegisterRequest,
+        client: ClientInfo,
         jwt: &JwtService,
+        mailer: &MailerService,
         redis: &RedisService,
     ) -> Result<AuthResponse, AuthError> {
         body.validate()?;
@@ -45,31 +74,21 @@ impl AuthService {
         .await;
 
         let new_user = new_user.map_err(|e| {
-            if let sqlx::Error::Database(db_err) = &e {
-                if db_err.code().as_deref() == Some("23505") {
-                    return AuthError::DuplicateEmail;
-                }
+            if let sqlx::Error::Database(db_err) = &e
+                && db_err.code().as_deref() == Some("23505")
+            {
+                return AuthError::DuplicateEmail;
             }
 
             AuthError::internal(e)
         })?;
 
-        let refresh_token_jti = create_uuid();
-        let sub = new_user.id;
-
-        redis
-            .set(
-                format!("user:{}:refresh:{refresh_token_jti}", sub),
-                &refresh_token_jti,
-                60 * 60 * 24 * 7,
-            )
-            .await
-            .map_err(AuthError::internal)?;
+        let (token, refresh_token) = self.start_session(new_user.id, client, jwt, redis).await?;
 
-        let token = jwt.create_access_token(sub).map_err(AuthError::internal)?;
-        let refresh_token = jwt
-            .create_refresh_token(&refresh_token_jti, sub)
-            .map_err(AuthError::internal)?;
+        // the account exists either way, a failed mail can be resent later
+        if let Err(e) = send_verification(new_user.id, &new_user.email, jwt, mailer).await {
+            error!(error = ?e, "Failed to send verification mail.");
+        }
 
         Ok(AuthResponse {
             email: new_user.email,
@@ -81,52 +100,222 @@ impl AuthService {
     pub async fn login(
         &self,
         body: LoginRequest,
+        client: ClientInfo,
         jwt: &JwtService,
         redis: &RedisService,
-    ) -> Result<AuthResponse, AuthError> {
+    ) -> Result<LoginOutcome, AuthError> {
         body.validate()?;
 
+        // before bcrypt, so floods never reach the expensive part
+        self.check_login_limits(&body.email, &client, redis).await?;
+
         let user = sqlx::query_as::<_, LoginQuery>(
             r#"
-                SELECT email, id, password FROM users
+                SELECT email, id, password, totp_enabled_at IS NOT NULL AS totp_enabled
+                FROM users
                 WHERE email = $1
             "#,
         )
-        .bind(body.email)
+        .bind(&body.email)
         .fetch_one(&self.pool)
         .await;
 
-        let user = user.map_err(|e| {
-            if let sqlx::Error::RowNotFound = &e {
-                return AuthError::InvalidCredentials;
-            }
+        let user = match user {
+            Ok(user) => user,
+            Err(sqlx::Error::RowNotFound) => {
+                self.record_login_failure(&body.email, &client, redis)
+                    .await?;
 
-            AuthError::internal(e)
-        })?;
+                return Err(AuthError::InvalidCredentials);
+            }
+            Err(e) => return Err(AuthError::internal(e)),
+        };
 
-        if !bcrypt::verify(body.password, &user.password)
+        if !bcrypt::verify(&body.password, &user.password)
             .context("failed to verify password hash")?
         {
+            self.record_login_failure(&body.email, &client, redis)
+                .await?;
+
             return Err(AuthError::InvalidCredentials)?;
         }
 
-        let refresh_token_jti = create_uuid();
-        let sub = user.id;
-
         redis
-            .set(
-                format!("user:{}:refresh:{refresh_token_jti}", sub),
-                &refresh_token_jti,
-                60 * 60 * 24 * 7,
-            )
+            .revoke(&login_failures_key(&body.email))
             .await
             .map_err(AuthError::internal)?;
 
-        let token = jwt.create_access_token(sub).map_err(AuthError::internal)?;
-        let refresh_token = jwt
-            .create_refresh_token(&refresh_token_jti, sub)
+        if user.totp_enabled {
+            let challenge = create_token();
+
+            redis
+                .set(
+                    login_challenge_key(&hash_token(&challenge)),
+                    user.id.to_string(),
+                    LOGIN_CHALLENGE_TTL,
+                )
+                .await
+                .map_err(AuthError::internal)?;
+
+            return Ok(LoginOutcome::ChallengeRequired(challenge));
+        }
+
+        let (token, refresh_token) = self.start_session(user.id, client, jwt, redis).await?;
+
+        Ok(LoginOutcome::Authenticated(AuthResponse {
+            email: user.email,
+            refresh_token,
+            token,
+        }))
+    }
+
+    async fn check_login_limits(
+        &self,
+        email: &str,
+        client: &ClientInfo,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        if let Some(retry_after) = redis
+            .ttl(&login_lockout_key(email))
+            .await
+            .map_err(AuthError::internal)?
+        {
+            return Err(AuthError::AccountLocked(retry_after));
+        }
+
+        if let Some(ip) = &client.ip
+            && let Some(retry_after) = redis
+                .sliding_window(&login_ip_rate_key(ip), LOGIN_IP_LIMIT, LOGIN_RATE_WINDOW)
+                .await
+                .map_err(AuthError::internal)?
+        {
+            return Err(AuthError::TooManyRequests(retry_after));
+        }
+
+        if let Some(retry_after) = redis
+            .sliding_window(
+                &login_email_rate_key(email),
+                LOGIN_EMAIL_LIMIT,
+                LOGIN_RATE_WINDOW,
+            )
+            .await
+            .map_err(AuthError::internal)?
+        {
+            return Err(AuthError::TooManyRequests(retry_after));
+        }
+
+        Ok(())
+    }
+
+    // every failure past the threshold doubles the lockout, up to MAX_LOCKOUT
+    async fn record_login_failure(
+        &self,
+        email: &str,
+        client: &ClientInfo,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        let key = login_failures_key(email);
+
+        let (failures, _): (u32, bool) = redis
+            .pipeline(|pipe| {
+                pipe.incr(&key, 1).expire(&key, LOGIN_FAILURES_TTL);
+            })
+            .await
             .map_err(AuthError::internal)?;
 
+        if failures < LOCKOUT_THRESHOLD {
+            return Ok(());
+        }
+
+        let lockout = BASE_LOCKOUT
+            .saturating_mul(1 << (failures - LOCKOUT_THRESHOLD).min(16))
+            .min(MAX_LOCKOUT);
+
+        warn!(
+            target: "security",
+            event = "login_lockout",
+            email,
+            failures,
+            lockout,
+            ip = ?client.ip,
+            "Repeated login failures, locking account."
+        );
+
+        redis
+            .set(login_lockout_key(email), 1, lockout)
+            .await
+            .map_err(AuthError::internal)
+    }
+
+    // second step of a login with 2FA, accepts a TOTP code or a recovery code
+    pub async fn login_two_factor(
+        &self,
+        body: TwoFactorLoginRequest,
+        client: ClientInfo,
+        jwt: &JwtService,
+        redis: &RedisService,
+    ) -> Result<AuthResponse, AuthError> {
+        let token_hash = hash_token(&body.challenge_token);
+        let challenge_key = login_challenge_key(&token_hash);
+
+        let user_id = redis
+            .get(&challenge_key)
+            .await
+            .map_err(AuthError::internal)?
+            .and_then(|id| Uuid::parse_str(&id).ok())
+            .ok_or(AuthError::InvalidToken)?;
+
+        if !self
+            .verify_second_factor(user_id, &body.code, redis)
+            .await?
+        {
+            let attempts_key = login_challenge_attempts_key(&token_hash);
+
+            let (attempts, _): (i64, bool) = redis
+                .pipeline(|pipe| {
+                    pipe.incr(&attempts_key, 1)
+                        .expire(&attempts_key, LOGIN_CHALLENGE_TTL as i64);
+                })
+                .await
+                .map_err(AuthError::internal)?;
+
+            // guessing codes needs a fresh password check after a few misses
+            if attempts >= MAX_CHALLENGE_ATTEMPTS {
+                redis
+                    .pipeline::<()>(|pipe| {
+                        pipe.del(&challenge_key).del(&attempts_key);
+                    })
+                    .await
+                    .map_err(AuthError::internal)?;
+            }
+
+            return Err(AuthError::InvalidTwoFactorCode);
+        }
+
+        // a challenge can only be redeemed once
+        if redis
+            .take(&challenge_key)
+            .await
+            .map_err(AuthError::internal)?
+            .is_none()
+        {
+            return Err(AuthError::InvalidToken);
+        }
+
+        let user = sqlx::query_as::<_, UserQuery>(
+            r#"
+                SELECT email, id FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::InvalidToken)?;
+
+        let (token, refresh_token) = self.start_session(user.id, client, jwt, redis).await?;
+
         Ok(AuthResponse {
             email: user.email,
             refresh_token,
@@ -134,22 +323,295 @@ impl AuthService {
         })
     }
 
+    // starting over replaces a pending secret, an enabled one has to be disabled first
+    pub async fn setup_two_factor(
+        &self,
+        user_id: Uuid,
+    ) -> Result<TwoFactorSetupResponse, AuthError> {
+        let (email, enabled): (String, bool) = sqlx::query_as(
+            r#"
+                SELECT email, totp_enabled_at IS NOT NULL FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::Unauthorized)?;
+
+        if enabled {
+            return Err(AuthError::TwoFactorAlreadyEnabled);
+        }
+
+        let secret = Secret::generate().to_base32();
+        let otpauth_uri = totp(&secret, &email)?
+            .to_url()
+            .map_err(AuthError::internal)?;
+
+        let qr_svg = QrCode::new(otpauth_uri.as_bytes())
+            .map_err(AuthError::internal)?
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build();
+
+        sqlx::query(
+            r#"
+                UPDATE users SET totp_secret = $2, updated_at = NOW()
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(&secret)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthError::internal)?;
+
+        Ok(TwoFactorSetupResponse {
+            secret,
+            otpauth_uri,
+            qr_svg,
+        })
+    }
+
+    // proves the authenticator app was set up before 2FA is switched on
+    pub async fn confirm_two_factor(
+        &self,
+        user_id: Uuid,
+        body: TwoFactorCodeRequest,
+        redis: &RedisService,
+    ) -> Result<RecoveryCodesResponse, AuthError> {
+        let (secret, enabled): (Option<String>, bool) = sqlx::query_as(
+            r#"
+                SELECT totp_secret, totp_enabled_at IS NOT NULL FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::Unauthorized)?;
+
+        if enabled {
+            return Err(AuthError::TwoFactorAlreadyEnabled);
+        }
+
+        let secret = secret.ok_or(AuthError::TwoFactorNotSetUp)?;
+
+        if !check_totp(user_id, &secret, &body.code, redis).await? {
+            return Err(AuthError::InvalidTwoFactorCode);
+        }
+
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let code = Uuid::new_v4().simple().to_string();
+                format!("{}-{}", &code[..5], &code[5..10])
+            })
+            .collect();
+
+        let code_hashes: Vec<String> = recovery_codes
+            .iter()
+            .map(|code| hash_token(&normalize_code(code)))
+            .collect();
+
+        let mut tx = self.pool.begin().await.map_err(AuthError::internal)?;
+
+        sqlx::query(
+            r#"
+                UPDATE users SET totp_enabled_at = NOW(), updated_at = NOW()
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthError::internal)?;
+
+        sqlx::query(
+            r#"
+                DELETE FROM recovery_code
+                WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthError::internal)?;
+
+        sqlx::query(
+            r#"
+                INSERT INTO recovery_code (user_id, code_hash)
+                SELECT $1, * FROM UNNEST($2::text[])
+            "#,
+        )
+        .bind(user_id)
+        .bind(&code_hashes)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthError::internal)?;
+
+        tx.commit().await.map_err(AuthError::internal)?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    pub async fn disable_two_factor(
+        &self,
+        user_id: Uuid,
+        body: DisableTwoFactorRequest,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        let (password, enabled): (String, bool) = sqlx::query_as(
+            r#"
+                SELECT password, totp_enabled_at IS NOT NULL FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::Unauthorized)?;
+
+        if !enabled {
+            return Err(AuthError::TwoFactorNotEnabled);
+        }
+
+        if !bcrypt::verify(&body.password, &password).context("failed to verify password hash")? {
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        if !self
+            .verify_second_factor(user_id, &body.code, redis)
+            .await?
+        {
+            return Err(AuthError::InvalidTwoFactorCode);
+        }
+
+        let mut tx = self.pool.begin().await.map_err(AuthError::internal)?;
+
+        sqlx::query(
+            r#"
+                UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, updated_at = NOW()
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthError::internal)?;
+
+        sqlx::query(
+            r#"
+                DELETE FROM recovery_code
+                WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthError::internal)?;
+
+        tx.commit().await.map_err(AuthError::internal)
+    }
+
+    // six digits are checked as a TOTP code, anything else as a recovery code
+    async fn verify_second_factor(
+        &self,
+        user_id: Uuid,
+        code: &str,
+        redis: &RedisService,
+    ) -> Result<bool, AuthError> {
+        let code = normalize_code(code);
+
+        if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+            let secret: Option<String> = sqlx::query_scalar(
+                r#"
+                    SELECT totp_secret FROM users
+                    WHERE id = $1 AND totp_enabled_at IS NOT NULL
+                "#,
+            )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AuthError::internal)?
+            .flatten();
+
+            return match secret {
+                Some(secret) => check_totp(user_id, &secret, &code, redis).await,
+                None => Ok(false),
+            };
+        }
+
+        let used = sqlx::query_scalar::<_, Uuid>(
+            r#"
+                UPDATE recovery_code SET used_at = NOW()
+                WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash_token(&code))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?;
+
+        Ok(used.is_some())
+    }
+
     pub async fn refresh(
         &self,
         cookie: &str,
+        client: ClientInfo,
         jwt: &JwtService,
         redis: &RedisService,
     ) -> Result<AuthResponse, AuthError> {
         let claims = jwt
             .validate_refresh_token(cookie)
-            .map_err(|_| return AuthError::Unauthorized)?;
+            .map_err(|_| AuthError::Unauthorized)?;
+
+        // each session is a token family, it is gone once the family was revoked.
+        // the raw value is kept to rotate only if nobody else did in the meantime
+        let stored = redis
+            .get(&session_key(claims.sub, &claims.sid))
+            .await
+            .map_err(AuthError::internal)?
+            .ok_or(AuthError::Unauthorized)?;
+        let mut session: SessionMeta =
+            serde_json::from_str(&stored).map_err(AuthError::internal)?;
 
-        let exists = redis
-            .exists(&format!("user:{}:refresh:{}", claims.sub, claims.jti))
+        if session.refresh_jti != claims.jti {
+            // a parallel refresh from the same client lost the race, not an attack
+            let within_grace = session.previous_jti.as_deref() == Some(claims.jti.as_str())
+                && (Utc::now() - session.last_refresh).num_seconds() < REUSE_GRACE_SECONDS;
+
+            if within_grace {
+                return Err(AuthError::Unauthorized);
+            }
+
+            // only we can sign tokens of this family, so an old jti means it was copied
+            warn!(
+                target: "security",
+                event = "refresh_token_reuse",
+                user_id = %claims.sub,
+                session_id = %claims.sid,
+                ip = ?client.ip,
+                user_agent = ?client.user_agent,
+                "Refresh token reuse detected, revoking token family."
+            );
+
+            self.end_session(claims.sub, &claims.sid, redis).await?;
+
+            return Err(AuthError::RefreshTokenReused);
+        }
+
+        let current = redis
+            .get(&refresh_token_key(claims.sub, &claims.jti))
             .await
             .context("failed to check refresh token")?;
 
-        if !exists {
+        if current.as_deref() != Some(claims.sid.as_str()) {
             return Err(AuthError::Unauthorized);
         }
 
@@ -174,22 +636,46 @@ impl AuthService {
         let jti = create_uuid();
         let sub = user.id;
 
-        let token = jwt.create_access_token(sub).map_err(AuthError::internal)?;
+        let token = jwt
+            .create_access_token(sub, &session.id)
+            .map_err(AuthError::internal)?;
         let refresh_token = jwt
-            .create_refresh_token(&jti, sub)
+            .create_refresh_token(&jti, sub, &session.id)
             .map_err(AuthError::internal)?;
 
+        session.last_refresh = Utc::now();
+        session.previous_jti = Some(std::mem::replace(&mut session.refresh_jti, jti));
+        session.user_agent = client.user_agent.or(session.user_agent);
+        session.ip = client.ip.or(session.ip);
+
+        let json = serde_json::to_string(&session).map_err(AuthError::internal)?;
+
+        // a parallel refresh with the same token rotated first, this one lost the race
+        let rotated = redis
+            .compare_and_set(
+                &session_key(sub, &session.id),
+                &stored,
+                &json,
+                REFRESH_TOKEN_TTL,
+            )
+            .await
+            .map_err(AuthError::internal)?;
+
+        if !rotated {
+            return Err(AuthError::Unauthorized);
+        }
+
         redis
             .set(
-                format!("user:{}:refresh:{jti}", sub),
-                &jti,
-                60 * 60 * 24 * 7,
+                refresh_token_key(sub, &session.refresh_jti),
+                &session.id,
+                REFRESH_TOKEN_TTL,
             )
             .await
             .map_err(AuthError::internal)?;
 
         redis
-            .revoke(&format!("user:{}:refresh:{}", claims.sub, claims.jti))
+            .revoke(&refresh_token_key(claims.sub, &claims.jti))
             .await
             .context("internal server error")?;
 
@@ -199,4 +685,804 @@ impl AuthService {
             token,
         })
     }
+
+    // both tokens are optional, an expired access token must not block logging out
+    pub async fn logout(
+        &self,
+        access_token: Option<&str>,
+        refresh_token: Option<&str>,
+        jwt: &JwtService,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        if let Some(claims) = access_token.and_then(|t| jwt.validate_token(t).ok()) {
+            deny_access_token(claims.sub, &claims.jti, claims.exp, redis).await?;
+            self.end_session(claims.sub, &claims.sid, redis).await?;
+        }
+
+        if let Some(claims) = refresh_token.and_then(|t| jwt.validate_refresh_token(t).ok()) {
+            redis
+                .revoke(&refresh_token_key(claims.sub, &claims.jti))
+                .await
+                .map_err(AuthError::internal)?;
+
+            self.end_session(claims.sub, &claims.sid, redis).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn logout_all(
+        &self,
+        user_id: Uuid,
+        redis: &RedisService,
+    ) -> Result<usize, AuthError> {
+        let revoked = redis
+            .delete_pattern(&sessions_pattern(user_id))
+            .await
+            .map_err(AuthError::internal)?;
+
+        redis
+            .delete_pattern(&refresh_tokens_pattern(user_id))
+            .await
+            .map_err(AuthError::internal)?;
+
+        // access tokens issued before now stop working on every device
+        redis
+            .set(
+                access_revoked_at_key(user_id),
+                Utc::now().timestamp(),
+                ACCESS_TOKEN_MAX_TTL,
+            )
+            .await
+            .map_err(AuthError::internal)?;
+
+        Ok(revoked)
+    }
+
+    pub async fn get_sessions(
+        &self,
+        user_id: Uuid,
+        current_session: &str,
+        redis: &RedisService,
+    ) -> Result<Vec<SessionResponse>, AuthError> {
+        let keys = redis
+            .keys_matching(&sessions_pattern(user_id))
+            .await
+            .map_err(AuthError::internal)?;
+
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let values: Vec<Option<String>> = redis
+            .pipeline(|pipe| {
+                for key in &keys {
+                    pipe.get(key);
+                }
+            })
+            .await
+            .map_err(AuthError::internal)?;
+
+        let mut sessions: Vec<SessionResponse> = values
+            .into_iter()
+            .flatten()
+            .filter_map(|v| serde_json::from_str::<SessionMeta>(&v).ok())
+            .map(|s| SessionResponse {
+                current: s.id == current_session,
+                id: s.id,
+                created_at: s.created_at,
+                last_refresh: s.last_refresh,
+                user_agent: s.user_agent,
+                ip: s.ip,
+            })
+            .collect();
+
+        sessions.sort_by_key(|s| Reverse(s.last_refresh));
+
+        Ok(sessions)
+    }
+
+    pub async fn revoke_session(
+        &self,
+        user_id: Uuid,
+        path: SessionPath,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        if !self.end_session(user_id, &path.session_id, redis).await? {
+            return Err(AuthError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_profile(&self, user_id: Uuid) -> Result<ProfileResponse, AuthError> {
+        sqlx::query_as::<_, ProfileResponse>(
+            r#"
+                SELECT id, email, name, email_verified_at IS NOT NULL AS email_verified,
+                    totp_enabled_at IS NOT NULL AS two_factor_enabled, base_currency, created_at
+                FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::Unauthorized)
+    }
+
+    // a new email starts unverified and gets its own verification link
+    pub async fn update_profile(
+        &self,
+        user_id: Uuid,
+        body: UpdateProfileRequest,
+        jwt: &JwtService,
+        mailer: &MailerService,
+    ) -> Result<ProfileResponse, AuthError> {
+        body.validate()?;
+
+        let current = self.get_profile(user_id).await?;
+        let email = body.email.filter(|email| *email != current.email);
+
+        if email.is_some() {
+            self.verify_password(user_id, body.password.as_deref().unwrap_or_default())
+                .await?;
+        }
+
+        let profile = sqlx::query_as::<_, ProfileResponse>(
+            r#"
+                UPDATE users SET
+                    name = COALESCE($2, name),
+                    email = COALESCE($3, email),
+                    email_verified_at = CASE WHEN $3 IS NULL THEN email_verified_at END,
+                    updated_at = NOW()
+                WHERE id = $1
+                RETURNING id, email, name, email_verified_at IS NOT NULL AS email_verified,
+                    totp_enabled_at IS NOT NULL AS two_factor_enabled, base_currency, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(body.name)
+        .bind(&email)
+        .fetch_one(&self.pool)
+        .await;
+
+        let profile = profile.map_err(|e| {
+            if let sqlx::Error::Database(db_err) = &e
+                && db_err.code().as_deref() == Some("23505")
+            {
+                return AuthError::DuplicateEmail;
+            }
+
+            AuthError::internal(e)
+        })?;
+
+        if email.is_some()
+            && let Err(e) = send_verification(user_id, &profile.email, jwt, mailer).await
+        {
+            error!(error = ?e, "Failed to send verification mail.");
+        }
+
+        Ok(profile)
+    }
+
+    // rows go through ON DELETE CASCADE, the user's redis keys are purged after
+    pub async fn delete_account(
+        &self,
+        user_id: Uuid,
+        body: DeleteAccountRequest,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        self.verify_password(user_id, &body.password).await?;
+
+        let co_members: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+                SELECT DISTINCT m.user_id FROM ledger_member m
+                JOIN ledger_member own ON own.ledger_id = m.ledger_id AND own.user_id = $1
+                WHERE m.user_id <> $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthError::internal)?;
+
+        // a trigger hands what this user wrote into shared ledgers to the members left there,
+        // promoting one of them when the ledger would end up without an owner
+        sqlx::query(
+            r#"
+                DELETE FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthError::internal)?;
+
+        for member in co_members {
+            forget_cached(redis, member)
+                .await
+                .map_err(AuthError::internal)?;
+        }
+
+        // the only user data keyed outside user:{id}:*
+        if let Some(reset) = redis
+            .get(&user_password_reset_key(user_id))
+            .await
+            .map_err(AuthError::internal)?
+        {
+            redis
+                .revoke(&password_reset_key(&reset))
+                .await
+                .map_err(AuthError::internal)?;
+        }
+
+        // sessions and refresh tokens live here too, so every token stops working
+        redis
+            .delete_pattern(&user_keys_pattern(user_id))
+            .await
+            .map_err(AuthError::internal)?;
+
+        Ok(())
+    }
+
+    async fn verify_password(&self, user_id: Uuid, password: &str) -> Result<(), AuthError> {
+        let hashed: String = sqlx::query_scalar(
+            r#"
+                SELECT password FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::Unauthorized)?;
+
+        if !bcrypt::verify(password, &hashed).context("failed to verify password hash")? {
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        Ok(())
+    }
+
+    // verifying twice is harmless, the first timestamp is kept
+    pub async fn verify_email(
+        &self,
+        body: VerifyEmailRequest,
+        jwt: &JwtService,
+    ) -> Result<(), AuthError> {
+        let claims = jwt
+            .validate_email_verification_token(&body.token)
+            .map_err(|_| AuthError::InvalidToken)?;
+
+        sqlx::query_scalar::<_, Uuid>(
+            r#"
+                UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW())
+                WHERE id = $1 AND email = $2
+                RETURNING id
+            "#,
+        )
+        .bind(claims.sub)
+        .bind(claims.email)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::InvalidToken)?;
+
+        Ok(())
+    }
+
+    pub async fn resend_verification(
+        &self,
+        user_id: Uuid,
+        jwt: &JwtService,
+        mailer: &MailerService,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        let (email, verified): (String, bool) = sqlx::query_as(
+            r#"
+                SELECT email, email_verified_at IS NOT NULL FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::Unauthorized)?;
+
+        if verified {
+            return Err(AuthError::EmailAlreadyVerified);
+        }
+
+        if !redis
+            .set_nx_ex(
+                &verification_sent_key(user_id),
+                1,
+                VERIFICATION_RESEND_COOLDOWN,
+            )
+            .await
+            .map_err(AuthError::internal)?
+        {
+            return Err(AuthError::VerificationRecentlySent);
+        }
+
+        send_verification(user_id, &email, jwt, mailer).await
+    }
+
+    // only enforced when the deployment sets REQUIRE_VERIFIED_EMAIL
+    pub async fn ensure_verified(&self, user_id: Uuid) -> Result<(), AuthError> {
+        if !self.require_verified_email {
+            return Ok(());
+        }
+
+        let verified: bool = sqlx::query_scalar(
+            r#"
+                SELECT email_verified_at IS NOT NULL FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .unwrap_or_default();
+
+        if !verified {
+            return Err(AuthError::EmailNotVerified);
+        }
+
+        Ok(())
+    }
+
+    // every session is revoked, the handler clears the caller's cookies
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        body: ChangePasswordRequest,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        body.validate()?;
+
+        let user = sqlx::query_as::<_, PasswordQuery>(
+            r#"
+                SELECT email, name, password FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::Unauthorized)?;
+
+        if !bcrypt::verify(&body.current_password, &user.password)
+            .context("failed to verify password hash")?
+        {
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        validate_password(
+            &body.new_password,
+            &[
+                user.email.as_str(),
+                user.name.as_deref().unwrap_or_default(),
+            ],
+        )?;
+
+        self.update_password(user_id, &body.new_password, redis)
+            .await
+    }
+
+    // always succeeds, the response must not reveal which emails are registered
+    pub async fn forgot_password(
+        &self,
+        body: ForgotPasswordRequest,
+        mailer: &MailerService,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        body.validate()?;
+
+        let user = sqlx::query_as::<_, UserQuery>(
+            r#"
+                SELECT email, id FROM users
+                WHERE email = $1
+            "#,
+        )
+        .bind(&body.email)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?;
+
+        let Some(user) = user else {
+            return Ok(());
+        };
+
+        let token = create_token();
+        let token_hash = hash_token(&token);
+
+        // only the latest link stays valid, requesting a new one replaces the old
+        let previous = redis
+            .get(&user_password_reset_key(user.id))
+            .await
+            .map_err(AuthError::internal)?;
+
+        redis
+            .pipeline::<()>(|pipe| {
+                if let Some(previous) = &previous {
+                    pipe.del(password_reset_key(previous));
+                }
+
+                pipe.set_ex(
+                    password_reset_key(&token_hash),
+                    user.id.to_string(),
+                    PASSWORD_RESET_TTL,
+                )
+                .set_ex(
+                    user_password_reset_key(user.id),
+                    &token_hash,
+                    PASSWORD_RESET_TTL,
+                );
+            })
+            .await
+            .map_err(AuthError::internal)?;
+
+        let mail = Mail {
+            to: user.email,
+            subject: "Reset your password".to_owned(),
+            body: format!(
+                "Someone asked to reset the password of your account.\n\n\
+                Open this link within 30 minutes to choose a new one:\n{}\n\n\
+                If it wasn't you, ignore this mail, your password stays the same.",
+                mailer.link("/reset-password", &token)
+            ),
+        };
+
+        if let Err(e) = mailer.send(mail).await {
+            error!(error = ?e, "Failed to send password reset mail.");
+        }
+
+        Ok(())
+    }
+
+    pub async fn reset_password(
+        &self,
+        body: ResetPasswordRequest,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        body.validate()?;
+
+        let key = password_reset_key(&hash_token(&body.token));
+
+        // only looked at here, a password that gets rejected must not use up the link
+        let user_id = redis
+            .get(&key)
+            .await
+            .map_err(AuthError::internal)?
+            .and_then(|id| Uuid::parse_str(&id).ok())
+            .ok_or(AuthError::InvalidToken)?;
+
+        let user = sqlx::query_as::<_, PasswordQuery>(
+            r#"
+                SELECT email, name, password FROM users
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::internal)?
+        .ok_or(AuthError::InvalidToken)?;
+
+        validate_password(
+            &body.new_password,
+            &[
+                user.email.as_str(),
+                user.name.as_deref().unwrap_or_default(),
+            ],
+        )?;
+
+        // GETDEL, of two concurrent resets with the same token only one gets it
+        let taken = redis.take(&key).await.map_err(AuthError::internal)?;
+
+        if taken.as_deref() != Some(user_id.to_string().as_str()) {
+            return Err(AuthError::InvalidToken);
+        }
+
+        redis
+            .revoke(&user_password_reset_key(user_id))
+            .await
+            .map_err(AuthError::internal)?;
+
+        self.update_password(user_id, &body.new_password, redis)
+            .await
+    }
+
+    async fn update_password(
+        &self,
+        user_id: Uuid,
+        password: &str,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        let hashed_password = hash(password, DEFAULT_COST).context("failed to hash password")?;
+
+        sqlx::query(
+            r#"
+                UPDATE users SET password = $2, updated_at = NOW()
+                WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(hashed_password)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthError::internal)?;
+
+        self.logout_all(user_id, redis).await?;
+
+        Ok(())
+    }
+
+    async fn start_session(
+        &self,
+        sub: Uuid,
+        client: ClientInfo,
+        jwt: &JwtService,
+        redis: &RedisService,
+    ) -> Result<(String, String), AuthError> {
+        let now = Utc::now();
+        let session = SessionMeta {
+            id: create_uuid(),
+            created_at: now,
+            last_refresh: now,
+            user_agent: client.user_agent,
+            ip: client.ip,
+            refresh_jti: create_uuid(),
+            previous_jti: None,
+        };
+
+        self.store_session(sub, &session, redis).await?;
+
+        let token = jwt
+            .create_access_token(sub, &session.id)
+            .map_err(AuthError::internal)?;
+        let refresh_token = jwt
+            .create_refresh_token(&session.refresh_jti, sub, &session.id)
+            .map_err(AuthError::internal)?;
+
+        Ok((token, refresh_token))
+    }
+
+    async fn store_session(
+        &self,
+        sub: Uuid,
+        session: &SessionMeta,
+        redis: &RedisService,
+    ) -> Result<(), AuthError> {
+        let json = serde_json::to_string(session).map_err(AuthError::internal)?;
+
+        redis
+            .pipeline::<()>(|pipe| {
+                pipe.set_ex(session_key(sub, &session.id), json, REFRESH_TOKEN_TTL)
+                    .set_ex(
+                        refresh_token_key(sub, &session.refresh_jti),
+                        &session.id,
+                        REFRESH_TOKEN_TTL,
+                    );
+            })
+            .await
+            .map_err(AuthError::internal)
+    }
+
+    async fn get_session(
+        &self,
+        sub: Uuid,
+        session_id: &str,
+        redis: &RedisService,
+    ) -> Result<Option<SessionMeta>, AuthError> {
+        let session = redis
+            .get(&session_key(sub, session_id))
+            .await
+            .map_err(AuthError::internal)?;
+
+        session
+            .map(|s| serde_json::from_str(&s).map_err(AuthError::internal))
+            .transpose()
+    }
+
+    // access tokens of the session stop working too, the middleware checks it exists
+    async fn end_session(
+        &self,
+        sub: Uuid,
+        session_id: &str,
+        redis: &RedisService,
+    ) -> Result<bool, AuthError> {
+        let Some(session) = self.get_session(sub, session_id, redis).await? else {
+            return Ok(false);
+        };
+
+        redis
+            .pipeline::<()>(|pipe| {
+                pipe.del(session_key(sub, &session.id))
+                    .del(refresh_token_key(sub, &session.refresh_jti));
+            })
+            .await
+            .map_err(AuthError::internal)?;
+
+        Ok(true)
+    }
+}
+
+// refresh tokens and their sessions slide forward on every refresh
+const REFRESH_TOKEN_TTL: u64 = 60 * 60 * 24 * 7;
+const REUSE_GRACE_SECONDS: i64 = 10;
+
+// upper bound of an access token lifetime, denylist entries never need to outlive it
+const ACCESS_TOKEN_MAX_TTL: u64 = 60 * 15;
+
+const PASSWORD_RESET_TTL: u64 = 60 * 30;
+const VERIFICATION_RESEND_COOLDOWN: u64 = 60;
+
+const LOGIN_RATE_WINDOW: u64 = 60;
+const LOGIN_IP_LIMIT: u64 = 30;
+const LOGIN_EMAIL_LIMIT: u64 = 10;
+
+const LOCKOUT_THRESHOLD: u32 = 5;
+const BASE_LOCKOUT: u64 = 30;
+const MAX_LOCKOUT: u64 = 60 * 60;
+const LOGIN_FAILURES_TTL: i64 = 60 * 60 * 24;
+
+const LOGIN_CHALLENGE_TTL: u64 = 60 * 5;
+const MAX_CHALLENGE_ATTEMPTS: i64 = 5;
+const RECOVERY_CODE_COUNT: usize = 10;
+const TOTP_ISSUER: &str = "Expense Tracker";
+
+fn totp(secret: &str, email: &str) -> Result<Totp, AuthError> {
+    let secret = Secret::try_from_base32(secret).map_err(AuthError::internal)?;
+
+    Builder::new()
+        .with_secret(secret)
+        .with_account_name(email)
+        .with_issuer(Some(TOTP_ISSUER))
+        .build()
+        .map_err(AuthError::internal)
+}
+
+// a code is accepted once, RFC 6238 leaves replay protection to the caller
+async fn check_totp(
+    user_id: Uuid,
+    secret: &str,
+    code: &str,
+    redis: &RedisService,
+) -> Result<bool, AuthError> {
+    let now = Utc::now().timestamp() as u64;
+
+    let key = totp_last_step_key(user_id);
+    let last_step = redis.get(&key).await.map_err(AuthError::internal)?;
+    let last_step = last_step.and_then(|s| s.parse::<u64>().ok());
+
+    let Some(step) = fresh_step(secret, code, now, last_step)? else {
+        return Ok(false);
+    };
+
+    // skew allows one step on either side, older steps are rejected by the check
+    redis
+        .set(key, step, 60 * 3)
+        .await
+        .map_err(AuthError::internal)?;
+
+    Ok(true)
+}
+
+// the step a code belongs to, unless it was already used or comes before one that was
+fn fresh_step(
+    secret: &str,
+    code: &str,
+    now: u64,
+    last_step: Option<u64>,
+) -> Result<Option<u64>, AuthError> {
+    Ok(totp(secret, "")?
+        .check(code, now)
+        .filter(|step| last_step.is_none_or(|last| *step > last)))
+}
+
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+async fn send_verification(
+    user_id: Uuid,
+    email: &str,
+    jwt: &JwtService,
+    mailer: &MailerService,
+) -> Result<(), AuthError> {
+    let token = jwt
+        .create_email_verification_token(user_id, email)
+        .map_err(AuthError::internal)?;
+
+    let mail = Mail {
+        to: email.to_owned(),
+        subject: "Verify your email".to_owned(),
+        body: format!(
+            "Confirm this address to be able to recover your account.\n\n\
+            Open this link within 24 hours:\n{}",
+            mailer.link("/verify-email", &token)
+        ),
+    };
+
+    mailer.send(mail).await.map_err(AuthError::internal)
+}
+
+async fn deny_access_token(
+    user_id: Uuid,
+    jti: &str,
+    exp: i64,
+    redis: &RedisService,
+) -> Result<(), AuthError> {
+    let ttl = (exp - Utc::now().timestamp()).clamp(1, ACCESS_TOKEN_MAX_TTL as i64);
+
+    redis
+        .set(denied_access_token_key(user_id, jti), 1, ttl as u64)
+        .await
+        .map_err(AuthError::internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP";
+    const NOW: u64 = 1_760_000_000;
+
+    fn code_at(time: u64) -> String {
+        totp(SECRET, "").unwrap().generate(time).to_string()
+    }
+
+    #[test]
+    fn accepts_the_current_step_and_one_either_side() {
+        for offset in [-30i64, 0, 30] {
+            let code = code_at(NOW.saturating_add_signed(offset));
+
+            assert!(fresh_step(SECRET, &code, NOW, None).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn rejects_codes_outside_the_window() {
+        for offset in [-90i64, -60, 60, 90] {
+            let code = code_at(NOW.saturating_add_signed(offset));
+
+            assert_eq!(fresh_step(SECRET, &code, NOW, None).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn rejects_a_replayed_or_older_step() {
+        let step = NOW / 30;
+
+        assert_eq!(
+            fresh_step(SECRET, &code_at(NOW), NOW, Some(step)).unwrap(),
+            None
+        );
+        assert_eq!(
+            fresh_step(SECRET, &code_at(NOW - 30), NOW, Some(step)).unwrap(),
+            None
+        );
+        assert_eq!(
+            fresh_step(SECRET, &code_at(NOW + 30), NOW, Some(step)).unwrap(),
+            Some(step + 1)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        assert_eq!(fresh_step(SECRET, "", NOW, None).unwrap(), None);
+        assert_eq!(fresh_step(SECRET, "12345", NOW, None).unwrap(), None);
+        assert_eq!(fresh_step(SECRET, "abcdef", NOW, None).unwrap(), None);
+    }
 }
@@ -0,0 +1,391 @@
+use chrono::{Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, query_as};
+use uuid::Uuid;
+
+use crate::{
+    errors::budget_errors::BudgetError,
+    models::{
+        budget_models::{
+            BudgetPath, BudgetPeriodParams, BudgetRequest, BudgetResponse, BudgetSpentQuery,
+            BudgetStatus, BudgetStatusCached,
+        },
+        ledger_models::LedgerAccess,
+    },
+    services::{ledger_services::member_ids, redis_services::RedisService},
+    utils::utils::{all_expenses_version_key, budgets_version_key, first_day_of_month},
+};
+
+#[derive(Clone)]
+pub struct BudgetService {
+    pool: PgPool,
+}
+
+impl BudgetService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // budgets are shared by the ledger, every member caches their own status
+    async fn invalidate_budgets(
+        &self,
+        redis: &RedisService,
+        ledger_id: Uuid,
+    ) -> Result<(), BudgetError> {
+        let members = member_ids(&self.pool, ledger_id)
+            .await
+            .map_err(BudgetError::internal)?;
+
+        redis
+            .pipeline::<()>(|pipe| {
+                for &user_id in &members {
+                    pipe.incr(budgets_version_key(user_id), 1);
+                }
+            })
+            .await
+            .map_err(BudgetError::internal)
+    }
+
+    pub async fn set_budget(
+        &self,
+        body: BudgetRequest,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<BudgetResponse, BudgetError> {
+        body.validate()?;
+
+        let mut tx = self.pool.begin().await.map_err(BudgetError::internal)?;
+
+        // setting the same category and month twice replaces the amount
+        let budget = query_as::<_, BudgetResponse>(
+            r#"
+                INSERT INTO budget (user_id, category_id, month, amount, rollover)
+                SELECT $1, id, $3, $4, $5 FROM category
+                WHERE id = $2 AND ledger_id = $6 AND deleted_at IS NULL
+                ON CONFLICT (category_id, month)
+                DO UPDATE SET amount = EXCLUDED.amount,
+                    rollover = EXCLUDED.rollover,
+                    updated_at = NOW()
+                RETURNING id, user_id, category_id, month, amount, rollover
+            "#,
+        )
+        .bind(access.user_id)
+        .bind(body.category_id)
+        .bind(first_day_of_month(body.month))
+        .bind(body.amount)
+        .bind(body.rollover)
+        .bind(access.ledger_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(BudgetError::internal)?
+        .ok_or(BudgetError::ForeignKeyNotFound)?;
+
+        self.invalidate_budgets(redis, access.ledger_id).await?;
+
+        tx.commit().await.map_err(BudgetError::internal)?;
+
+        Ok(budget)
+    }
+
+    pub async fn edit_budget(
+        &self,
+        body: BudgetRequest,
+        path: BudgetPath,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<BudgetResponse, BudgetError> {
+        body.validate()?;
+
+        let mut tx = self.pool.begin().await.map_err(BudgetError::internal)?;
+
+        let budget = query_as::<_, BudgetResponse>(
+            r#"
+                UPDATE budget
+                SET category_id = $3, month = $4, amount = $5,
+                    rollover = $6, updated_at = NOW()
+                WHERE id = $1
+                    AND category_id IN (SELECT id FROM category WHERE ledger_id = $2)
+                    AND EXISTS (
+                        SELECT 1 FROM category
+                        WHERE id = $3 AND ledger_id = $2 AND deleted_at IS NULL
+                    )
+                RETURNING id, user_id, category_id, month, amount, rollover
+            "#,
+        )
+        .bind(path.budget_id)
+        .bind(access.ledger_id)
+        .bind(body.category_id)
+        .bind(first_day_of_month(body.month))
+        .bind(body.amount)
+        .bind(body.rollover)
+        .fetch_optional(&mut *tx)
+        .await;
+
+        let budget = budget
+            .map_err(|e| {
+                if let sqlx::Error::Database(db_err) = &e
+                    && db_err.code().as_deref() == Some("23505")
+                {
+                    return BudgetError::BudgetExisting;
+                }
+
+                BudgetError::internal(e)
+            })?
+            .ok_or(BudgetError::BudgetNotFound)?;
+
+        self.invalidate_budgets(redis, access.ledger_id).await?;
+
+        tx.commit().await.map_err(BudgetError::internal)?;
+
+        Ok(budget)
+    }
+
+    pub async fn delete_budget(
+        &self,
+        path: BudgetPath,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<String, BudgetError> {
+        let mut tx = self.pool.begin().await.map_err(BudgetError::internal)?;
+
+        let (id,): (Uuid,) = query_as(
+            r#"
+                DELETE FROM budget
+                WHERE id = $1
+                    AND category_id IN (SELECT id FROM category WHERE ledger_id = $2)
+                RETURNING id
+            "#,
+        )
+        .bind(path.budget_id)
+        .bind(access.ledger_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(BudgetError::internal)?
+        .ok_or(BudgetError::BudgetNotFound)?;
+
+        self.invalidate_budgets(redis, access.ledger_id).await?;
+
+        tx.commit().await.map_err(BudgetError::internal)?;
+
+        Ok(id.to_string())
+    }
+
+    pub async fn get_budget_status(
+        &self,
+        params: BudgetPeriodParams,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<BudgetStatusCached, BudgetError> {
+        let month = first_day_of_month(params.month.unwrap_or_else(|| Utc::now().date_naive()));
+
+        let b_key = budgets_version_key(access.user_id);
+        let e_key = all_expenses_version_key(access.user_id);
+
+        // spending changes with expenses, so both versions are part of the key
+        let (_, bv, _, ev): (i64, String, i64, String) = redis
+            .pipeline(|pipe| {
+                pipe.set_nx(&b_key, "1")
+                    .get(&b_key)
+                    .set_nx(&e_key, "1")
+                    .get(&e_key);
+            })
+            .await
+            .map_err(BudgetError::internal)?;
+
+        let key = format!(
+            "user:{}:ledger:{}:budgets:v:{}:e:{}:m:{}",
+            access.user_id, access.ledger_id, bv, ev, month
+        );
+
+        if let Some(cached) = redis.get(&key).await.ok().flatten() {
+            let budgets = serde_json::from_str(&cached).map_err(BudgetError::internal)?;
+
+            return Ok(BudgetStatusCached {
+                cached: true,
+                budgets,
+            });
+        }
+
+        // earlier months are needed to carry unused rollover budget forward,
+        // spending of every member is converted to the reader's base currency
+        let rows = query_as::<_, BudgetSpentQuery>(
+            r#"
+                SELECT b.id, b.category_id, c.name AS category_name,
+                    b.month, b.amount, b.rollover,
+                    COALESCE((
+                        SELECT ROUND(SUM(convert_amount(
+                            e.user_id, e.amount, e.currency, u.base_currency, e.date
+                        )), 2)
+                        FROM expense_portion e
+                        WHERE e.category_id = b.category_id
+                            AND e.date >= b.month
+                            AND e.date < b.month + INTERVAL '1 month'
+                            AND e.deleted_at IS NULL
+                    ), 0) AS spent
+                FROM budget b
+                JOIN category c ON c.id = b.category_id
+                JOIN users u ON u.id = $1
+                WHERE c.ledger_id = $3 AND b.month <= $2 AND c.deleted_at IS NULL
+                ORDER BY b.category_id, b.month
+            "#,
+        )
+        .bind(access.user_id)
+        .bind(month)
+        .bind(access.ledger_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(BudgetError::conversion)?;
+
+        let budgets = budget_statuses(rows, month);
+
+        let json = serde_json::to_string(&budgets).map_err(BudgetError::internal)?;
+
+        redis
+            .set(key, json, 300)
+            .await
+            .map_err(BudgetError::internal)?;
+
+        Ok(BudgetStatusCached {
+            cached: false,
+            budgets,
+        })
+    }
+}
+
+// rows must be ordered by category then month; a gap month breaks the rollover chain
+fn budget_statuses(rows: Vec<BudgetSpentQuery>, month: NaiveDate) -> Vec<BudgetStatus> {
+    let mut statuses = Vec::new();
+    let mut previous: Option<(Uuid, NaiveDate, bool, Decimal)> = None;
+
+    for row in rows {
+        let rolled_over = match previous {
+            Some((category_id, prev_month, true, left))
+                if category_id == row.category_id
+                    && prev_month.checked_add_months(Months::new(1)) == Some(row.month) =>
+            {
+                left.max(Decimal::ZERO)
+            }
+            _ => Decimal::ZERO,
+        };
+
+        let available = row.amount + rolled_over;
+        let remaining = available - row.spent;
+
+        previous = Some((row.category_id, row.month, row.rollover, remaining));
+
+        if row.month != month {
+            continue;
+        }
+
+        statuses.push(BudgetStatus {
+            budget_id: row.id,
+            category_id: row.category_id,
+            category_name: row.category_name,
+            month: row.month,
+            amount: row.amount,
+            rolled_over,
+            available,
+            spent: row.spent,
+            remaining,
+            over_budget: remaining < Decimal::ZERO,
+        });
+    }
+
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(
+        category: u128,
+        month: u32,
+        amount: i64,
+        spent: i64,
+        rollover: bool,
+    ) -> BudgetSpentQuery {
+        BudgetSpentQuery {
+            id: Uuid::new_v4(),
+            category_id: Uuid::from_u128(category),
+            category_name: "Groceries".to_owned(),
+            month: NaiveDate::from_ymd_opt(2026, month, 1).unwrap(),
+            amount: Decimal::from(amount),
+            rollover,
+            spent: Decimal::from(spent),
+        }
+    }
+
+    fn october() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 1).unwrap()
+    }
+
+    #[test]
+    fn carries_unused_budget_along_a_contiguous_chain() {
+        // 40 left in august, 140 available and 60 left in september
+        let rows = vec![
+            row(1, 8, 100, 60, true),
+            row(1, 9, 100, 80, true),
+            row(1, 10, 100, 30, true),
+        ];
+
+        let statuses = budget_statuses(rows, october());
+
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].month, october());
+        assert_eq!(statuses[0].rolled_over, Decimal::from(60));
+        assert_eq!(statuses[0].available, Decimal::from(160));
+        assert_eq!(statuses[0].remaining, Decimal::from(130));
+        assert!(!statuses[0].over_budget);
+    }
+
+    #[test]
+    fn a_gap_month_breaks_the_chain() {
+        let rows = vec![row(1, 8, 100, 0, true), row(1, 10, 100, 30, true)];
+
+        let statuses = budget_statuses(rows, october());
+
+        assert_eq!(statuses[0].rolled_over, Decimal::ZERO);
+        assert_eq!(statuses[0].available, Decimal::from(100));
+        assert_eq!(statuses[0].remaining, Decimal::from(70));
+    }
+
+    #[test]
+    fn an_overspent_month_rolls_over_nothing() {
+        let rows = vec![row(1, 9, 100, 150, true), row(1, 10, 100, 120, true)];
+
+        let statuses = budget_statuses(rows, october());
+
+        assert_eq!(statuses[0].rolled_over, Decimal::ZERO);
+        assert_eq!(statuses[0].available, Decimal::from(100));
+        assert_eq!(statuses[0].remaining, Decimal::from(-20));
+        assert!(statuses[0].over_budget);
+    }
+
+    #[test]
+    fn nothing_rolls_over_from_a_month_without_rollover() {
+        let rows = vec![row(1, 9, 100, 20, false), row(1, 10, 100, 30, true)];
+
+        let statuses = budget_statuses(rows, october());
+
+        assert_eq!(statuses[0].rolled_over, Decimal::ZERO);
+        assert_eq!(statuses[0].remaining, Decimal::from(70));
+    }
+
+    #[test]
+    fn chains_stay_within_their_category() {
+        let rows = vec![
+            row(1, 9, 100, 20, true),
+            row(2, 10, 50, 10, true),
+            row(3, 10, 70, 0, false),
+        ];
+
+        let statuses = budget_statuses(rows, october());
+
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].category_id, Uuid::from_u128(2));
+        assert_eq!(statuses[0].rolled_over, Decimal::ZERO);
+        assert_eq!(statuses[1].category_id, Uuid::from_u128(3));
+        assert_eq!(statuses[1].remaining, Decimal::from(70));
+    }
+}
@@ -1,11 +1,28 @@
-use sqlx::{PgPool, query_as};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, query, query_as, query_scalar, types::Json};
 use uuid::Uuid;
 
 use crate::{
     errors::category_errors::CategoryError,
-    models::category_models::{CategoriesCached, Category, CategoryPagination, CategoryResponse},
-    services::redis_services::RedisService,
-    utils::utils::categories_version_key,
+    models::{
+        category_models::{
+            CategoriesCached, Category, CategoryCached, CategoryDeleted, CategoryPagination,
+            CategoryPath, CategoryResponse, CategoryRestored, DeleteCategoryParams,
+            TrashedCategoryResponse,
+        },
+        expense_model::{ExpenseHistoryAction, ExpenseResponse, ExpenseSplit},
+        ledger_models::LedgerAccess,
+    },
+    services::{
+        expense_services::{EXPENSE_SPLITS, record_history},
+        ledger_services::member_ids,
+        redis_services::RedisService,
+    },
+    utils::utils::{
+        all_expenses_version_key, budgets_version_key, categories_version_key,
+        category_filter_expenses_version_key, category_filter_total_expense_key,
+        single_category_key, single_expense_key, total_expense_key,
+    },
 };
 
 #[derive(Clone)]
@@ -18,11 +35,18 @@ impl CategoryService {
         Self { pool }
     }
 
+    // every member caches their own view of the ledger
+    async fn members(&self, ledger_id: Uuid) -> Result<Vec<Uuid>, CategoryError> {
+        member_ids(&self.pool, ledger_id)
+            .await
+            .map_err(CategoryError::internal)
+    }
+
     pub async fn add_category(
         &self,
         body: Category,
         redis: &RedisService,
-        user_id: uuid::Uuid,
+        access: &LedgerAccess,
     ) -> Result<CategoryResponse, CategoryError> {
         body.validate()?;
 
@@ -30,28 +54,36 @@ impl CategoryService {
 
         let category = query_as::<_, CategoryResponse>(
             r#"
-                INSERT INTO category (name, user_id)
-                VALUES ($1, $2)
+                INSERT INTO category (name, description, user_id, ledger_id)
+                VALUES ($1, $2, $3, $4)
                 RETURNING id, description, name, user_id
             "#,
         )
         .bind(body.name)
-        .bind(user_id)
+        .bind(body.description)
+        .bind(access.user_id)
+        .bind(access.ledger_id)
         .fetch_one(&mut *tx)
         .await;
 
         let category = category.map_err(|e| {
-            if let sqlx::Error::Database(db_err) = &e {
-                if db_err.code().as_deref() == Some("23505") {
-                    return CategoryError::NameExisting;
-                }
+            if let sqlx::Error::Database(db_err) = &e
+                && db_err.code().as_deref() == Some("23505")
+            {
+                return CategoryError::NameExisting;
             }
 
             CategoryError::internal(e)
         })?;
 
+        let members = self.members(access.ledger_id).await?;
+
         redis
-            .incr(&categories_version_key(user_id))
+            .pipeline::<()>(|pipe| {
+                for &user_id in &members {
+                    pipe.incr(categories_version_key(user_id), 1);
+                }
+            })
             .await
             .map_err(CategoryError::internal)?;
 
@@ -64,12 +96,12 @@ impl CategoryService {
         &self,
         params: CategoryPagination,
         redis: &RedisService,
-        user_id: Uuid,
+        access: &LedgerAccess,
     ) -> Result<CategoriesCached, CategoryError> {
         let limit = 10;
         let page = params.page.max(1);
         let offset = (page - 1) * limit;
-        let key = categories_version_key(user_id);
+        let key = categories_version_key(access.user_id);
 
         let (_, v): (i64, String) = redis
             .pipeline(|pipe| {
@@ -78,7 +110,10 @@ impl CategoryService {
             .await
             .map_err(CategoryError::internal)?;
 
-        let key = format!("user:{}:v:{}:p:{}", user_id, v, page);
+        let key = format!(
+            "user:{}:ledger:{}:v:{}:p:{}",
+            access.user_id, access.ledger_id, v, page
+        );
 
         if let Some(cached) = redis.get(&key).await.ok().flatten() {
             let json: Vec<CategoryResponse> =
@@ -93,12 +128,12 @@ impl CategoryService {
         let categories: Vec<CategoryResponse> = query_as(
             r#"
                 SELECT id, description, name, user_id FROM category
-                WHERE user_id = $1
+                WHERE ledger_id = $1 AND deleted_at IS NULL
                 ORDER BY updated_at DESC
                 LIMIT $2 OFFSET $3
             "#,
         )
-        .bind(user_id)
+        .bind(access.ledger_id)
         .bind(limit)
         .bind(offset)
         .fetch_all(&self.pool)
@@ -117,4 +152,480 @@ impl CategoryService {
             categories,
         })
     }
+
+    pub async fn get_single_category(
+        &self,
+        path: CategoryPath,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<CategoryCached, CategoryError> {
+        let key = single_category_key(path.category_id, access.ledger_id, access.user_id);
+
+        if let Some(cached) = redis.get(&key).await.ok().flatten() {
+            let category = serde_json::from_str(&cached).map_err(CategoryError::internal)?;
+
+            return Ok(CategoryCached {
+                cached: true,
+                category,
+            });
+        }
+
+        let category = query_as::<_, CategoryResponse>(
+            r#"
+                SELECT id, description, name, user_id FROM category
+                WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(path.category_id)
+        .bind(access.ledger_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(CategoryError::internal)?
+        .ok_or(CategoryError::CategoryNotFound)?;
+
+        let json = serde_json::to_string(&category).map_err(CategoryError::internal)?;
+
+        redis
+            .set(key, json, 300)
+            .await
+            .map_err(CategoryError::internal)?;
+
+        Ok(CategoryCached {
+            cached: false,
+            category,
+        })
+    }
+
+    pub async fn edit_category(
+        &self,
+        body: Category,
+        path: CategoryPath,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<CategoryResponse, CategoryError> {
+        body.validate()?;
+
+        let mut tx = self.pool.begin().await.map_err(CategoryError::internal)?;
+
+        let category = query_as::<_, CategoryResponse>(
+            r#"
+                UPDATE category
+                SET name = $3, description = $4, updated_at = NOW()
+                WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL
+                RETURNING id, description, name, user_id
+            "#,
+        )
+        .bind(path.category_id)
+        .bind(access.ledger_id)
+        .bind(body.name)
+        .bind(body.description)
+        .fetch_optional(&mut *tx)
+        .await;
+
+        let category = category
+            .map_err(|e| {
+                if let sqlx::Error::Database(db_err) = &e
+                    && db_err.code().as_deref() == Some("23505")
+                {
+                    return CategoryError::NameExisting;
+                }
+
+                CategoryError::internal(e)
+            })?
+            .ok_or(CategoryError::CategoryNotFound)?;
+
+        let members = self.members(access.ledger_id).await?;
+
+        redis
+            .pipeline::<()>(|pipe| {
+                for &user_id in &members {
+                    pipe.incr(categories_version_key(user_id), 1)
+                        .incr(budgets_version_key(user_id), 1)
+                        .del(single_category_key(
+                            path.category_id,
+                            access.ledger_id,
+                            user_id,
+                        ));
+                }
+            })
+            .await
+            .map_err(CategoryError::internal)?;
+
+        tx.commit().await.map_err(CategoryError::internal)?;
+
+        Ok(category)
+    }
+
+    pub async fn delete_category(
+        &self,
+        params: DeleteCategoryParams,
+        path: CategoryPath,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<CategoryDeleted, CategoryError> {
+        let category_id = path.category_id;
+
+        if params.reassign_to.is_none() && !params.delete_expenses {
+            return Err(CategoryError::DeleteTargetRequired);
+        }
+
+        if params.reassign_to == Some(category_id) {
+            return Err(CategoryError::ReassignTargetInvalid);
+        }
+
+        let mut tx = self.pool.begin().await.map_err(CategoryError::internal)?;
+
+        let moved: Vec<ExpenseResponse> = if let Some(target) = params.reassign_to {
+            let target_exists: bool = query_scalar(
+                r#"
+                    SELECT EXISTS (
+                        SELECT 1 FROM category
+                        WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL
+                    )
+                "#,
+            )
+            .bind(target)
+            .bind(access.ledger_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(CategoryError::internal)?;
+
+            if !target_exists {
+                return Err(CategoryError::ReassignTargetInvalid);
+            }
+
+            // split lines in the category move along with the expenses they belong to
+            let sql = format!(
+                r#"
+                    SELECT id, amount, currency, description, user_id,
+                        category_id, date, account_id,
+                        is_recurring, tags, {EXPENSE_SPLITS} FROM expense
+                    WHERE ledger_id = $2 AND deleted_at IS NULL
+                        AND (category_id = $1 OR EXISTS (
+                            SELECT 1 FROM expense_split s
+                            WHERE s.expense_id = expense.id AND s.category_id = $1
+                        ))
+                    FOR UPDATE
+                "#
+            );
+
+            let before = query_as::<_, ExpenseResponse>(&sql)
+                .bind(category_id)
+                .bind(access.ledger_id)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(CategoryError::internal)?;
+
+            let ids: Vec<Uuid> = before.iter().map(|e| e.id).collect();
+
+            query(
+                r#"
+                    UPDATE expense
+                    SET category_id = $3, updated_at = NOW()
+                    WHERE id = ANY($2) AND category_id = $1
+                "#,
+            )
+            .bind(category_id)
+            .bind(&ids)
+            .bind(target)
+            .execute(&mut *tx)
+            .await
+            .map_err(CategoryError::internal)?;
+
+            query(
+                r#"
+                    UPDATE expense_split SET category_id = $3
+                    WHERE expense_id = ANY($2) AND category_id = $1
+                "#,
+            )
+            .bind(category_id)
+            .bind(&ids)
+            .bind(target)
+            .execute(&mut *tx)
+            .await
+            .map_err(CategoryError::internal)?;
+
+            let reassign = |id: Uuid| if id == category_id { target } else { id };
+            let mut moved = Vec::with_capacity(before.len());
+
+            for before in before {
+                let expense = ExpenseResponse {
+                    category_id: reassign(before.category_id),
+                    splits: Json(
+                        before
+                            .splits
+                            .iter()
+                            .map(|s| ExpenseSplit {
+                                category_id: reassign(s.category_id),
+                                ..s.clone()
+                            })
+                            .collect(),
+                    ),
+                    ..before.clone()
+                };
+
+                record_history(
+                    &mut tx,
+                    ExpenseHistoryAction::Updated,
+                    Some(&before),
+                    Some(&expense),
+                    access.user_id,
+                )
+                .await
+                .map_err(CategoryError::internal)?;
+
+                moved.push(expense);
+            }
+
+            moved
+        } else {
+            // NOW() is fixed for the transaction, restoring the category matches on it,
+            // expenses with a split line in the category go to the trash too
+            let sql = format!(
+                r#"
+                    UPDATE expense SET deleted_at = NOW()
+                    WHERE ledger_id = $2 AND deleted_at IS NULL
+                        AND (category_id = $1 OR EXISTS (
+                            SELECT 1 FROM expense_split s
+                            WHERE s.expense_id = expense.id AND s.category_id = $1
+                        ))
+                    RETURNING id, amount, currency, description, user_id,
+                        category_id, date, account_id,
+                        is_recurring, tags, {EXPENSE_SPLITS}
+                "#
+            );
+
+            let trashed = query_as::<_, ExpenseResponse>(&sql)
+                .bind(category_id)
+                .bind(access.ledger_id)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(CategoryError::internal)?;
+
+            for expense in &trashed {
+                record_history(
+                    &mut tx,
+                    ExpenseHistoryAction::Deleted,
+                    Some(expense),
+                    None,
+                    access.user_id,
+                )
+                .await
+                .map_err(CategoryError::internal)?;
+            }
+
+            trashed
+        };
+
+        // budgets and schedules stay with the trashed category until it's purged
+        let id: Uuid = query_scalar(
+            r#"
+                UPDATE category SET deleted_at = NOW()
+                WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL
+                RETURNING id
+            "#,
+        )
+        .bind(category_id)
+        .bind(access.ledger_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(CategoryError::internal)?
+        .ok_or(CategoryError::CategoryNotFound)?;
+
+        let mut categories: Vec<Uuid> = moved.iter().flat_map(|e| e.category_ids()).collect();
+        categories.sort();
+        categories.dedup();
+
+        let members = self.members(access.ledger_id).await?;
+
+        redis
+            .pipeline::<()>(|pipe| {
+                for &user_id in &members {
+                    for expense in &moved {
+                        pipe.del(single_expense_key(expense.id, access.ledger_id, user_id));
+                    }
+
+                    for &id in &categories {
+                        pipe.incr(category_filter_expenses_version_key(id, user_id), 1)
+                            .del(category_filter_total_expense_key(id, user_id));
+                    }
+
+                    pipe.incr(categories_version_key(user_id), 1)
+                        .incr(budgets_version_key(user_id), 1)
+                        .del(single_category_key(category_id, access.ledger_id, user_id))
+                        .incr(all_expenses_version_key(user_id), 1)
+                        .del(total_expense_key(access.ledger_id, user_id))
+                        .incr(
+                            category_filter_expenses_version_key(category_id, user_id),
+                            1,
+                        )
+                        .del(category_filter_total_expense_key(category_id, user_id));
+
+                    if let Some(target) = params.reassign_to {
+                        pipe.incr(category_filter_expenses_version_key(target, user_id), 1)
+                            .del(category_filter_total_expense_key(target, user_id));
+                    }
+                }
+            })
+            .await
+            .map_err(CategoryError::internal)?;
+
+        tx.commit().await.map_err(CategoryError::internal)?;
+
+        Ok(CategoryDeleted {
+            id,
+            reassigned_to: params.reassign_to,
+            expenses_affected: moved.len(),
+        })
+    }
+
+    pub async fn get_trashed_categories(
+        &self,
+        params: CategoryPagination,
+        access: &LedgerAccess,
+    ) -> Result<Vec<TrashedCategoryResponse>, CategoryError> {
+        let limit = 10;
+        let page = params.page.max(1);
+        let offset = (page - 1) * limit;
+
+        query_as::<_, TrashedCategoryResponse>(
+            r#"
+                SELECT id, description, name, user_id, deleted_at FROM category
+                WHERE ledger_id = $1 AND deleted_at IS NOT NULL
+                ORDER BY deleted_at DESC
+                LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(access.ledger_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(CategoryError::internal)
+    }
+
+    // expenses trashed along with the category come back with it
+    pub async fn restore_category(
+        &self,
+        path: CategoryPath,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<CategoryRestored, CategoryError> {
+        let category_id = path.category_id;
+
+        let mut tx = self.pool.begin().await.map_err(CategoryError::internal)?;
+
+        let deleted_at: DateTime<Utc> = query_scalar(
+            r#"
+                SELECT deleted_at FROM category
+                WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NOT NULL
+                FOR UPDATE
+            "#,
+        )
+        .bind(category_id)
+        .bind(access.ledger_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(CategoryError::internal)?
+        .ok_or(CategoryError::CategoryNotFound)?;
+
+        let category = query_as::<_, CategoryResponse>(
+            r#"
+                UPDATE category
+                SET deleted_at = NULL, updated_at = NOW()
+                WHERE id = $1 AND ledger_id = $2
+                RETURNING id, description, name, user_id
+            "#,
+        )
+        .bind(category_id)
+        .bind(access.ledger_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            if let sqlx::Error::Database(db_err) = &e
+                && db_err.code().as_deref() == Some("23505")
+            {
+                return CategoryError::NameExisting;
+            }
+
+            CategoryError::internal(e)
+        })?;
+
+        // an expense split into another category still in the trash waits for that one
+        let sql = format!(
+            r#"
+                UPDATE expense
+                SET deleted_at = NULL, updated_at = NOW()
+                WHERE ledger_id = $2 AND deleted_at = $3
+                    AND (category_id = $1 OR EXISTS (
+                        SELECT 1 FROM expense_split s
+                        WHERE s.expense_id = expense.id AND s.category_id = $1
+                    ))
+                    AND NOT EXISTS (
+                        SELECT 1 FROM expense_portion p
+                        JOIN category c ON c.id = p.category_id
+                        WHERE p.expense_id = expense.id AND c.deleted_at IS NOT NULL
+                    )
+                RETURNING id, amount, currency, description, user_id,
+                    category_id, date, account_id,
+                    is_recurring, tags, {EXPENSE_SPLITS}
+            "#
+        );
+
+        let restored = query_as::<_, ExpenseResponse>(&sql)
+            .bind(category_id)
+            .bind(access.ledger_id)
+            .bind(deleted_at)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(CategoryError::internal)?;
+
+        for expense in &restored {
+            record_history(
+                &mut tx,
+                ExpenseHistoryAction::Restored,
+                None,
+                Some(expense),
+                access.user_id,
+            )
+            .await
+            .map_err(CategoryError::internal)?;
+        }
+
+        let mut categories: Vec<Uuid> = restored.iter().flat_map(|e| e.category_ids()).collect();
+        categories.sort();
+        categories.dedup();
+
+        let members = self.members(access.ledger_id).await?;
+
+        redis
+            .pipeline::<()>(|pipe| {
+                for &user_id in &members {
+                    for &id in &categories {
+                        pipe.incr(category_filter_expenses_version_key(id, user_id), 1)
+                            .del(category_filter_total_expense_key(id, user_id));
+                    }
+
+                    pipe.incr(categories_version_key(user_id), 1)
+                        .incr(budgets_version_key(user_id), 1)
+                        .del(single_category_key(category_id, access.ledger_id, user_id))
+                        .incr(all_expenses_version_key(user_id), 1)
+                        .del(total_expense_key(access.ledger_id, user_id))
+                        .incr(
+                            category_filter_expenses_version_key(category_id, user_id),
+                            1,
+                        )
+                        .del(category_filter_total_expense_key(category_id, user_id));
+                }
+            })
+            .await
+            .map_err(CategoryError::internal)?;
+
+        tx.commit().await.map_err(CategoryError::internal)?;
+
+        Ok(CategoryRestored {
+            category,
+            expenses_restored: restored.len(),
+        })
+    }
 }
@@ -0,0 +1,442 @@
+use anyhow::Context;
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, Trim};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, query, query_as, query_scalar};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    errors::currency_errors::CurrencyError,
+    models::currency_models::{
+        BaseCurrencyRequest, BaseCurrencyResponse, ExchangeRateParams, ExchangeRatePath,
+        ExchangeRateResponse, ManualRateRequest,
+    },
+    services::{ledger_services::co_member_ids, redis_services::RedisService},
+    utils::utils::{
+        all_expenses_version_key, category_filter_totals_pattern, is_currency_code,
+        total_expenses_pattern,
+    },
+};
+
+const RATES_PER_STATEMENT: usize = 5000;
+
+type Rate = (String, NaiveDate, Decimal);
+
+#[derive(Clone)]
+pub struct CurrencyService {
+    pool: PgPool,
+}
+
+impl CurrencyService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // every cached sum is in the old base currency or used the old rate
+    async fn invalidate_totals(
+        &self,
+        redis: &RedisService,
+        user_ids: &[Uuid],
+    ) -> Result<(), CurrencyError> {
+        for &user_id in user_ids {
+            redis
+                .incr(&all_expenses_version_key(user_id))
+                .await
+                .map_err(CurrencyError::internal)?;
+
+            redis
+                .delete_pattern(&total_expenses_pattern(user_id))
+                .await
+                .map_err(CurrencyError::internal)?;
+
+            redis
+                .delete_pattern(&category_filter_totals_pattern(user_id))
+                .await
+                .map_err(CurrencyError::internal)?;
+        }
+
+        Ok(())
+    }
+
+    // expenses are converted with their author's manual rates, shared ledgers included
+    async fn invalidate_shared_totals(
+        &self,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<(), CurrencyError> {
+        let users = co_member_ids(&self.pool, user_id)
+            .await
+            .map_err(CurrencyError::internal)?;
+
+        self.invalidate_totals(redis, &users).await
+    }
+
+    pub async fn set_base_currency(
+        &self,
+        body: BaseCurrencyRequest,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<BaseCurrencyResponse, CurrencyError> {
+        body.validate()?;
+
+        let currency: String = query_scalar(
+            r#"
+                UPDATE users SET base_currency = $2, updated_at = NOW()
+                WHERE id = $1
+                RETURNING base_currency
+            "#,
+        )
+        .bind(user_id)
+        .bind(body.currency)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(CurrencyError::internal)?;
+
+        self.invalidate_totals(redis, &[user_id]).await?;
+
+        Ok(BaseCurrencyResponse { currency })
+    }
+
+    pub async fn add_manual_rate(
+        &self,
+        body: ManualRateRequest,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<ExchangeRateResponse, CurrencyError> {
+        body.validate()?;
+
+        // euros have no rate of their own, 1 USD = 0.92 EUR is stored as the USD rate
+        let (currency, base, rate) = if body.currency == "EUR" {
+            let rate = Decimal::ONE
+                .checked_div(body.rate)
+                .ok_or(CurrencyError::InvalidRate)?;
+
+            (body.base, body.currency, rate)
+        } else {
+            (body.currency, body.base, body.rate)
+        };
+
+        let rate = query_as::<_, ExchangeRateResponse>(
+            r#"
+                INSERT INTO exchange_rate (user_id, currency, date, rate)
+                VALUES ($1, $2, $4, $5 * eur_rate($1, $3, $4))
+                ON CONFLICT (user_id, currency, date) WHERE user_id IS NOT NULL
+                DO UPDATE SET rate = EXCLUDED.rate, updated_at = NOW()
+                RETURNING id, currency, date, rate, true AS manual
+            "#,
+        )
+        .bind(user_id)
+        .bind(currency)
+        .bind(base)
+        .bind(body.date)
+        .bind(rate)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(CurrencyError::conversion)?;
+
+        self.invalidate_shared_totals(redis, user_id).await?;
+
+        Ok(rate)
+    }
+
+    pub async fn get_rates(
+        &self,
+        params: ExchangeRateParams,
+        user_id: Uuid,
+    ) -> Result<Vec<ExchangeRateResponse>, CurrencyError> {
+        params.validate()?;
+
+        let limit: i64 = 50;
+        let page = params.page.max(1);
+        let offset = (page - 1) * limit;
+
+        query_as::<_, ExchangeRateResponse>(
+            r#"
+                SELECT id, currency, date, rate, user_id IS NOT NULL AS manual
+                FROM exchange_rate
+                WHERE (user_id IS NULL OR user_id = $1)
+                    AND ($2::varchar IS NULL OR currency = $2)
+                    AND ($3::date IS NULL OR date >= $3)
+                    AND ($4::date IS NULL OR date <= $4)
+                ORDER BY date DESC, currency, manual DESC
+                LIMIT $5 OFFSET $6
+            "#,
+        )
+        .bind(user_id)
+        .bind(params.currency)
+        .bind(params.from)
+        .bind(params.to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(CurrencyError::internal)
+    }
+
+    // published rates are shared and can only be replaced by loading a new file
+    pub async fn delete_rate(
+        &self,
+        path: ExchangeRatePath,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<String, CurrencyError> {
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM exchange_rate
+                WHERE id = $1 AND user_id = $2
+                RETURNING id
+            "#,
+        )
+        .bind(path.rate_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(CurrencyError::internal)?
+        .ok_or(CurrencyError::RateNotFound)?;
+
+        self.invalidate_shared_totals(redis, user_id).await?;
+
+        Ok(id.to_string())
+    }
+
+    // cached totals pick up new published rates when they expire
+    pub async fn load_file(&self, path: &Path) -> anyhow::Result<usize> {
+        let file = path.to_owned();
+        let content = actix_web::web::block(move || std::fs::read(file))
+            .await?
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        let rates = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("xml") => {
+                parse_ecb_xml(std::str::from_utf8(&content)?)?
+            }
+            _ => parse_rates_csv(&content)?,
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        for chunk in rates.chunks(RATES_PER_STATEMENT) {
+            let mut currencies = Vec::with_capacity(chunk.len());
+            let mut dates = Vec::with_capacity(chunk.len());
+            let mut values = Vec::with_capacity(chunk.len());
+
+            for (currency, date, rate) in chunk {
+                currencies.push(currency.as_str());
+                dates.push(*date);
+                values.push(*rate);
+            }
+
+            query(
+                r#"
+                    INSERT INTO exchange_rate (currency, date, rate)
+                    SELECT * FROM UNNEST($1::varchar[], $2::date[], $3::numeric[])
+                    ON CONFLICT (currency, date) WHERE user_id IS NULL
+                    DO UPDATE SET rate = EXCLUDED.rate, updated_at = NOW()
+                    WHERE exchange_rate.rate <> EXCLUDED.rate
+                "#,
+            )
+            .bind(currencies)
+            .bind(dates)
+            .bind(values)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(rates.len())
+    }
+
+    // the file is read again on every tick so a cron job can drop in the daily feed
+    pub fn spawn_loader(self, path: PathBuf, period: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(period);
+
+            loop {
+                interval.tick().await;
+
+                match self.load_file(&path).await {
+                    Ok(loaded) => info!(loaded, path = %path.display(), "Loaded exchange rates."),
+                    Err(e) => {
+                        error!(error = ?e, path = %path.display(), "Exchange rate loader failed.")
+                    }
+                }
+            }
+        });
+    }
+}
+
+// eurofxref-daily.xml and eurofxref-hist.xml: <Cube time="..."><Cube currency="USD" rate="..."/>
+fn parse_ecb_xml(content: &str) -> anyhow::Result<Vec<Rate>> {
+    let document = roxmltree::Document::parse(content).context("invalid rate XML")?;
+    let mut rates = Vec::new();
+
+    for day in document
+        .descendants()
+        .filter(|n| n.has_tag_name("Cube") && n.has_attribute("time"))
+    {
+        let date = day.attribute("time").unwrap_or_default();
+        let date = NaiveDate::from_str(date).with_context(|| format!("invalid date {date}"))?;
+
+        for cube in day.children().filter(|n| n.has_tag_name("Cube")) {
+            let (Some(currency), Some(rate)) = (cube.attribute("currency"), cube.attribute("rate"))
+            else {
+                continue;
+            };
+
+            rates.push(rate_entry(currency, date, rate)?);
+        }
+    }
+
+    Ok(rates)
+}
+
+// either the ECB layout (Date,USD,JPY,... one row per day) or date,currency,rate rows
+fn parse_rates_csv(content: &[u8]) -> anyhow::Result<Vec<Rate>> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(content);
+
+    let headers = reader.headers().context("invalid rate CSV")?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+
+    let mut rates = Vec::new();
+
+    if let (Some(date_col), Some(currency_col), Some(rate_col)) =
+        (column("date"), column("currency"), column("rate"))
+    {
+        for record in reader.records() {
+            let record = record.context("invalid rate CSV")?;
+            let field = |i: usize| record.get(i).unwrap_or_default();
+
+            rates.push(rate_entry(
+                field(currency_col),
+                parse_date(field(date_col))?,
+                field(rate_col),
+            )?);
+        }
+
+        return Ok(rates);
+    }
+
+    for record in reader.records() {
+        let record = record.context("invalid rate CSV")?;
+        let date = parse_date(record.get(0).unwrap_or_default())?;
+
+        // the ECB marks currencies that weren't quoted yet (or anymore) as N/A
+        for (currency, rate) in headers.iter().zip(record.iter()).skip(1) {
+            if currency.is_empty() || rate.is_empty() || rate == "N/A" {
+                continue;
+            }
+
+            rates.push(rate_entry(currency, date, rate)?);
+        }
+    }
+
+    Ok(rates)
+}
+
+fn parse_date(date: &str) -> anyhow::Result<NaiveDate> {
+    NaiveDate::from_str(date).with_context(|| format!("invalid date {date}"))
+}
+
+fn rate_entry(currency: &str, date: NaiveDate, rate: &str) -> anyhow::Result<Rate> {
+    let currency = currency.to_uppercase();
+
+    if !is_currency_code(&currency) {
+        anyhow::bail!("invalid currency {currency}");
+    }
+
+    let rate = Decimal::from_str(rate).with_context(|| format!("invalid rate {rate}"))?;
+
+    if rate <= Decimal::ZERO {
+        anyhow::bail!("invalid rate {rate} for {currency}");
+    }
+
+    Ok((currency, date, rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(currency: &str, date: &str, rate: &str) -> Rate {
+        (
+            currency.to_owned(),
+            NaiveDate::from_str(date).unwrap(),
+            Decimal::from_str(rate).unwrap(),
+        )
+    }
+
+    #[test]
+    fn parses_ecb_xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+                <Cube>
+                    <Cube time="2026-10-16">
+                        <Cube currency="USD" rate="1.1650"/>
+                        <Cube currency="JPY" rate="175.42"/>
+                    </Cube>
+                    <Cube time="2026-10-15">
+                        <Cube currency="USD" rate="1.1612"/>
+                    </Cube>
+                </Cube>
+            </gesmes:Envelope>"#;
+
+        assert_eq!(
+            parse_ecb_xml(xml).unwrap(),
+            [
+                rate("USD", "2026-10-16", "1.1650"),
+                rate("JPY", "2026-10-16", "175.42"),
+                rate("USD", "2026-10-15", "1.1612"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        assert!(parse_ecb_xml("<Cube><Cube time=").is_err());
+        assert!(parse_ecb_xml(r#"<Cube time="yesterday"/>"#).is_err());
+    }
+
+    #[test]
+    fn parses_ecb_csv_skipping_unquoted_currencies() {
+        let csv = "Date, USD, JPY, CYP, \n2026-10-16, 1.1650, 175.42, N/A, \n";
+
+        assert_eq!(
+            parse_rates_csv(csv.as_bytes()).unwrap(),
+            [
+                rate("USD", "2026-10-16", "1.1650"),
+                rate("JPY", "2026-10-16", "175.42"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_long_csv_in_any_column_order() {
+        let csv = "rate,currency,date\n1.1650,usd,2026-10-16\n0.8712,GBP,2026-10-16\n";
+
+        assert_eq!(
+            parse_rates_csv(csv.as_bytes()).unwrap(),
+            [
+                rate("USD", "2026-10-16", "1.1650"),
+                rate("GBP", "2026-10-16", "0.8712"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_csv_values() {
+        assert!(parse_rates_csv(b"date,currency,rate\n2026-10-16,USD,0\n").is_err());
+        assert!(parse_rates_csv(b"date,currency,rate\n2026-10-16,DOLLAR,1.1\n").is_err());
+        assert!(parse_rates_csv(b"date,currency,rate\n16/10/2026,USD,1.1\n").is_err());
+        assert!(parse_rates_csv(b"Date,USD\n2026-10-16,abc\n").is_err());
+    }
+}
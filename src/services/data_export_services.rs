@@ -0,0 +1,441 @@
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{PgPool, query, query_as, query_scalar};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{error, info};
+use uuid::Uuid;
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    errors::data_export_errors::DataExportError,
+    models::{
+        account_models::{AccountResponse, TransferResponse},
+        budget_models::BudgetResponse,
+        category_models::CategoryResponse,
+        data_export_models::{DataExportPath, DataExportResponse, DataExportStatus},
+        expense_model::ExpenseResponse,
+        income_models::{IncomeCategoryResponse, IncomeResponse},
+        ledger_models::LedgerAccess,
+    },
+    services::{
+        auth_services::AuthService, expense_services::EXPENSE_SPLITS, redis_services::RedisService,
+    },
+};
+
+// archives hold everything about a user and the ledger they asked for,
+// they don't stay on disk for long
+const EXPORT_TTL_HOURS: i32 = 24;
+
+#[derive(Clone)]
+pub struct DataExportService {
+    pool: PgPool,
+    dir: PathBuf,
+}
+
+impl DataExportService {
+    pub fn new(pool: PgPool, dir: PathBuf) -> Self {
+        Self { pool, dir }
+    }
+
+    // a user has at most one job in flight, asking again returns it
+    pub async fn request_export(
+        &self,
+        auth: &AuthService,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<DataExportResponse, DataExportError> {
+        let user_id = access.user_id;
+
+        let active = query_as::<_, DataExportResponse>(
+            r#"
+                SELECT id, status, error, created_at, completed_at, expires_at
+                FROM data_export
+                WHERE user_id = $1 AND status IN ('pending', 'running')
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DataExportError::internal)?;
+
+        if let Some(active) = active {
+            return Ok(active);
+        }
+
+        let export = query_as::<_, DataExportResponse>(
+            r#"
+                INSERT INTO data_export (user_id)
+                VALUES ($1)
+                RETURNING id, status, error, created_at, completed_at, expires_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DataExportError::internal)?;
+
+        let service = self.clone();
+        let auth = auth.clone();
+        let redis = redis.clone();
+        let access = *access;
+        let id = export.id;
+
+        actix_web::rt::spawn(async move {
+            service.run(id, &access, &auth, &redis).await;
+        });
+
+        Ok(export)
+    }
+
+    pub async fn get_export(
+        &self,
+        path: DataExportPath,
+        user_id: Uuid,
+    ) -> Result<DataExportResponse, DataExportError> {
+        query_as::<_, DataExportResponse>(
+            r#"
+                SELECT id, status, error, created_at, completed_at, expires_at
+                FROM data_export
+                WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(path.export_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DataExportError::internal)?
+        .ok_or(DataExportError::ExportNotFound)
+    }
+
+    pub async fn download_export(
+        &self,
+        path: DataExportPath,
+        user_id: Uuid,
+    ) -> Result<Vec<u8>, DataExportError> {
+        let export = self.get_export(path, user_id).await?;
+
+        if export.status != DataExportStatus::Completed {
+            return Err(DataExportError::ExportNotReady);
+        }
+
+        if export.expires_at.is_some_and(|at| at <= chrono::Utc::now()) {
+            return Err(DataExportError::ExportExpired);
+        }
+
+        let file = self.archive_path(export.id);
+
+        actix_web::web::block(move || std::fs::read(file))
+            .await
+            .map_err(DataExportError::internal)?
+            .map_err(|_| DataExportError::ExportExpired)
+    }
+
+    async fn run(&self, id: Uuid, access: &LedgerAccess, auth: &AuthService, redis: &RedisService) {
+        let result = self.set_status(id, DataExportStatus::Running).await;
+        let result = match result {
+            Ok(()) => self.build_archive(id, access, auth, redis).await,
+            Err(e) => Err(e),
+        };
+
+        let finished = match result {
+            Ok(()) => {
+                query(
+                    r#"
+                        UPDATE data_export
+                        SET status = 'completed', completed_at = NOW(),
+                            expires_at = NOW() + make_interval(hours => $2)
+                        WHERE id = $1
+                    "#,
+                )
+                .bind(id)
+                .bind(EXPORT_TTL_HOURS)
+                .execute(&self.pool)
+                .await
+            }
+            Err(e) => {
+                error!(error = ?e, export_id = %id, "Data export failed.");
+
+                // the cause stays in the logs, it may mention server paths
+                query(
+                    r#"
+                        UPDATE data_export
+                        SET status = 'failed', error = 'export failed, try again later',
+                            completed_at = NOW()
+                        WHERE id = $1
+                    "#,
+                )
+                .bind(id)
+                .execute(&self.pool)
+                .await
+            }
+        };
+
+        if let Err(e) = finished {
+            error!(error = ?e, export_id = %id, "Failed to record data export status.");
+        }
+    }
+
+    async fn set_status(&self, id: Uuid, status: DataExportStatus) -> anyhow::Result<()> {
+        query(
+            r#"
+                UPDATE data_export SET status = $2
+                WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn build_archive(
+        &self,
+        id: Uuid,
+        access: &LedgerAccess,
+        auth: &AuthService,
+        redis: &RedisService,
+    ) -> anyhow::Result<()> {
+        let user_id = access.user_id;
+
+        let profile = auth.get_profile(user_id).await?;
+        let sessions = auth.get_sessions(user_id, "", redis).await?;
+
+        let categories = query_as::<_, CategoryResponse>(
+            r#"
+                SELECT id, description, name, user_id FROM category
+                WHERE ledger_id = $1
+                ORDER BY name
+            "#,
+        )
+        .bind(access.ledger_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let sql = format!(
+            r#"
+                SELECT id, amount, currency, description, user_id, category_id, date,
+                    account_id, is_recurring, tags, {EXPENSE_SPLITS}
+                FROM expense
+                WHERE ledger_id = $1
+                ORDER BY date, created_at
+            "#
+        );
+
+        let expenses = query_as::<_, ExpenseResponse>(&sql)
+            .bind(access.ledger_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let income_categories = query_as::<_, IncomeCategoryResponse>(
+            r#"
+                SELECT id, description, name, user_id FROM income_category
+                WHERE user_id = $1
+                ORDER BY name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let income = query_as::<_, IncomeResponse>(
+            r#"
+                SELECT id, amount, currency, description, user_id, category_id, date,
+                    account_id
+                FROM income
+                WHERE user_id = $1
+                ORDER BY date, created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let accounts = query_as::<_, AccountResponse>(
+            r#"
+                SELECT id, user_id, name, type, currency, opening_balance FROM account
+                WHERE user_id = $1
+                ORDER BY name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let transfers = query_as::<_, TransferResponse>(
+            r#"
+                SELECT id, user_id, from_account_id, to_account_id, amount, to_amount,
+                    description, date
+                FROM transfer
+                WHERE user_id = $1
+                ORDER BY date, created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let budgets = query_as::<_, BudgetResponse>(
+            r#"
+                SELECT id, user_id, category_id, month, amount, rollover FROM budget
+                WHERE category_id IN (SELECT id FROM category WHERE ledger_id = $1)
+                ORDER BY month
+            "#,
+        )
+        .bind(access.ledger_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let files = vec![
+            ("profile.json", serde_json::to_vec_pretty(&profile)?),
+            ("categories.json", serde_json::to_vec_pretty(&categories)?),
+            ("categories.csv", to_csv(&categories)?),
+            ("expenses.json", serde_json::to_vec_pretty(&expenses)?),
+            ("expenses.csv", to_csv(&expenses)?),
+            (
+                "income_categories.json",
+                serde_json::to_vec_pretty(&income_categories)?,
+            ),
+            ("income_categories.csv", to_csv(&income_categories)?),
+            ("income.json", serde_json::to_vec_pretty(&income)?),
+            ("income.csv", to_csv(&income)?),
+            ("accounts.json", serde_json::to_vec_pretty(&accounts)?),
+            ("accounts.csv", to_csv(&accounts)?),
+            ("transfers.json", serde_json::to_vec_pretty(&transfers)?),
+            ("transfers.csv", to_csv(&transfers)?),
+            ("budgets.json", serde_json::to_vec_pretty(&budgets)?),
+            ("budgets.csv", to_csv(&budgets)?),
+            ("sessions.json", serde_json::to_vec_pretty(&sessions)?),
+            ("sessions.csv", to_csv(&sessions)?),
+        ];
+
+        let file = self.archive_path(id);
+
+        actix_web::web::block(move || write_archive(&file, files)).await??;
+
+        Ok(())
+    }
+
+    fn archive_path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{id}.zip"))
+    }
+
+    // drops expired archives and jobs a restart left behind
+    pub async fn purge_expired(&self) -> Result<usize, DataExportError> {
+        let ids: Vec<Uuid> = query_scalar(
+            r#"
+                DELETE FROM data_export
+                WHERE expires_at <= NOW()
+                    OR (status IN ('pending', 'running')
+                        AND created_at <= NOW() - make_interval(hours => $1))
+                RETURNING id
+            "#,
+        )
+        .bind(EXPORT_TTL_HOURS)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DataExportError::internal)?;
+
+        for id in &ids {
+            let file = self.archive_path(*id);
+
+            if let Err(e) = actix_web::web::block(move || std::fs::remove_file(file))
+                .await
+                .map_err(DataExportError::internal)?
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                error!(error = ?e, export_id = %id, "Failed to remove data export.");
+            }
+        }
+
+        Ok(ids.len())
+    }
+
+    // jobs run in-process, anything unfinished at startup was interrupted
+    async fn fail_interrupted(&self) -> Result<(), DataExportError> {
+        query(
+            r#"
+                UPDATE data_export
+                SET status = 'failed', error = 'export interrupted, try again',
+                    completed_at = NOW()
+                WHERE status IN ('pending', 'running')
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(DataExportError::internal)?;
+
+        Ok(())
+    }
+
+    pub fn spawn_worker(self, period: Duration) {
+        actix_web::rt::spawn(async move {
+            if let Err(e) = self.fail_interrupted().await {
+                error!(error = ?e.to_string(), "Failed to clean up interrupted data exports.");
+            }
+
+            let mut interval = actix_web::rt::time::interval(period);
+
+            loop {
+                interval.tick().await;
+
+                match self.purge_expired().await {
+                    Ok(0) => {}
+                    Ok(purged) => info!(purged, "Purged expired data exports."),
+                    Err(e) => error!(error = ?e.to_string(), "Data export cleanup failed."),
+                }
+            }
+        });
+    }
+}
+
+fn write_archive(file: &Path, files: Vec<(&str, Vec<u8>)>) -> anyhow::Result<()> {
+    std::fs::create_dir_all(file.parent().context("invalid DATA_EXPORT_DIR")?)?;
+
+    let mut zip = ZipWriter::new(std::fs::File::create(file)?);
+
+    for (name, content) in files {
+        zip.start_file(name, SimpleFileOptions::default())?;
+        zip.write_all(&content)?;
+    }
+
+    zip.finish()?;
+
+    Ok(())
+}
+
+// columns follow the JSON field names, lists are joined with ';' like the expense export
+fn to_csv<T: Serialize>(rows: &[T]) -> anyhow::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut header_written = false;
+
+    for row in rows {
+        let Value::Object(fields) = serde_json::to_value(row)? else {
+            anyhow::bail!("csv rows must serialize to objects");
+        };
+
+        if !header_written {
+            writer.write_record(fields.keys())?;
+            header_written = true;
+        }
+
+        writer.write_record(fields.values().map(csv_field))?;
+    }
+
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(csv_field).collect::<Vec<_>>().join(";"),
+        other => other.to_string(),
+    }
+}
@@ -5,18 +5,26 @@ use uuid::Uuid;
 
 use crate::{
     errors::expense_errors::ExpenseError,
-    models::expense_model::{
-        CategoryIdPath, ExpenseCached, ExpensePath, ExpenseRequest, ExpenseResponse, ExpensesTotal,
-        ExpensesTotalCached, PageParams,
+    models::{
+        expense_model::{
+            CategoryIdPath, ExpenseCached, ExpenseFilterParams, ExpenseHistoryAction,
+            ExpenseHistoryResponse, ExpensePath, ExpenseRequest, ExpenseResponse, ExpenseSplit,
+            ExpenseSplitRequest, ExpensesTotal, ExpensesTotalCached, TagMatch, TrashPagination,
+            TrashedExpenseResponse,
+        },
+        ledger_models::LedgerAccess,
     },
-    services::redis_services::RedisService,
+    services::{ledger_services::member_ids, redis_services::RedisService},
     utils::utils::{
         all_expenses_version_key, category_filter_expenses_version_key,
         category_filter_total_expense_key, single_expense_key, total_expense_key,
     },
 };
 
-use sqlx::{query_as, query_scalar};
+use sqlx::{
+    PgConnection, Postgres, postgres::PgArguments, query, query::QueryAs, query_as, query_scalar,
+    types::Json,
+};
 
 #[derive(Debug, Clone)]
 pub struct ExpenseServices {
@@ -28,26 +36,36 @@ impl ExpenseServices {
         Self { pool }
     }
 
-    async fn invalidate_expense_cache(
+    // every member caches their own view of the ledger
+    pub async fn invalidate_expense_cache(
         &self,
         redis: &RedisService,
-        category_id: Uuid,
-        user_id: Uuid,
+        category_ids: &[Uuid],
+        ledger_id: Uuid,
         expense_id: Option<Uuid>,
     ) -> Result<(), ExpenseError> {
+        let members = member_ids(&self.pool, ledger_id)
+            .await
+            .map_err(ExpenseError::internal)?;
+
         redis
             .pipeline(|pipe| {
-                if let Some(id) = expense_id {
-                    pipe.del(single_expense_key(id, user_id));
+                for &user_id in &members {
+                    if let Some(id) = expense_id {
+                        pipe.del(single_expense_key(id, ledger_id, user_id));
+                    }
+
+                    pipe.incr(all_expenses_version_key(user_id), 1)
+                        .del(total_expense_key(ledger_id, user_id));
+
+                    for &category_id in category_ids {
+                        pipe.incr(
+                            category_filter_expenses_version_key(category_id, user_id),
+                            1,
+                        )
+                        .del(category_filter_total_expense_key(category_id, user_id));
+                    }
                 }
-
-                pipe.incr(all_expenses_version_key(user_id), 1)
-                    .del(total_expense_key(user_id))
-                    .incr(
-                        category_filter_expenses_version_key(category_id, user_id),
-                        1,
-                    )
-                    .del(category_filter_total_expense_key(category_id, user_id));
             })
             .await
             .map_err(ExpenseError::internal)
@@ -57,42 +75,74 @@ impl ExpenseServices {
         &self,
         expense: ExpenseRequest,
         redis: &RedisService,
-        user_id: Uuid,
+        access: &LedgerAccess,
     ) -> Result<ExpenseResponse, ExpenseError> {
         expense.validate()?;
 
         let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+        let splits = expense.split_lines().to_vec();
 
         let expense = query_as::<_, ExpenseResponse>(
             r#"
-                INSERT INTO expense (amount, description, user_id, category_id, date, is_recurring, tags)
-                VALUES ($1, $2, $3, $4, $5, $6, $7)
-                RETURNING id, amount, description, user_id, category_id, date, payment_method, is_recurring, tags;
+                INSERT INTO expense (amount, currency, description, user_id, category_id, date, account_id, is_recurring, tags, ledger_id)
+                SELECT $1,
+                    COALESCE(
+                        $9,
+                        (SELECT currency FROM account WHERE id = $6 AND user_id = $3),
+                        (SELECT base_currency FROM users WHERE id = $3)
+                    ),
+                    $2, $3, id, $5, $6, $7, $8, ledger_id FROM category
+                WHERE id = $4 AND ledger_id = $10 AND deleted_at IS NULL
+                    AND ($6::uuid IS NULL OR EXISTS (
+                        SELECT 1 FROM account WHERE id = $6 AND user_id = $3
+                    ))
+                RETURNING id, amount, currency, description, user_id, category_id, date, account_id, is_recurring, tags;
             "#,
         )
         .bind(expense.amount)
         .bind(expense.description)
-        .bind(user_id)
+        .bind(access.user_id)
         .bind(expense.category_id)
         .bind(expense.date)
+        .bind(expense.account_id)
         .bind(expense.is_recurring)
         .bind(expense.tags)
-        .fetch_one(&mut *tx)
+        .bind(expense.currency)
+        .bind(access.ledger_id)
+        .fetch_optional(&mut *tx)
         .await;
 
-        let expense = expense.map_err(|e| {
-            if let sqlx::Error::Database(db_err) = &e {
-                match db_err.code().as_deref() {
-                    Some("23502") => return ExpenseError::RequiredFieldMissing,
-                    Some("23503") => return ExpenseError::ForeignKeyNotFound,
-                    _ => return ExpenseError::internal(e),
+        let expense = expense
+            .map_err(|e| {
+                if let sqlx::Error::Database(db_err) = &e {
+                    match db_err.code().as_deref() {
+                        Some("23502") => return ExpenseError::RequiredFieldMissing,
+                        Some("23503") => return ExpenseError::ForeignKeyNotFound,
+                        _ => return ExpenseError::internal(e),
+                    }
                 }
-            }
 
-            ExpenseError::internal(e)
-        })?;
+                ExpenseError::internal(e)
+            })?
+            .ok_or(ExpenseError::ForeignKeyNotFound)?;
+
+        let expense = ExpenseResponse {
+            splits: save_splits(&mut tx, expense.id, access.ledger_id, &splits).await?,
+            ..expense
+        };
+
+        ensure_convertible(&mut tx, &expense).await?;
+
+        record_history(
+            &mut tx,
+            ExpenseHistoryAction::Created,
+            None,
+            Some(&expense),
+            access.user_id,
+        )
+        .await?;
 
-        self.invalidate_expense_cache(redis, expense.category_id, user_id, None)
+        self.invalidate_expense_cache(redis, &expense.category_ids(), access.ledger_id, None)
             .await?;
 
         tx.commit().await.map_err(ExpenseError::internal)?;
@@ -100,17 +150,140 @@ impl ExpenseServices {
         Ok(expense)
     }
 
+    // all or nothing, used by imports so a failed batch leaves no partial rows behind
+    pub async fn add_expenses(
+        &self,
+        expenses: Vec<ExpenseRequest>,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<Vec<ExpenseResponse>, ExpenseError> {
+        for expense in &expenses {
+            expense.validate()?;
+        }
+
+        let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+        let mut created = Vec::with_capacity(expenses.len());
+
+        for expense in expenses {
+            let splits = expense.split_lines().to_vec();
+
+            let expense = query_as::<_, ExpenseResponse>(
+                r#"
+                    INSERT INTO expense (amount, currency, description, user_id, category_id, date, account_id, is_recurring, tags, ledger_id)
+                    SELECT $1,
+                        COALESCE(
+                            $9,
+                            (SELECT currency FROM account WHERE id = $6 AND user_id = $3),
+                            (SELECT base_currency FROM users WHERE id = $3)
+                        ),
+                        $2, $3, id, $5, $6, $7, $8, ledger_id FROM category
+                    WHERE id = $4 AND ledger_id = $10 AND deleted_at IS NULL
+                        AND ($6::uuid IS NULL OR EXISTS (
+                            SELECT 1 FROM account WHERE id = $6 AND user_id = $3
+                        ))
+                    RETURNING id, amount, currency, description, user_id, category_id, date, account_id, is_recurring, tags;
+                "#,
+            )
+            .bind(expense.amount)
+            .bind(expense.description)
+            .bind(access.user_id)
+            .bind(expense.category_id)
+            .bind(expense.date)
+            .bind(expense.account_id)
+            .bind(expense.is_recurring)
+            .bind(expense.tags)
+            .bind(expense.currency)
+            .bind(access.ledger_id)
+            .fetch_optional(&mut *tx)
+            .await;
+
+            let expense = expense
+                .map_err(|e| {
+                    if let sqlx::Error::Database(db_err) = &e {
+                        match db_err.code().as_deref() {
+                            Some("23502") => return ExpenseError::RequiredFieldMissing,
+                            Some("23503") => return ExpenseError::ForeignKeyNotFound,
+                            _ => return ExpenseError::internal(e),
+                        }
+                    }
+
+                    ExpenseError::internal(e)
+                })?
+                .ok_or(ExpenseError::ForeignKeyNotFound)?;
+
+            let expense = ExpenseResponse {
+                splits: save_splits(&mut tx, expense.id, access.ledger_id, &splits).await?,
+                ..expense
+            };
+
+            ensure_convertible(&mut tx, &expense).await?;
+
+            record_history(
+                &mut tx,
+                ExpenseHistoryAction::Created,
+                None,
+                Some(&expense),
+                access.user_id,
+            )
+            .await?;
+
+            created.push(expense);
+        }
+
+        let mut categories: Vec<Uuid> = created.iter().flat_map(|e| e.category_ids()).collect();
+        categories.sort();
+        categories.dedup();
+
+        self.invalidate_expense_cache(redis, &categories, access.ledger_id, None)
+            .await?;
+
+        tx.commit().await.map_err(ExpenseError::internal)?;
+
+        Ok(created)
+    }
+
+    pub async fn get_trashed_expenses(
+        &self,
+        params: TrashPagination,
+        access: &LedgerAccess,
+    ) -> Result<Vec<TrashedExpenseResponse>, ExpenseError> {
+        let limit: i64 = 10;
+        let page = params.page.max(1);
+        let offset = (page - 1) * limit;
+
+        let sql = format!(
+            r#"
+                SELECT id, amount, currency, description, user_id,
+                    category_id, date, account_id,
+                    is_recurring, tags, {EXPENSE_SPLITS}, deleted_at FROM expense
+                WHERE ledger_id = $1 AND deleted_at IS NOT NULL
+                ORDER BY deleted_at DESC
+                LIMIT $2 OFFSET $3
+            "#
+        );
+
+        query_as::<_, TrashedExpenseResponse>(&sql)
+            .bind(access.ledger_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(ExpenseError::internal)
+    }
+
     pub async fn get_user_expenses(
         &self,
-        params: PageParams,
+        params: ExpenseFilterParams,
         redis: &RedisService,
-        user_id: Uuid,
+        access: &LedgerAccess,
     ) -> Result<ExpensesTotalCached, ExpenseError> {
+        params.validate()?;
+
         let limit: i64 = 10;
         let page = params.page.max(1);
         let offset = (page - 1) * limit;
 
-        let v_key = all_expenses_version_key(user_id);
+        let v_key = all_expenses_version_key(access.user_id);
 
         let (_, v): (i64, String) = redis
             .pipeline(|pipe| {
@@ -119,7 +292,14 @@ impl ExpenseServices {
             .await
             .map_err(ExpenseError::internal)?;
 
-        let key = format!("user:{}:p:{}:v:{}:expenses", user_id, page, v);
+        let key = format!(
+            "user:{}:ledger:{}:p:{}:v:{}:f:{}:expenses",
+            access.user_id,
+            access.ledger_id,
+            page,
+            v,
+            params.cache_key()
+        );
 
         if let Some(cached) = redis.get(&key).await.ok().flatten() {
             let cached_json = serde_json::from_str(&cached).map_err(ExpenseError::internal)?;
@@ -130,45 +310,33 @@ impl ExpenseServices {
             });
         }
 
-        let expenses = query_as::<_, ExpenseResponse>(
-            r#"
-                SELECT id, amount, description, user_id,
-                    category_id, date, payment_method,
-                    is_recurring, tags FROM expense
-                WHERE user_id = $1
-                ORDER BY updated_at DESC
-                LIMIT $2 OFFSET $3
-            "#,
-        )
-        .bind(user_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(ExpenseError::internal)?;
+        let expenses = self
+            .query_filtered_expenses(&params, access, limit, offset)
+            .await?;
 
-        let total: Decimal =
-            if let Some(v) = redis.get(&total_expense_key(user_id)).await.ok().flatten() {
-                Decimal::from_str(&v).map_err(ExpenseError::internal)?
-            } else {
-                let total: Decimal = query_scalar(
-                    r#"
-                    SELECT COALESCE(SUM(amount), 0) FROM expense
-                    WHERE user_id = $1
-                "#,
+        let total: Decimal = if params.has_filters() || params.category_id.is_some() {
+            self.query_filtered_total(&params, access).await?
+        } else if let Some(v) = redis
+            .get(&total_expense_key(access.ledger_id, access.user_id))
+            .await
+            .ok()
+            .flatten()
+        {
+            Decimal::from_str(&v).map_err(ExpenseError::internal)?
+        } else {
+            let total = self.query_filtered_total(&params, access).await?;
+
+            redis
+                .set(
+                    total_expense_key(access.ledger_id, access.user_id),
+                    total.to_string(),
+                    300,
                 )
-                .bind(user_id)
-                .fetch_one(&self.pool)
                 .await
                 .map_err(ExpenseError::internal)?;
 
-                redis
-                    .set(total_expense_key(user_id), total.to_string(), 300)
-                    .await
-                    .map_err(ExpenseError::internal)?;
-
-                total
-            };
+            total
+        };
 
         let result = ExpensesTotal { expenses, total };
 
@@ -185,13 +353,61 @@ impl ExpenseServices {
         })
     }
 
+    async fn query_filtered_expenses(
+        &self,
+        filter: &ExpenseFilterParams,
+        access: &LedgerAccess,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ExpenseResponse>, ExpenseError> {
+        let sql = format!(
+            r#"
+                SELECT id, amount, currency, description, user_id,
+                    category_id, date, account_id,
+                    is_recurring, tags, {EXPENSE_SPLITS} FROM expense
+                WHERE {EXPENSE_FILTER_CONDITIONS}
+                ORDER BY updated_at DESC
+                LIMIT $12 OFFSET $13
+            "#
+        );
+
+        bind_expense_filter(query_as::<_, ExpenseResponse>(&sql), filter, access)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(ExpenseError::internal)
+    }
+
+    async fn query_filtered_total(
+        &self,
+        filter: &ExpenseFilterParams,
+        access: &LedgerAccess,
+    ) -> Result<Decimal, ExpenseError> {
+        // the category filter sums only the split lines in that category
+        let sql = format!(
+            r#"
+                SELECT {BASE_CURRENCY_TOTAL} FROM expense_portion
+                WHERE expense_id IN (SELECT id FROM expense WHERE {EXPENSE_FILTER_CONDITIONS})
+                    AND ($6::uuid IS NULL OR category_id = $6)
+            "#
+        );
+
+        let (total,): (Decimal,) = bind_expense_filter(query_as(&sql), filter, access)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(ExpenseError::conversion)?;
+
+        Ok(total)
+    }
+
     pub async fn get_single_expense_per_user(
         &self,
         path: ExpensePath,
         redis: &RedisService,
-        user_id: Uuid,
+        access: &LedgerAccess,
     ) -> Result<ExpenseCached, ExpenseError> {
-        let key = single_expense_key(path.expense_id, user_id);
+        let key = single_expense_key(path.expense_id, access.ledger_id, access.user_id);
 
         if let Some(cached) = redis.get(&key).await.ok().flatten() {
             let expense = serde_json::from_str(&cached).map_err(ExpenseError::internal)?;
@@ -202,18 +418,20 @@ impl ExpenseServices {
             });
         }
 
-        let expense = query_as::<_, ExpenseResponse>(
+        let sql = format!(
             r#"
-                SELECT id, amount, description, user_id,
-                    category_id, date, payment_method,
-                    is_recurring, tags FROM expense
-                WHERE id = $1 AND user_id = $2
-            "#,
-        )
-        .bind(path.expense_id)
-        .bind(user_id)
-        .fetch_one(&self.pool)
-        .await;
+                SELECT id, amount, currency, description, user_id,
+                    category_id, date, account_id,
+                    is_recurring, tags, {EXPENSE_SPLITS} FROM expense
+                WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL
+            "#
+        );
+
+        let expense = query_as::<_, ExpenseResponse>(&sql)
+            .bind(path.expense_id)
+            .bind(access.ledger_id)
+            .fetch_one(&self.pool)
+            .await;
 
         let expense = expense.map_err(|e| {
             if let sqlx::Error::RowNotFound = &e {
@@ -241,40 +459,91 @@ impl ExpenseServices {
         body: ExpenseRequest,
         path: ExpensePath,
         redis: &RedisService,
-        user_id: Uuid,
+        access: &LedgerAccess,
     ) -> Result<ExpenseResponse, ExpenseError> {
         body.validate()?;
 
         let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+        let splits = body.split_lines().to_vec();
+
+        let sql = format!(
+            r#"
+                SELECT id, amount, currency, description, user_id,
+                    category_id, date, account_id,
+                    is_recurring, tags, {EXPENSE_SPLITS} FROM expense
+                WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL
+                FOR UPDATE
+            "#
+        );
+
+        let before = query_as::<_, ExpenseResponse>(&sql)
+            .bind(path.expense_id)
+            .bind(access.ledger_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(ExpenseError::internal)?
+            .ok_or(ExpenseError::ExpenseNotFound)?;
 
         let expense = query_as::<_, ExpenseResponse>(
             r#"
                 UPDATE expense
                 SET amount = $3, description = $4,
                     category_id = $5, date = $6,
-                    updated_at = NOW(), payment_method = $7,
-                    is_recurring = $8, tags = $9
-                WHERE id = $1 AND user_id = $2
-                RETURNING id, amount, description, user_id,
-                    category_id, date, payment_method,
+                    updated_at = NOW(), account_id = $7,
+                    is_recurring = $8, tags = $9,
+                    currency = COALESCE($10, currency)
+                WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL
+                    AND EXISTS (
+                        SELECT 1 FROM category
+                        WHERE id = $5 AND ledger_id = $2 AND deleted_at IS NULL
+                    )
+                    AND ($7::uuid IS NULL OR account_id = $7 OR EXISTS (
+                        SELECT 1 FROM account WHERE id = $7 AND user_id = $11
+                    ))
+                RETURNING id, amount, currency, description, user_id,
+                    category_id, date, account_id,
                     is_recurring, tags
             "#,
         )
         .bind(path.expense_id)
-        .bind(user_id)
+        .bind(access.ledger_id)
         .bind(body.amount)
         .bind(body.description)
         .bind(body.category_id)
         .bind(body.date)
-        .bind(body.payment_method)
+        .bind(body.account_id)
         .bind(body.is_recurring)
         .bind(body.tags)
+        .bind(body.currency)
+        .bind(access.user_id)
         .fetch_optional(&mut *tx)
         .await
         .map_err(ExpenseError::internal)?
         .ok_or(ExpenseError::ExpenseNotFound)?;
 
-        self.invalidate_expense_cache(redis, expense.category_id, user_id, Some(path.expense_id))
+        // sending no split lines clears them
+        let expense = ExpenseResponse {
+            splits: save_splits(&mut tx, expense.id, access.ledger_id, &splits).await?,
+            ..expense
+        };
+
+        ensure_convertible(&mut tx, &expense).await?;
+
+        record_history(
+            &mut tx,
+            ExpenseHistoryAction::Updated,
+            Some(&before),
+            Some(&expense),
+            access.user_id,
+        )
+        .await?;
+
+        let mut categories = before.category_ids();
+        categories.extend(expense.category_ids());
+        categories.sort();
+        categories.dedup();
+
+        self.invalidate_expense_cache(redis, &categories, access.ledger_id, Some(path.expense_id))
             .await?;
 
         tx.commit().await.map_err(ExpenseError::internal)?;
@@ -286,38 +555,171 @@ impl ExpenseServices {
         &self,
         path: ExpensePath,
         redis: &RedisService,
-        user_id: Uuid,
+        access: &LedgerAccess,
     ) -> Result<String, ExpenseError> {
         let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
 
-        let (id, category_id): (Uuid, Uuid) = query_as(
+        // kept in the trash until restored or purged by the retention worker
+        let sql = format!(
             r#"
-                DELETE FROM expense
-                WHERE id = $1 AND user_id = $2
-                RETURNING id, category_id
+                UPDATE expense SET deleted_at = NOW()
+                WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL
+                RETURNING id, amount, currency, description, user_id,
+                    category_id, date, account_id,
+                    is_recurring, tags, {EXPENSE_SPLITS}
+            "#
+        );
+
+        let expense = query_as::<_, ExpenseResponse>(&sql)
+            .bind(path.expense_id)
+            .bind(access.ledger_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(ExpenseError::internal)?
+            .ok_or(ExpenseError::ExpenseNotFound)?;
+
+        record_history(
+            &mut tx,
+            ExpenseHistoryAction::Deleted,
+            Some(&expense),
+            None,
+            access.user_id,
+        )
+        .await?;
+
+        self.invalidate_expense_cache(
+            redis,
+            &expense.category_ids(),
+            access.ledger_id,
+            Some(expense.id),
+        )
+        .await?;
+
+        tx.commit().await.map_err(ExpenseError::internal)?;
+
+        Ok(expense.id.to_string())
+    }
+
+    pub async fn restore_expense_per_user(
+        &self,
+        path: ExpensePath,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<ExpenseResponse, ExpenseError> {
+        let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+
+        let category_trashed: bool = query_scalar(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM expense_portion p
+                    JOIN category c ON c.id = p.category_id
+                    WHERE p.expense_id = e.id AND c.deleted_at IS NOT NULL
+                ) FROM expense e
+                WHERE e.id = $1 AND e.ledger_id = $2 AND e.deleted_at IS NOT NULL
+                FOR UPDATE OF e
             "#,
         )
         .bind(path.expense_id)
-        .bind(user_id)
+        .bind(access.ledger_id)
         .fetch_optional(&mut *tx)
         .await
         .map_err(ExpenseError::internal)?
         .ok_or(ExpenseError::ExpenseNotFound)?;
 
-        self.invalidate_expense_cache(redis, category_id, user_id, Some(path.expense_id))
-            .await?;
+        if category_trashed {
+            return Err(ExpenseError::CategoryTrashed);
+        }
+
+        let sql = format!(
+            r#"
+                UPDATE expense
+                SET deleted_at = NULL, updated_at = NOW()
+                WHERE id = $1 AND ledger_id = $2
+                RETURNING id, amount, currency, description, user_id,
+                    category_id, date, account_id,
+                    is_recurring, tags, {EXPENSE_SPLITS}
+            "#
+        );
+
+        let expense = query_as::<_, ExpenseResponse>(&sql)
+            .bind(path.expense_id)
+            .bind(access.ledger_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(ExpenseError::internal)?;
+
+        record_history(
+            &mut tx,
+            ExpenseHistoryAction::Restored,
+            None,
+            Some(&expense),
+            access.user_id,
+        )
+        .await?;
+
+        self.invalidate_expense_cache(
+            redis,
+            &expense.category_ids(),
+            access.ledger_id,
+            Some(expense.id),
+        )
+        .await?;
 
         tx.commit().await.map_err(ExpenseError::internal)?;
 
-        Ok(id.to_string())
+        Ok(expense)
+    }
+
+    // trashed and purged expenses keep their history
+    pub async fn get_expense_history(
+        &self,
+        path: ExpensePath,
+        access: &LedgerAccess,
+    ) -> Result<Vec<ExpenseHistoryResponse>, ExpenseError> {
+        let history = query_as::<_, ExpenseHistoryResponse>(
+            r#"
+                SELECT id, expense_id, actor_id, action, before, after, created_at
+                FROM expense_history
+                WHERE expense_id = $1 AND ledger_id = $2
+                ORDER BY created_at, id
+            "#,
+        )
+        .bind(path.expense_id)
+        .bind(access.ledger_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(ExpenseError::internal)?;
+
+        if history.is_empty() {
+            // expenses from before the trail existed have no entries yet
+            let exists: bool = query_scalar(
+                r#"
+                    SELECT EXISTS (
+                        SELECT 1 FROM expense
+                        WHERE id = $1 AND ledger_id = $2
+                    )
+                "#,
+            )
+            .bind(path.expense_id)
+            .bind(access.ledger_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(ExpenseError::internal)?;
+
+            if !exists {
+                return Err(ExpenseError::ExpenseNotFound);
+            }
+        }
+
+        Ok(history)
     }
 
     pub async fn get_total_of_all_expenses(
         &self,
         redis: &RedisService,
-        user_id: Uuid,
+        access: &LedgerAccess,
     ) -> Result<Decimal, ExpenseError> {
-        let key = total_expense_key(user_id);
+        let key = total_expense_key(access.ledger_id, access.user_id);
 
         if let Some(cached) = redis.get(&key).await.ok().flatten() {
             let amount = Decimal::from_str(&cached).map_err(ExpenseError::internal)?;
@@ -325,16 +727,19 @@ impl ExpenseServices {
             return Ok(amount);
         }
 
-        let total: Decimal = query_scalar(
+        let sql = format!(
             r#"
-                SELECT COALESCE(SUM(amount), 0) FROM expense
-                WHERE user_id = $1
-            "#,
-        )
-        .bind(user_id)
-        .fetch_one(&self.pool)
-        .await
-        .map_err(ExpenseError::internal)?;
+                SELECT {BASE_CURRENCY_TOTAL} FROM expense
+                WHERE ledger_id = $2 AND deleted_at IS NULL
+            "#
+        );
+
+        let total: Decimal = query_scalar(&sql)
+            .bind(access.user_id)
+            .bind(access.ledger_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(ExpenseError::conversion)?;
 
         redis
             .set(key, &total.to_string(), 300)
@@ -346,17 +751,21 @@ impl ExpenseServices {
 
     pub async fn filter_expense_by_category_per_user(
         &self,
-        params: PageParams,
+        mut params: ExpenseFilterParams,
         path: CategoryIdPath,
         redis: &RedisService,
-        user_id: Uuid,
+        access: &LedgerAccess,
     ) -> Result<ExpensesTotalCached, ExpenseError> {
+        params.validate()?;
+
         let page = params.page.max(1);
         let limit = 10;
         let offset = (page - 1) * limit;
         let category_id = path.category_id;
 
-        let key = category_filter_expenses_version_key(category_id, user_id);
+        params.category_id = Some(category_id);
+
+        let key = category_filter_expenses_version_key(category_id, access.user_id);
 
         let (_, v): (i64, String) = redis
             .pipeline(|pipe| {
@@ -366,35 +775,32 @@ impl ExpenseServices {
             .map_err(ExpenseError::internal)?;
 
         let key = format!(
-            "user:{}:filter:category:{}:v:{}:p:{}",
-            user_id, category_id, v, page
+            "user:{}:filter:category:{}:v:{}:p:{}:f:{}",
+            access.user_id,
+            category_id,
+            v,
+            page,
+            params.cache_key()
         );
 
-        let total_key = category_filter_total_expense_key(category_id, user_id);
-
-        let total: Decimal = if let Some(cached) = redis.get(&total_key).await.ok().flatten() {
-            let total = Decimal::from_str(&cached).map_err(ExpenseError::internal)?;
-
-            total
+        // the standalone total cache only holds the unfiltered category sum
+        let total: Decimal = if params.has_filters() {
+            self.query_filtered_total(&params, access).await?
         } else {
-            let total = query_scalar::<_, Decimal>(
-                r#"
-                    SELECT COALESCE(SUM(amount), 0) FROM expense
-                    WHERE user_id = $1 AND category_id = $2
-                "#,
-            )
-            .bind(user_id)
-            .bind(path.category_id)
-            .fetch_one(&self.pool)
-            .await
-            .map_err(ExpenseError::internal)?;
+            let total_key = category_filter_total_expense_key(category_id, access.user_id);
 
-            redis
-                .set(total_key, total.to_string(), 300)
-                .await
-                .map_err(ExpenseError::internal)?;
+            if let Some(cached) = redis.get(&total_key).await.ok().flatten() {
+                Decimal::from_str(&cached).map_err(ExpenseError::internal)?
+            } else {
+                let total = self.query_filtered_total(&params, access).await?;
 
-            total
+                redis
+                    .set(total_key, total.to_string(), 300)
+                    .await
+                    .map_err(ExpenseError::internal)?;
+
+                total
+            }
         };
 
         if let Some(cached) = redis.get(&key).await.ok().flatten() {
@@ -412,23 +818,9 @@ impl ExpenseServices {
             });
         }
 
-        let expenses = query_as::<_, ExpenseResponse>(
-            r#"
-                SELECT id, amount, description, user_id,
-                    category_id, date, payment_method,
-                    is_recurring, tags FROM expense
-                WHERE user_id = $1 AND category_id = $2
-                ORDER BY updated_at DESC
-                LIMIT $3 OFFSET $4
-            "#,
-        )
-        .bind(user_id)
-        .bind(path.category_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(ExpenseError::internal)?;
+        let expenses = self
+            .query_filtered_expenses(&params, access, limit, offset)
+            .await?;
 
         let json = serde_json::to_string(&expenses).map_err(ExpenseError::internal)?;
 
@@ -445,3 +837,155 @@ impl ExpenseServices {
         })
     }
 }
+
+// every member sees totals in their own base currency, an amount one of them can't
+// convert would fail each of those sums, so it is turned away on write instead
+pub async fn ensure_convertible(
+    conn: &mut PgConnection,
+    expense: &ExpenseResponse,
+) -> Result<(), ExpenseError> {
+    query(
+        r#"
+            SELECT eur_rate(e.user_id, e.currency, e.date),
+                eur_rate(e.user_id, u.base_currency, e.date)
+            FROM expense e
+            JOIN ledger_member m ON m.ledger_id = e.ledger_id
+            JOIN users u ON u.id = m.user_id
+            WHERE e.id = $1 AND u.base_currency <> e.currency
+        "#,
+    )
+    .bind(expense.id)
+    .execute(conn)
+    .await
+    .map_err(ExpenseError::conversion)?;
+
+    Ok(())
+}
+
+// runs in the caller's transaction so a change and its entry commit together
+pub async fn record_history(
+    conn: &mut PgConnection,
+    action: ExpenseHistoryAction,
+    before: Option<&ExpenseResponse>,
+    after: Option<&ExpenseResponse>,
+    actor_id: Uuid,
+) -> Result<(), ExpenseError> {
+    let Some(expense) = after.or(before) else {
+        return Ok(());
+    };
+
+    query(
+        r#"
+            INSERT INTO expense_history (expense_id, user_id, actor_id, action, before, after,
+                ledger_id)
+            SELECT $1, $2, $3, $4, $5, $6, ledger_id FROM expense
+            WHERE id = $1
+        "#,
+    )
+    .bind(expense.id)
+    .bind(expense.user_id)
+    .bind(actor_id)
+    .bind(action)
+    .bind(before.map(Json))
+    .bind(after.map(Json))
+    .execute(conn)
+    .await
+    .map_err(ExpenseError::internal)?;
+
+    Ok(())
+}
+
+// replaces the split lines of an expense, each category has to be the user's and live
+pub async fn save_splits(
+    conn: &mut PgConnection,
+    expense_id: Uuid,
+    ledger_id: Uuid,
+    splits: &[ExpenseSplitRequest],
+) -> Result<Json<Vec<ExpenseSplit>>, ExpenseError> {
+    query("DELETE FROM expense_split WHERE expense_id = $1")
+        .bind(expense_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(ExpenseError::internal)?;
+
+    for (position, split) in splits.iter().enumerate() {
+        let inserted = query(
+            r#"
+                INSERT INTO expense_split (expense_id, category_id, amount, tags, position)
+                SELECT $1, id, $3, $4, $5 FROM category
+                WHERE id = $2 AND ledger_id = $6 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(expense_id)
+        .bind(split.category_id)
+        .bind(split.amount)
+        .bind(&split.tags)
+        .bind(position as i16)
+        .bind(ledger_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(ExpenseError::internal)?;
+
+        if inserted.rows_affected() == 0 {
+            return Err(ExpenseError::ForeignKeyNotFound);
+        }
+    }
+
+    Ok(Json(splits.iter().map(ExpenseSplit::from).collect()))
+}
+
+// the split lines as a json array, for selects and returning clauses on `expense`
+pub const EXPENSE_SPLITS: &str = r#"
+    COALESCE((
+        SELECT jsonb_agg(jsonb_build_object(
+            'category_id', s.category_id, 'amount', s.amount::text, 'tags', s.tags
+        ) ORDER BY s.position)
+        FROM expense_split s
+        WHERE s.expense_id = expense.id
+    ), '[]') AS splits
+"#;
+
+// sums amounts in the base currency of the user bound to $1, each converted at the
+// rate on its own date
+pub const BASE_CURRENCY_TOTAL: &str = r#"
+    ROUND(COALESCE(SUM(convert_amount(
+        user_id, amount, currency, (SELECT base_currency FROM users WHERE id = $1), date
+    )), 0), 2)
+"#;
+
+// every filter is optional, a NULL parameter disables its condition; $1 is the
+// requesting user and $11 the ledger
+pub const EXPENSE_FILTER_CONDITIONS: &str = r#"
+    ledger_id = $11
+    AND deleted_at IS NULL
+    AND ($2::date IS NULL OR date >= $2)
+    AND ($3::date IS NULL OR date <= $3)
+    AND ($4::numeric IS NULL OR amount >= $4)
+    AND ($5::numeric IS NULL OR amount <= $5)
+    AND ($6::uuid IS NULL OR EXISTS (
+        SELECT 1 FROM expense_portion p
+        WHERE p.expense_id = expense.id AND p.category_id = $6
+    ))
+    AND ($7::varchar[] IS NULL OR CASE WHEN $8 THEN tags @> $7::varchar[] ELSE tags && $7::varchar[] END)
+    AND ($9::uuid IS NULL OR account_id = $9)
+    AND ($10::boolean IS NULL OR is_recurring = $10)
+"#;
+
+pub fn bind_expense_filter<'q, O>(
+    query: QueryAs<'q, Postgres, O, PgArguments>,
+    filter: &ExpenseFilterParams,
+    access: &LedgerAccess,
+) -> QueryAs<'q, Postgres, O, PgArguments> {
+    query
+        .bind(access.user_id)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.min_amount)
+        .bind(filter.max_amount)
+        .bind(filter.category_id)
+        .bind(filter.tag_list())
+        .bind(filter.tags_match == TagMatch::All)
+        .bind(filter.account_id)
+        .bind(filter.is_recurring)
+        .bind(access.ledger_id)
+}
@@ -0,0 +1,202 @@
+use actix_web::web::Bytes;
+use futures::{SinkExt, Stream, StreamExt, channel::mpsc};
+use rust_xlsxwriter::{Format, Workbook};
+use sqlx::{PgPool, query_as};
+
+use crate::{
+    errors::expense_errors::ExpenseError,
+    models::{
+        expense_model::{ExportFormat, ExportParams, ExportRow},
+        ledger_models::LedgerAccess,
+    },
+    services::expense_services::{EXPENSE_FILTER_CONDITIONS, bind_expense_filter},
+};
+
+const EXPORT_HEADERS: [&str; 9] = [
+    "id",
+    "date",
+    "amount",
+    "currency",
+    "description",
+    "category",
+    "account",
+    "is_recurring",
+    "tags",
+];
+
+#[derive(Clone)]
+pub struct ExportService {
+    pool: PgPool,
+}
+
+impl ExportService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    fn export_sql() -> String {
+        // category and account are subqueries so the shared filter columns stay unambiguous
+        format!(
+            r#"
+                SELECT id, date, amount, currency, description,
+                    (SELECT name FROM category WHERE category.id = expense.category_id) AS category,
+                    (SELECT name FROM account WHERE account.id = expense.account_id) AS account,
+                    is_recurring, tags
+                FROM expense
+                WHERE {EXPENSE_FILTER_CONDITIONS}
+                ORDER BY date, created_at
+            "#
+        )
+    }
+
+    // rows are encoded as they come off the cursor, the channel bounds memory use
+    pub fn stream_expenses(
+        &self,
+        params: ExportParams,
+        access: LedgerAccess,
+    ) -> impl Stream<Item = Result<Bytes, ExpenseError>> + 'static {
+        let (mut sender, receiver) = mpsc::channel::<Result<Bytes, ExpenseError>>(64);
+        let pool = self.pool.clone();
+
+        actix_web::rt::spawn(async move {
+            let sql = Self::export_sql();
+            let filter = params.filter();
+
+            if params.format == ExportFormat::Csv {
+                let header = encode_csv_row(&EXPORT_HEADERS);
+
+                if sender.send(header).await.is_err() {
+                    return;
+                }
+            }
+
+            let mut rows =
+                bind_expense_filter(query_as::<_, ExportRow>(&sql), &filter, &access).fetch(&pool);
+
+            while let Some(row) = rows.next().await {
+                let chunk =
+                    row.map_err(ExpenseError::internal)
+                        .and_then(|row| match params.format {
+                            ExportFormat::Json => encode_json_row(&row),
+                            _ => encode_csv_row(&csv_fields(&row)),
+                        });
+
+                let failed = chunk.is_err();
+
+                // a closed channel means the client went away
+                if sender.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        receiver
+    }
+
+    // xlsx is a zip container and can't be streamed, constant memory mode spills rows to disk
+    pub async fn export_xlsx(
+        &self,
+        params: ExportParams,
+        access: &LedgerAccess,
+    ) -> Result<Vec<u8>, ExpenseError> {
+        let sql = Self::export_sql();
+        let filter = params.filter();
+
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet_with_constant_memory();
+        let date_format = Format::new().set_num_format("yyyy-mm-dd");
+        let bold = Format::new().set_bold();
+
+        for (col, header) in EXPORT_HEADERS.iter().enumerate() {
+            sheet
+                .write_string_with_format(0, col as u16, *header, &bold)
+                .map_err(ExpenseError::internal)?;
+        }
+
+        let mut rows =
+            bind_expense_filter(query_as::<_, ExportRow>(&sql), &filter, access).fetch(&self.pool);
+        let mut index: u32 = 0;
+
+        while let Some(row) = rows.next().await {
+            let row = row.map_err(ExpenseError::internal)?;
+            index += 1;
+
+            sheet
+                .write_string(index, 0, row.id.to_string())
+                .and_then(|s| s.write_date_with_format(index, 1, row.date, &date_format))
+                // as text, a float would round the cents of large amounts
+                .and_then(|s| s.write_string(index, 2, row.amount.to_string()))
+                .and_then(|s| s.write_string(index, 3, &row.currency))
+                .and_then(|s| s.write_string(index, 4, &row.description))
+                .and_then(|s| s.write_string(index, 5, &row.category))
+                .and_then(|s| s.write_string(index, 6, row.account.as_deref().unwrap_or_default()))
+                .and_then(|s| s.write_boolean(index, 7, row.is_recurring))
+                .and_then(|s| s.write_string(index, 8, row.tags.unwrap_or_default().join(";")))
+                .map_err(ExpenseError::internal)?;
+        }
+
+        workbook.save_to_buffer().map_err(ExpenseError::internal)
+    }
+}
+
+fn csv_fields(row: &ExportRow) -> [String; 9] {
+    [
+        row.id.to_string(),
+        row.date.to_string(),
+        row.amount.to_string(),
+        row.currency.clone(),
+        spreadsheet_text(&row.description),
+        spreadsheet_text(&row.category),
+        spreadsheet_text(row.account.as_deref().unwrap_or_default()),
+        row.is_recurring.to_string(),
+        spreadsheet_text(&row.tags.clone().unwrap_or_default().join(";")),
+    ]
+}
+
+// imported descriptions can start like a formula, a leading ' keeps spreadsheets from running it
+fn spreadsheet_text(value: &str) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{value}")
+    } else {
+        value.to_owned()
+    }
+}
+
+fn encode_csv_row<T: AsRef<[u8]>>(fields: &[T]) -> Result<Bytes, ExpenseError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer
+        .write_record(fields)
+        .map_err(ExpenseError::internal)?;
+
+    writer
+        .into_inner()
+        .map(Bytes::from)
+        .map_err(|e| ExpenseError::internal(e.into_error()))
+}
+
+fn encode_json_row(row: &ExportRow) -> Result<Bytes, ExpenseError> {
+    let mut line = serde_json::to_vec(row).map_err(ExpenseError::internal)?;
+    line.push(b'\n');
+
+    Ok(Bytes::from(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neutralizes_formula_like_text() {
+        for value in ["=1+1", "+SUM(A1)", "-2+3", "@cmd", "\t=1", "\r=1"] {
+            assert_eq!(spreadsheet_text(value), format!("'{value}"));
+        }
+    }
+
+    #[test]
+    fn leaves_plain_text_alone() {
+        for value in ["", "Groceries", "coffee = 3", "a-b", "'quoted"] {
+            assert_eq!(spreadsheet_text(value), value);
+        }
+    }
+}
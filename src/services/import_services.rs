@@ -0,0 +1,393 @@
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord, Trim};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, query_as, query_scalar};
+use std::{collections::HashMap, str::FromStr};
+use uuid::Uuid;
+
+use crate::{
+    errors::import_errors::{ImportError, RowError},
+    models::{
+        expense_model::ExpenseRequest,
+        import_models::{
+            ImportParams, ImportProfilePath, ImportProfileRequest, ImportProfileResponse,
+            ImportReport, ImportRowReport, RowStatus, SignConvention,
+        },
+        ledger_models::LedgerAccess,
+    },
+    services::{expense_services::ExpenseServices, redis_services::RedisService},
+};
+
+const MAX_IMPORT_ROWS: usize = 5000;
+
+#[derive(Clone)]
+pub struct ImportService {
+    pool: PgPool,
+}
+
+impl ImportService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn add_profile(
+        &self,
+        body: ImportProfileRequest,
+        user_id: Uuid,
+    ) -> Result<ImportProfileResponse, ImportError> {
+        body.validate()?;
+
+        if let Some(category_id) = body.default_category_id {
+            let owned: bool = query_scalar(
+                r#"
+                    SELECT EXISTS (
+                        SELECT 1 FROM category
+                        WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+                    )
+                "#,
+            )
+            .bind(category_id)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(ImportError::internal)?;
+
+            if !owned {
+                return Err(ImportError::ForeignKeyNotFound);
+            }
+        }
+
+        let profile = query_as::<_, ImportProfileResponse>(
+            r#"
+                INSERT INTO import_profile (user_id, name, date_column, amount_column,
+                    description_column, category_column, tags_column, date_format,
+                    decimal_separator, delimiter, sign_convention, has_header,
+                    default_category_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                RETURNING id, user_id, name, date_column, amount_column, description_column,
+                    category_column, tags_column, date_format, decimal_separator, delimiter,
+                    sign_convention, has_header, default_category_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(body.name)
+        .bind(body.date_column)
+        .bind(body.amount_column)
+        .bind(body.description_column)
+        .bind(body.category_column)
+        .bind(body.tags_column)
+        .bind(body.date_format)
+        .bind(body.decimal_separator)
+        .bind(body.delimiter)
+        .bind(body.sign_convention)
+        .bind(body.has_header)
+        .bind(body.default_category_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        profile.map_err(|e| {
+            if let sqlx::Error::Database(db_err) = &e
+                && db_err.code().as_deref() == Some("23505")
+            {
+                return ImportError::NameExisting;
+            }
+
+            ImportError::internal(e)
+        })
+    }
+
+    pub async fn get_user_profiles(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<ImportProfileResponse>, ImportError> {
+        query_as::<_, ImportProfileResponse>(
+            r#"
+                SELECT id, user_id, name, date_column, amount_column, description_column,
+                    category_column, tags_column, date_format, decimal_separator, delimiter,
+                    sign_convention, has_header, default_category_id
+                FROM import_profile
+                WHERE user_id = $1
+                ORDER BY name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(ImportError::internal)
+    }
+
+    pub async fn delete_profile(
+        &self,
+        path: ImportProfilePath,
+        user_id: Uuid,
+    ) -> Result<String, ImportError> {
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM import_profile
+                WHERE id = $1 AND user_id = $2
+                RETURNING id
+            "#,
+        )
+        .bind(path.profile_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(ImportError::internal)?
+        .ok_or(ImportError::ProfileNotFound)?;
+
+        Ok(id.to_string())
+    }
+
+    pub async fn import_csv(
+        &self,
+        params: ImportParams,
+        body: &[u8],
+        expenses: &ExpenseServices,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<ImportReport, ImportError> {
+        let profile = query_as::<_, ImportProfileResponse>(
+            r#"
+                SELECT id, user_id, name, date_column, amount_column, description_column,
+                    category_column, tags_column, date_format, decimal_separator, delimiter,
+                    sign_convention, has_header, default_category_id
+                FROM import_profile
+                WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(params.profile_id)
+        .bind(access.user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(ImportError::internal)?
+        .ok_or(ImportError::ProfileNotFound)?;
+
+        if let Some(account_id) = params.account_id {
+            let owned: bool = query_scalar(
+                r#"
+                    SELECT EXISTS (
+                        SELECT 1 FROM account WHERE id = $1 AND user_id = $2
+                    )
+                "#,
+            )
+            .bind(account_id)
+            .bind(access.user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(ImportError::internal)?;
+
+            if !owned {
+                return Err(ImportError::ForeignKeyNotFound);
+            }
+        }
+
+        let categories: Vec<(Uuid, String)> = query_as(
+            r#"
+                SELECT id, name FROM category
+                WHERE ledger_id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(access.ledger_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(ImportError::internal)?;
+
+        let categories: HashMap<String, Uuid> = categories
+            .into_iter()
+            .map(|(id, name)| (name.to_lowercase(), id))
+            .collect();
+
+        let mut reader = ReaderBuilder::new()
+            .delimiter(
+                profile
+                    .delimiter
+                    .as_bytes()
+                    .first()
+                    .copied()
+                    .unwrap_or(b','),
+            )
+            .has_headers(profile.has_header)
+            .flexible(true)
+            .trim(Trim::All)
+            .from_reader(body);
+
+        let headers = if profile.has_header {
+            Some(
+                reader
+                    .headers()
+                    .map_err(|_| ImportError::InvalidCsv)?
+                    .clone(),
+            )
+        } else {
+            None
+        };
+
+        let mapping = ColumnMapping::resolve(&profile, headers.as_ref())?;
+
+        let mut rows = Vec::new();
+        let mut pending = Vec::new();
+
+        for (i, record) in reader.records().enumerate() {
+            if i >= MAX_IMPORT_ROWS {
+                return Err(ImportError::TooManyRows);
+            }
+
+            let record = record.map_err(|_| ImportError::InvalidCsv)?;
+
+            // 1-based line numbers as they appear in a spreadsheet
+            let row = i + if profile.has_header { 2 } else { 1 };
+
+            match mapping.expense(&record, &profile, &categories) {
+                Ok(expense) => {
+                    let expense = ExpenseRequest {
+                        account_id: params.account_id,
+                        ..expense
+                    };
+
+                    pending.push((rows.len(), expense));
+                    rows.push(ImportRowReport {
+                        row,
+                        status: RowStatus::Accepted,
+                        expense_id: None,
+                        error: None,
+                    });
+                }
+                Err(e) => rows.push(ImportRowReport {
+                    row,
+                    status: RowStatus::Rejected,
+                    expense_id: None,
+                    error: Some(e.to_string()),
+                }),
+            }
+        }
+
+        let accepted = pending.len();
+
+        if !params.dry_run && !pending.is_empty() {
+            let (indexes, requests): (Vec<usize>, Vec<ExpenseRequest>) =
+                pending.into_iter().unzip();
+
+            let created = expenses.add_expenses(requests, redis, access).await?;
+
+            for (index, expense) in indexes.into_iter().zip(created) {
+                rows[index].expense_id = Some(expense.id);
+            }
+        }
+
+        Ok(ImportReport {
+            dry_run: params.dry_run,
+            accepted,
+            rejected: rows.len() - accepted,
+            rows,
+        })
+    }
+}
+
+struct ColumnMapping {
+    date: usize,
+    amount: usize,
+    description: usize,
+    category: Option<usize>,
+    tags: Option<usize>,
+}
+
+impl ColumnMapping {
+    fn resolve(
+        profile: &ImportProfileResponse,
+        headers: Option<&StringRecord>,
+    ) -> Result<Self, ImportError> {
+        let find = |column: &str| -> Result<usize, ImportError> {
+            headers
+                .and_then(|h| h.iter().position(|name| name.eq_ignore_ascii_case(column)))
+                .or_else(|| column.parse().ok())
+                .ok_or_else(|| ImportError::ColumnNotFound(column.to_owned()))
+        };
+
+        Ok(Self {
+            date: find(&profile.date_column)?,
+            amount: find(&profile.amount_column)?,
+            description: find(&profile.description_column)?,
+            category: profile.category_column.as_deref().map(find).transpose()?,
+            tags: profile.tags_column.as_deref().map(find).transpose()?,
+        })
+    }
+
+    fn expense(
+        &self,
+        record: &StringRecord,
+        profile: &ImportProfileResponse,
+        categories: &HashMap<String, Uuid>,
+    ) -> Result<ExpenseRequest, RowError> {
+        let value = |index: usize| record.get(index).filter(|v| !v.is_empty());
+
+        let date = value(self.date).ok_or(RowError::MissingValue("date"))?;
+        let date = NaiveDate::parse_from_str(date, &profile.date_format)
+            .map_err(|_| RowError::InvalidDate)?;
+
+        let amount = value(self.amount).ok_or(RowError::MissingValue("amount"))?;
+        let amount = parse_amount(amount, &profile.decimal_separator)?;
+
+        let amount = match profile.sign_convention {
+            SignConvention::NegativeExpense if amount < Decimal::ZERO => -amount,
+            SignConvention::PositiveExpense if amount > Decimal::ZERO => amount,
+            _ => return Err(RowError::NotAnExpense),
+        };
+
+        let description = value(self.description)
+            .ok_or(RowError::MissingValue("description"))?
+            .to_owned();
+
+        let category_id = match self.category.and_then(value) {
+            Some(category) => Uuid::from_str(category)
+                .ok()
+                .filter(|id| categories.values().any(|c| c == id))
+                .or_else(|| categories.get(&category.to_lowercase()).copied())
+                .ok_or(RowError::UnknownCategory)?,
+            None => profile
+                .default_category_id
+                .ok_or(RowError::MissingValue("category"))?,
+        };
+
+        let tags = self.tags.and_then(value).map(|tags| {
+            tags.split(';')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        });
+
+        let expense = ExpenseRequest {
+            amount,
+            currency: None,
+            description,
+            category_id,
+            date,
+            account_id: None,
+            is_recurring: false,
+            tags: tags.filter(|t| !t.is_empty()),
+            splits: None,
+        };
+
+        expense.validate()?;
+
+        Ok(expense)
+    }
+}
+
+// accepts thousands separators, currency symbols and accounting style (12.50)
+fn parse_amount(raw: &str, decimal_separator: &str) -> Result<Decimal, RowError> {
+    let negative = raw.starts_with('(') && raw.ends_with(')');
+
+    let normalized: String = raw
+        .chars()
+        .filter_map(|c| match c {
+            '0'..='9' | '-' | '+' => Some(c),
+            ',' | '.' if decimal_separator.starts_with(c) => Some('.'),
+            _ => None,
+        })
+        .collect();
+
+    let amount = Decimal::from_str(&normalized).map_err(|_| RowError::InvalidAmount)?;
+
+    Ok(if negative { -amount } else { amount })
+}
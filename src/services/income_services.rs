@@ -0,0 +1,311 @@
+use sqlx::{PgPool, query_as, query_scalar};
+use uuid::Uuid;
+
+use crate::{
+    errors::income_errors::IncomeError,
+    models::income_models::{
+        IncomeCategoryPath, IncomeCategoryRequest, IncomeCategoryResponse, IncomeFilterParams,
+        IncomePath, IncomeRequest, IncomeResponse,
+    },
+    services::redis_services::RedisService,
+    utils::utils::incomes_version_key,
+};
+
+#[derive(Clone)]
+pub struct IncomeService {
+    pool: PgPool,
+}
+
+impl IncomeService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // cash flow reports are cached against this version
+    async fn invalidate_incomes(
+        &self,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<(), IncomeError> {
+        redis
+            .incr(&incomes_version_key(user_id))
+            .await
+            .map_err(IncomeError::internal)?;
+
+        Ok(())
+    }
+
+    pub async fn add_category(
+        &self,
+        body: IncomeCategoryRequest,
+        user_id: Uuid,
+    ) -> Result<IncomeCategoryResponse, IncomeError> {
+        body.validate()?;
+
+        query_as::<_, IncomeCategoryResponse>(
+            r#"
+                INSERT INTO income_category (name, description, user_id)
+                VALUES ($1, $2, $3)
+                RETURNING id, description, name, user_id
+            "#,
+        )
+        .bind(body.name)
+        .bind(body.description)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(IncomeError::constraint)
+    }
+
+    pub async fn get_categories(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<IncomeCategoryResponse>, IncomeError> {
+        query_as::<_, IncomeCategoryResponse>(
+            r#"
+                SELECT id, description, name, user_id FROM income_category
+                WHERE user_id = $1
+                ORDER BY name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(IncomeError::internal)
+    }
+
+    pub async fn edit_category(
+        &self,
+        body: IncomeCategoryRequest,
+        path: IncomeCategoryPath,
+        user_id: Uuid,
+    ) -> Result<IncomeCategoryResponse, IncomeError> {
+        body.validate()?;
+
+        query_as::<_, IncomeCategoryResponse>(
+            r#"
+                UPDATE income_category
+                SET name = $3, description = $4, updated_at = NOW()
+                WHERE id = $1 AND user_id = $2
+                RETURNING id, description, name, user_id
+            "#,
+        )
+        .bind(path.category_id)
+        .bind(user_id)
+        .bind(body.name)
+        .bind(body.description)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(IncomeError::constraint)?
+        .ok_or(IncomeError::CategoryNotFound)
+    }
+
+    // entries have to be moved or deleted first, income is never dropped implicitly
+    pub async fn delete_category(
+        &self,
+        path: IncomeCategoryPath,
+        user_id: Uuid,
+    ) -> Result<String, IncomeError> {
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM income_category
+                WHERE id = $1 AND user_id = $2
+                RETURNING id
+            "#,
+        )
+        .bind(path.category_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(IncomeError::constraint)?
+        .ok_or(IncomeError::CategoryNotFound)?;
+
+        Ok(id.to_string())
+    }
+
+    pub async fn add_income(
+        &self,
+        body: IncomeRequest,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<IncomeResponse, IncomeError> {
+        body.validate()?;
+
+        let income = query_as::<_, IncomeResponse>(
+            r#"
+                INSERT INTO income (amount, currency, description, user_id, category_id, date,
+                    account_id)
+                SELECT $1,
+                    COALESCE(
+                        $6,
+                        (SELECT currency FROM account WHERE id = $7 AND user_id = $3),
+                        (SELECT base_currency FROM users WHERE id = $3)
+                    ),
+                    $2, $3, id, $5, $7 FROM income_category
+                WHERE id = $4 AND user_id = $3
+                    AND ($7::uuid IS NULL OR EXISTS (
+                        SELECT 1 FROM account WHERE id = $7 AND user_id = $3
+                    ))
+                RETURNING id, amount, currency, description, user_id, category_id, date,
+                    account_id
+            "#,
+        )
+        .bind(body.amount)
+        .bind(body.description)
+        .bind(user_id)
+        .bind(body.category_id)
+        .bind(body.date)
+        .bind(body.currency)
+        .bind(body.account_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(IncomeError::internal)?
+        .ok_or(IncomeError::ForeignKeyNotFound)?;
+
+        self.invalidate_incomes(redis, user_id).await?;
+
+        Ok(income)
+    }
+
+    pub async fn get_user_income(
+        &self,
+        params: IncomeFilterParams,
+        user_id: Uuid,
+    ) -> Result<Vec<IncomeResponse>, IncomeError> {
+        params.validate()?;
+
+        let limit: i64 = 10;
+        let page = params.page.max(1);
+        let offset = (page - 1) * limit;
+
+        query_as::<_, IncomeResponse>(
+            r#"
+                SELECT id, amount, currency, description, user_id, category_id, date,
+                    account_id
+                FROM income
+                WHERE user_id = $1
+                    AND ($2::date IS NULL OR date >= $2)
+                    AND ($3::date IS NULL OR date <= $3)
+                    AND ($4::uuid IS NULL OR category_id = $4)
+                ORDER BY date DESC, created_at DESC
+                LIMIT $5 OFFSET $6
+            "#,
+        )
+        .bind(user_id)
+        .bind(params.from)
+        .bind(params.to)
+        .bind(params.category_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(IncomeError::internal)
+    }
+
+    pub async fn get_single_income(
+        &self,
+        path: IncomePath,
+        user_id: Uuid,
+    ) -> Result<IncomeResponse, IncomeError> {
+        query_as::<_, IncomeResponse>(
+            r#"
+                SELECT id, amount, currency, description, user_id, category_id, date,
+                    account_id
+                FROM income
+                WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(path.income_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(IncomeError::internal)?
+        .ok_or(IncomeError::IncomeNotFound)
+    }
+
+    pub async fn edit_income(
+        &self,
+        body: IncomeRequest,
+        path: IncomePath,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<IncomeResponse, IncomeError> {
+        body.validate()?;
+
+        let mut tx = self.pool.begin().await.map_err(IncomeError::internal)?;
+
+        let references_exist: bool = query_scalar(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM income_category WHERE id = $1 AND user_id = $2
+                ) AND ($3::uuid IS NULL OR EXISTS (
+                    SELECT 1 FROM account WHERE id = $3 AND user_id = $2
+                ))
+            "#,
+        )
+        .bind(body.category_id)
+        .bind(user_id)
+        .bind(body.account_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(IncomeError::internal)?;
+
+        if !references_exist {
+            return Err(IncomeError::ForeignKeyNotFound);
+        }
+
+        let income = query_as::<_, IncomeResponse>(
+            r#"
+                UPDATE income
+                SET amount = $3, currency = COALESCE($4, currency), description = $5,
+                    category_id = $6, date = $7, account_id = $8, updated_at = NOW()
+                WHERE id = $1 AND user_id = $2
+                RETURNING id, amount, currency, description, user_id, category_id, date,
+                    account_id
+            "#,
+        )
+        .bind(path.income_id)
+        .bind(user_id)
+        .bind(body.amount)
+        .bind(body.currency)
+        .bind(body.description)
+        .bind(body.category_id)
+        .bind(body.date)
+        .bind(body.account_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(IncomeError::internal)?
+        .ok_or(IncomeError::IncomeNotFound)?;
+
+        tx.commit().await.map_err(IncomeError::internal)?;
+
+        self.invalidate_incomes(redis, user_id).await?;
+
+        Ok(income)
+    }
+
+    pub async fn delete_income(
+        &self,
+        path: IncomePath,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<String, IncomeError> {
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM income
+                WHERE id = $1 AND user_id = $2
+                RETURNING id
+            "#,
+        )
+        .bind(path.income_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(IncomeError::internal)?
+        .ok_or(IncomeError::IncomeNotFound)?;
+
+        self.invalidate_incomes(redis, user_id).await?;
+
+        Ok(id.to_string())
+    }
+}
@@ -1,80 +1,359 @@
+use anyhow::{Context, bail};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode, errors::Error};
-use serde::{Deserialize, Serialize};
+use ed25519_dalek::{SigningKey, pkcs8::DecodePrivateKey};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+    },
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::utils::utils::create_uuid;
+
 #[derive(Deserialize, Serialize)]
 pub struct RefreshTokenClaims {
     pub sub: Uuid,
+    pub aud: String,
     pub exp: i64,
     pub iat: i64,
     pub jti: String,
+    pub sid: String,
+}
+
+// aud keeps these from ever passing as an access or refresh token
+#[derive(Deserialize, Serialize)]
+pub struct EmailVerificationClaims {
+    pub sub: Uuid,
+    pub email: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
 }
 
+// access and refresh tokens share a key and a claim shape, aud tells them apart
 #[derive(Deserialize, Serialize)]
 pub struct TokenClaims {
     pub sub: Uuid,
+    pub aud: String,
     pub exp: i64,
     pub iat: i64,
+    pub jti: String,
+    pub sid: String,
+}
+
+const ACCESS_AUDIENCE: &str = "access";
+const REFRESH_AUDIENCE: &str = "refresh";
+const EMAIL_VERIFICATION_AUDIENCE: &str = "email_verification";
+
+// verification-only keys stay listed after a rotation until their tokens expired
+struct JwtKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    // public half for the JWKS endpoint, None for shared secrets
+    jwk: Option<Jwk>,
 }
 
 #[derive(Clone)]
 pub struct JwtService {
-    secret: String,
+    keys: Arc<Vec<JwtKey>>,
+    signing: usize,
 }
 
 impl JwtService {
+    // HS256 with a shared secret, tokens carry no kid
     pub fn new(secret: String) -> Self {
-        Self { secret }
+        let key = JwtKey {
+            kid: None,
+            algorithm: Algorithm::HS256,
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        };
+
+        Self {
+            keys: Arc::new(vec![key]),
+            signing: 0,
+        }
     }
 
-    pub fn create_access_token(&self, sub: Uuid) -> Result<String, Error> {
+    // specs are comma separated kid:algorithm:path entries, e.g. 2026-10:RS256:keys/a.pem
+    pub fn from_pem_keys(specs: &str, signing_kid: Option<&str>) -> anyhow::Result<Self> {
+        let mut keys = Vec::new();
+
+        for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut parts = spec.splitn(3, ':');
+            let (Some(kid), Some(algorithm), Some(path)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                bail!("invalid JWT_KEYS entry {spec}, expected kid:algorithm:path");
+            };
+
+            if keys.iter().any(|k: &JwtKey| k.kid.as_deref() == Some(kid)) {
+                bail!("duplicate kid {kid} in JWT_KEYS");
+            }
+
+            let pem = std::fs::read(path).with_context(|| format!("failed to read {path}"))?;
+            let key = load_pem_key(kid, algorithm, &pem)
+                .with_context(|| format!("invalid key {kid} in {path}"))?;
+
+            keys.push(key);
+        }
+
+        let signing = match signing_kid {
+            Some(kid) => keys
+                .iter()
+                .position(|k| k.kid.as_deref() == Some(kid))
+                .with_context(|| format!("JWT_SIGNING_KID {kid} is not in JWT_KEYS"))?,
+            None if keys.is_empty() => bail!("JWT_KEYS has no keys"),
+            None => 0,
+        };
+
+        Ok(Self {
+            keys: Arc::new(keys),
+            signing,
+        })
+    }
+
+    // public keys of every active kid, so other services can verify our tokens
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|k| k.jwk.clone()).collect(),
+        }
+    }
+
+    fn encode<T: Serialize>(&self, claims: &T) -> Result<String, Error> {
+        let key = &self.keys[self.signing];
+        let mut header = Header::new(key.algorithm);
+        header.kid = key.kid.clone();
+
+        encode(&header, claims, &key.encoding)
+    }
+
+    fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        configure: impl FnOnce(&mut Validation),
+    ) -> Result<T, Error> {
+        let header = decode_header(token)?;
+
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.kid == header.kid)
+            .ok_or(ErrorKind::InvalidToken)?;
+
+        // pinned to the key's algorithm, the header alg is never trusted
+        let mut validation = Validation::new(key.algorithm);
+        configure(&mut validation);
+
+        Ok(decode::<T>(token, &key.decoding, &validation)?.claims)
+    }
+
+    pub fn create_access_token(&self, sub: Uuid, sid: &str) -> Result<String, Error> {
         let claims = TokenClaims {
             sub,
+            aud: ACCESS_AUDIENCE.to_owned(),
             exp: (Utc::now() + Duration::seconds(15)).timestamp(),
             // exp: (Utc::now() + Duration::minutes(15)).timestamp(),
             iat: Utc::now().timestamp(),
+            jti: create_uuid(),
+            sid: sid.to_owned(),
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.secret.as_bytes()),
-        )
+        self.encode(&claims)
     }
 
     pub fn validate_token(&self, token: &str) -> Result<TokenClaims, Error> {
-        let token = decode::<TokenClaims>(
-            token,
-            &DecodingKey::from_secret(self.secret.as_bytes()),
-            &Validation::default(),
-        )?;
+        self.decode(token, |validation| {
+            validation.set_audience(&[ACCESS_AUDIENCE])
+        })
+    }
 
-        Ok(token.claims)
+    // the email is signed too, a link sent to a previous address stops working
+    pub fn create_email_verification_token(&self, sub: Uuid, email: &str) -> Result<String, Error> {
+        let claims = EmailVerificationClaims {
+            sub,
+            email: email.to_owned(),
+            aud: EMAIL_VERIFICATION_AUDIENCE.to_owned(),
+            exp: (Utc::now() + Duration::days(1)).timestamp(),
+            iat: Utc::now().timestamp(),
+        };
+
+        self.encode(&claims)
     }
 
-    pub fn create_refresh_token(&self, jti: &str, sub: Uuid) -> Result<String, Error> {
+    pub fn validate_email_verification_token(
+        &self,
+        token: &str,
+    ) -> Result<EmailVerificationClaims, Error> {
+        self.decode(token, |validation| {
+            validation.set_audience(&[EMAIL_VERIFICATION_AUDIENCE])
+        })
+    }
+
+    pub fn create_refresh_token(&self, jti: &str, sub: Uuid, sid: &str) -> Result<String, Error> {
         let claims = RefreshTokenClaims {
             sub,
+            aud: REFRESH_AUDIENCE.to_owned(),
             exp: (Utc::now() + Duration::days(7)).timestamp(),
             iat: Utc::now().timestamp(),
             jti: jti.to_owned(),
+            sid: sid.to_owned(),
         };
 
-        encode(
-            &Header::default(),
+        self.encode(&claims)
+    }
+
+    pub fn validate_refresh_token(&self, token: &str) -> Result<RefreshTokenClaims, Error> {
+        self.decode(token, |validation| {
+            validation.set_audience(&[REFRESH_AUDIENCE])
+        })
+    }
+}
+
+fn load_pem_key(kid: &str, algorithm: &str, pem: &[u8]) -> anyhow::Result<JwtKey> {
+    let common = |key_algorithm| CommonParameters {
+        public_key_use: Some(PublicKeyUse::Signature),
+        key_algorithm: Some(key_algorithm),
+        key_id: Some(kid.to_owned()),
+        ..Default::default()
+    };
+
+    match algorithm {
+        "RS256" => {
+            let encoding = EncodingKey::from_rsa_pem(pem)?;
+            let mut jwk = Jwk::from_encoding_key(&encoding, Algorithm::RS256)?;
+            jwk.common = common(KeyAlgorithm::RS256);
+
+            Ok(JwtKey {
+                kid: Some(kid.to_owned()),
+                algorithm: Algorithm::RS256,
+                decoding: DecodingKey::from_jwk(&jwk)?,
+                encoding,
+                jwk: Some(jwk),
+            })
+        }
+        "EdDSA" => {
+            // jsonwebtoken can't derive an Ed25519 public key, dalek can
+            let pem = std::str::from_utf8(pem)?;
+            let public = SigningKey::from_pkcs8_pem(pem)
+                .map_err(|e| anyhow::anyhow!("{e}"))?
+                .verifying_key();
+
+            let jwk = Jwk {
+                common: common(KeyAlgorithm::EdDSA),
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(public.as_bytes()),
+                }),
+            };
+
+            Ok(JwtKey {
+                kid: Some(kid.to_owned()),
+                algorithm: Algorithm::EdDSA,
+                encoding: EncodingKey::from_ed_pem(pem.as_bytes())?,
+                decoding: DecodingKey::from_jwk(&jwk)?,
+                jwk: Some(jwk),
+            })
+        }
+        _ => bail!("unsupported algorithm {algorithm}, use RS256 or EdDSA"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> JwtService {
+        JwtService::new("test-secret".to_owned())
+    }
+
+    #[test]
+    fn access_token_round_trips() {
+        let jwt = service();
+        let sub = Uuid::new_v4();
+        let token = jwt.create_access_token(sub, "session").unwrap();
+
+        let claims = jwt.validate_token(&token).unwrap();
+
+        assert_eq!(claims.sub, sub);
+        assert_eq!(claims.sid, "session");
+    }
+
+    #[test]
+    fn rejects_tokens_signed_with_another_key() {
+        let token = JwtService::new("other-secret".to_owned())
+            .create_access_token(Uuid::new_v4(), "session")
+            .unwrap();
+
+        assert!(service().validate_token(&token).is_err());
+    }
+
+    #[test]
+    fn rejects_a_kid_that_is_not_configured() {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("retired".to_owned());
+
+        let claims = serde_json::json!({
+            "sub": Uuid::new_v4(),
+            "exp": (Utc::now() + Duration::minutes(5)).timestamp(),
+        });
+        let token = encode(
+            &header,
             &claims,
-            &EncodingKey::from_secret(self.secret.as_bytes()),
+            &EncodingKey::from_secret("test-secret".as_bytes()),
         )
+        .unwrap();
+
+        assert!(matches!(
+            service().validate_token(&token),
+            Err(e) if matches!(e.kind(), ErrorKind::InvalidToken)
+        ));
     }
 
-    pub fn validate_refresh_token(&self, token: &str) -> Result<RefreshTokenClaims, Error> {
-        let token = decode::<RefreshTokenClaims>(
-            token,
-            &DecodingKey::from_secret(self.secret.as_bytes()),
-            &Validation::default(),
-        )?;
+    #[test]
+    fn refresh_token_is_not_an_access_token() {
+        let jwt = service();
+        let token = jwt
+            .create_refresh_token("jti", Uuid::new_v4(), "session")
+            .unwrap();
+
+        assert!(jwt.validate_refresh_token(&token).is_ok());
+        assert!(matches!(
+            jwt.validate_token(&token),
+            Err(e) if matches!(e.kind(), ErrorKind::InvalidAudience)
+        ));
+    }
+
+    #[test]
+    fn access_token_is_not_a_refresh_token() {
+        let jwt = service();
+        let token = jwt.create_access_token(Uuid::new_v4(), "session").unwrap();
+
+        assert!(matches!(
+            jwt.validate_refresh_token(&token),
+            Err(e) if matches!(e.kind(), ErrorKind::InvalidAudience)
+        ));
+    }
+
+    #[test]
+    fn email_verification_token_is_neither() {
+        let jwt = service();
+        let token = jwt
+            .create_email_verification_token(Uuid::new_v4(), "a@example.com")
+            .unwrap();
 
-        Ok(token.claims)
+        assert!(jwt.validate_email_verification_token(&token).is_ok());
+        assert!(jwt.validate_token(&token).is_err());
+        assert!(jwt.validate_refresh_token(&token).is_err());
     }
 }
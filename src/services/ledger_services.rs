@@ -0,0 +1,640 @@
+use sqlx::{PgConnection, PgExecutor, PgPool, query, query_as, query_scalar};
+use uuid::Uuid;
+
+use crate::{
+    errors::ledger_errors::LedgerError,
+    models::ledger_models::{
+        InvitationPath, InvitationRequest, InvitationResponse, InvitationTokenRequest,
+        LedgerAccess, LedgerPath, LedgerRequest, LedgerResponse, LedgerRole, MemberPath,
+        MemberResponse, MemberRoleRequest,
+    },
+    services::{
+        mailer_services::{Mail, MailerService},
+        redis_services::RedisService,
+    },
+    utils::utils::{
+        all_expenses_version_key, categories_version_key, category_filter_pattern, create_token,
+        hash_token, recurring_version_key, single_categories_pattern, single_expenses_pattern,
+    },
+};
+
+#[derive(Clone)]
+pub struct LedgerService {
+    pool: PgPool,
+}
+
+impl LedgerService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // without a ledger id the user's default one is used, then the oldest they belong to
+    pub async fn authorize(
+        &self,
+        ledger_id: Option<Uuid>,
+        user_id: Uuid,
+    ) -> Result<LedgerAccess, LedgerError> {
+        query_as::<_, LedgerAccess>(
+            r#"
+                SELECT m.ledger_id, m.user_id, m.role FROM ledger_member m
+                WHERE m.user_id = $2 AND m.ledger_id = COALESCE(
+                    $1,
+                    (SELECT default_ledger_id FROM users WHERE id = $2),
+                    (SELECT ledger_id FROM ledger_member WHERE user_id = $2
+                        ORDER BY created_at LIMIT 1)
+                )
+            "#,
+        )
+        .bind(ledger_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(LedgerError::internal)?
+        .ok_or(LedgerError::LedgerNotFound)
+    }
+
+    pub async fn add_ledger(
+        &self,
+        body: LedgerRequest,
+        user_id: Uuid,
+    ) -> Result<LedgerResponse, LedgerError> {
+        body.validate()?;
+
+        let mut tx = self.pool.begin().await.map_err(LedgerError::internal)?;
+
+        let ledger = query_as::<_, LedgerResponse>(
+            r#"
+                INSERT INTO ledger (name) VALUES ($1)
+                RETURNING id, name, 'owner'::ledger_role AS role, false AS is_default
+            "#,
+        )
+        .bind(body.name.trim())
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(LedgerError::internal)?;
+
+        query("INSERT INTO ledger_member (ledger_id, user_id, role) VALUES ($1, $2, 'owner')")
+            .bind(ledger.id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(LedgerError::internal)?;
+
+        tx.commit().await.map_err(LedgerError::internal)?;
+
+        Ok(ledger)
+    }
+
+    pub async fn get_ledgers(&self, user_id: Uuid) -> Result<Vec<LedgerResponse>, LedgerError> {
+        query_as::<_, LedgerResponse>(
+            r#"
+                SELECT l.id, l.name, m.role,
+                    COALESCE(l.id = u.default_ledger_id, false) AS is_default
+                FROM ledger_member m
+                JOIN ledger l ON l.id = m.ledger_id
+                JOIN users u ON u.id = m.user_id
+                WHERE m.user_id = $1
+                ORDER BY l.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LedgerError::internal)
+    }
+
+    pub async fn edit_ledger(
+        &self,
+        body: LedgerRequest,
+        path: LedgerPath,
+        user_id: Uuid,
+    ) -> Result<LedgerResponse, LedgerError> {
+        body.validate()?;
+
+        require_owner(&self.pool, path.ledger_id, user_id).await?;
+
+        query_as::<_, LedgerResponse>(
+            r#"
+                UPDATE ledger SET name = $2, updated_at = NOW()
+                WHERE id = $1
+                RETURNING id, name, 'owner'::ledger_role AS role,
+                    COALESCE(id = (SELECT default_ledger_id FROM users WHERE id = $3), false)
+                        AS is_default
+            "#,
+        )
+        .bind(path.ledger_id)
+        .bind(body.name.trim())
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(LedgerError::internal)
+    }
+
+    // categories and expenses of the ledger go with it
+    pub async fn delete_ledger(
+        &self,
+        path: LedgerPath,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<String, LedgerError> {
+        let mut tx = self.pool.begin().await.map_err(LedgerError::internal)?;
+
+        require_owner(&mut *tx, path.ledger_id, user_id).await?;
+
+        let members = member_ids(&mut *tx, path.ledger_id)
+            .await
+            .map_err(LedgerError::internal)?;
+
+        let id: Uuid = query_scalar("DELETE FROM ledger WHERE id = $1 RETURNING id")
+            .bind(path.ledger_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(LedgerError::internal)?;
+
+        tx.commit().await.map_err(LedgerError::internal)?;
+
+        for member in members {
+            forget_cached(redis, member).await?;
+        }
+
+        Ok(id.to_string())
+    }
+
+    pub async fn get_members(
+        &self,
+        path: LedgerPath,
+        user_id: Uuid,
+    ) -> Result<Vec<MemberResponse>, LedgerError> {
+        self.authorize(Some(path.ledger_id), user_id).await?;
+
+        query_as::<_, MemberResponse>(
+            r#"
+                SELECT m.user_id, u.name, u.email, m.role, m.created_at
+                FROM ledger_member m
+                JOIN users u ON u.id = m.user_id
+                WHERE m.ledger_id = $1
+                ORDER BY m.created_at
+            "#,
+        )
+        .bind(path.ledger_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LedgerError::internal)
+    }
+
+    pub async fn edit_member(
+        &self,
+        body: MemberRoleRequest,
+        path: MemberPath,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<MemberResponse, LedgerError> {
+        let mut tx = self.pool.begin().await.map_err(LedgerError::internal)?;
+
+        require_owner(&mut *tx, path.ledger_id, user_id).await?;
+
+        if body.role != LedgerRole::Owner {
+            ensure_other_owner(&mut tx, path.ledger_id, path.user_id).await?;
+        }
+
+        let member = query_as::<_, MemberResponse>(
+            r#"
+                UPDATE ledger_member m SET role = $3
+                FROM users u
+                WHERE m.ledger_id = $1 AND m.user_id = $2 AND u.id = m.user_id
+                RETURNING m.user_id, u.name, u.email, m.role, m.created_at
+            "#,
+        )
+        .bind(path.ledger_id)
+        .bind(path.user_id)
+        .bind(body.role)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(LedgerError::internal)?
+        .ok_or(LedgerError::MemberNotFound)?;
+
+        let dropped = if member.role == LedgerRole::Viewer {
+            drop_schedules(&mut tx, path.ledger_id, path.user_id).await?
+        } else {
+            0
+        };
+
+        tx.commit().await.map_err(LedgerError::internal)?;
+
+        if dropped > 0 {
+            redis
+                .incr(&recurring_version_key(path.user_id))
+                .await
+                .map_err(LedgerError::internal)?;
+        }
+
+        Ok(member)
+    }
+
+    // owners remove anyone, everyone else can only leave
+    pub async fn remove_member(
+        &self,
+        path: MemberPath,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<String, LedgerError> {
+        let mut tx = self.pool.begin().await.map_err(LedgerError::internal)?;
+
+        if path.user_id == user_id {
+            member_role(&mut *tx, path.ledger_id, user_id).await?;
+        } else {
+            require_owner(&mut *tx, path.ledger_id, user_id).await?;
+        }
+
+        ensure_other_owner(&mut tx, path.ledger_id, path.user_id).await?;
+
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM ledger_member
+                WHERE ledger_id = $1 AND user_id = $2
+                RETURNING user_id
+            "#,
+        )
+        .bind(path.ledger_id)
+        .bind(path.user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(LedgerError::internal)?
+        .ok_or(LedgerError::MemberNotFound)?;
+
+        query(
+            r#"
+                UPDATE users SET default_ledger_id = NULL
+                WHERE id = $1 AND default_ledger_id = $2
+            "#,
+        )
+        .bind(path.user_id)
+        .bind(path.ledger_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(LedgerError::internal)?;
+
+        drop_schedules(&mut tx, path.ledger_id, id).await?;
+
+        tx.commit().await.map_err(LedgerError::internal)?;
+
+        forget_cached(redis, id).await?;
+
+        redis
+            .incr(&recurring_version_key(id))
+            .await
+            .map_err(LedgerError::internal)?;
+
+        Ok(id.to_string())
+    }
+
+    // the mail goes out before the invitation is committed, so a failed send leaves none
+    pub async fn invite(
+        &self,
+        body: InvitationRequest,
+        path: LedgerPath,
+        mailer: &MailerService,
+        user_id: Uuid,
+    ) -> Result<InvitationResponse, LedgerError> {
+        body.validate()?;
+
+        let mut tx = self.pool.begin().await.map_err(LedgerError::internal)?;
+
+        require_owner(&mut *tx, path.ledger_id, user_id).await?;
+
+        let email = body.email.trim();
+
+        let already_member: bool = query_scalar(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM ledger_member m
+                    JOIN users u ON u.id = m.user_id
+                    WHERE m.ledger_id = $1 AND LOWER(u.email) = LOWER($2)
+                )
+            "#,
+        )
+        .bind(path.ledger_id)
+        .bind(email)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(LedgerError::internal)?;
+
+        if already_member {
+            return Err(LedgerError::AlreadyMember);
+        }
+
+        let token = create_token();
+
+        let invitation = query_as::<_, InvitationResponse>(
+            r#"
+                INSERT INTO ledger_invitation (ledger_id, email, role, invited_by, token_hash)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id, ledger_id, email, role, invited_by, status, created_at
+            "#,
+        )
+        .bind(path.ledger_id)
+        .bind(email)
+        .bind(body.role)
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            if let sqlx::Error::Database(db_err) = &e
+                && db_err.code().as_deref() == Some("23505")
+            {
+                return LedgerError::InvitationExisting;
+            }
+
+            LedgerError::internal(e)
+        })?;
+
+        let (ledger, inviter): (String, Option<String>) = query_as(
+            r#"
+                SELECT l.name, u.name FROM ledger l, users u
+                WHERE l.id = $1 AND u.id = $2
+            "#,
+        )
+        .bind(path.ledger_id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(LedgerError::internal)?;
+
+        let mail = Mail {
+            to: invitation.email.clone(),
+            subject: format!("You're invited to {ledger}"),
+            body: format!(
+                "{} invited you to share the ledger \"{ledger}\".\n\n\
+                Sign in with this address and open this link to accept or decline:\n{}",
+                inviter.as_deref().unwrap_or("Someone"),
+                mailer.link("/ledger-invitation", &token)
+            ),
+        };
+
+        mailer.send(mail).await.map_err(LedgerError::internal)?;
+
+        tx.commit().await.map_err(LedgerError::internal)?;
+
+        Ok(invitation)
+    }
+
+    pub async fn get_invitations(
+        &self,
+        path: LedgerPath,
+        user_id: Uuid,
+    ) -> Result<Vec<InvitationResponse>, LedgerError> {
+        require_owner(&self.pool, path.ledger_id, user_id).await?;
+
+        query_as::<_, InvitationResponse>(
+            r#"
+                SELECT id, ledger_id, email, role, invited_by, status, created_at
+                FROM ledger_invitation
+                WHERE ledger_id = $1 AND status = 'pending'
+                ORDER BY created_at DESC
+            "#,
+        )
+        .bind(path.ledger_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LedgerError::internal)
+    }
+
+    pub async fn revoke_invitation(
+        &self,
+        path: InvitationPath,
+        user_id: Uuid,
+    ) -> Result<String, LedgerError> {
+        require_owner(&self.pool, path.ledger_id, user_id).await?;
+
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM ledger_invitation
+                WHERE id = $1 AND ledger_id = $2 AND status = 'pending'
+                RETURNING id
+            "#,
+        )
+        .bind(path.invitation_id)
+        .bind(path.ledger_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(LedgerError::internal)?
+        .ok_or(LedgerError::InvitationNotFound)?;
+
+        Ok(id.to_string())
+    }
+
+    pub async fn accept_invitation(
+        &self,
+        body: InvitationTokenRequest,
+        user_id: Uuid,
+    ) -> Result<LedgerResponse, LedgerError> {
+        let mut tx = self.pool.begin().await.map_err(LedgerError::internal)?;
+
+        let (ledger_id, role): (Uuid, LedgerRole) =
+            respond(&mut tx, &body.token, user_id, "accepted").await?;
+
+        query(
+            r#"
+                INSERT INTO ledger_member (ledger_id, user_id, role)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (ledger_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(ledger_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(&mut *tx)
+        .await
+        .map_err(LedgerError::internal)?;
+
+        let ledger = query_as::<_, LedgerResponse>(
+            r#"
+                SELECT l.id, l.name, m.role,
+                    COALESCE(l.id = u.default_ledger_id, false) AS is_default
+                FROM ledger_member m
+                JOIN ledger l ON l.id = m.ledger_id
+                JOIN users u ON u.id = m.user_id
+                WHERE m.ledger_id = $1 AND m.user_id = $2
+            "#,
+        )
+        .bind(ledger_id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(LedgerError::internal)?;
+
+        tx.commit().await.map_err(LedgerError::internal)?;
+
+        Ok(ledger)
+    }
+
+    pub async fn decline_invitation(
+        &self,
+        body: InvitationTokenRequest,
+        user_id: Uuid,
+    ) -> Result<String, LedgerError> {
+        let mut tx = self.pool.begin().await.map_err(LedgerError::internal)?;
+
+        let (ledger_id, _): (Uuid, LedgerRole) =
+            respond(&mut tx, &body.token, user_id, "declined").await?;
+
+        tx.commit().await.map_err(LedgerError::internal)?;
+
+        Ok(ledger_id.to_string())
+    }
+}
+
+// cache invalidation reaches every member, each one caches their own view
+pub async fn member_ids<'e>(
+    executor: impl PgExecutor<'e>,
+    ledger_id: Uuid,
+) -> sqlx::Result<Vec<Uuid>> {
+    query_scalar("SELECT user_id FROM ledger_member WHERE ledger_id = $1")
+        .bind(ledger_id)
+        .fetch_all(executor)
+        .await
+}
+
+// everyone sharing a ledger with the user, the user included
+pub async fn co_member_ids<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> sqlx::Result<Vec<Uuid>> {
+    query_scalar(
+        r#"
+            SELECT DISTINCT m.user_id FROM ledger_member m
+            JOIN ledger_member own ON own.ledger_id = m.ledger_id AND own.user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await
+}
+
+async fn member_role<'e>(
+    executor: impl PgExecutor<'e>,
+    ledger_id: Uuid,
+    user_id: Uuid,
+) -> Result<LedgerRole, LedgerError> {
+    query_scalar("SELECT role FROM ledger_member WHERE ledger_id = $1 AND user_id = $2")
+        .bind(ledger_id)
+        .bind(user_id)
+        .fetch_optional(executor)
+        .await
+        .map_err(LedgerError::internal)?
+        .ok_or(LedgerError::LedgerNotFound)
+}
+
+async fn require_owner<'e>(
+    executor: impl PgExecutor<'e>,
+    ledger_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), LedgerError> {
+    match member_role(executor, ledger_id, user_id).await? {
+        LedgerRole::Owner => Ok(()),
+        _ => Err(LedgerError::Forbidden),
+    }
+}
+
+// an owner can only step down or leave while another owner stays
+async fn ensure_other_owner(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), LedgerError> {
+    let last_owner: bool = query_scalar(
+        r#"
+            SELECT EXISTS (
+                SELECT 1 FROM ledger_member
+                WHERE ledger_id = $1 AND user_id = $2 AND role = 'owner'
+            ) AND NOT EXISTS (
+                SELECT 1 FROM ledger_member
+                WHERE ledger_id = $1 AND user_id <> $2 AND role = 'owner'
+            )
+        "#,
+    )
+    .bind(ledger_id)
+    .bind(user_id)
+    .fetch_one(conn)
+    .await
+    .map_err(LedgerError::internal)?;
+
+    if last_owner {
+        return Err(LedgerError::LastOwner);
+    }
+
+    Ok(())
+}
+
+// only the invited address can answer, a forwarded link is no use to anyone else
+async fn respond(
+    conn: &mut PgConnection,
+    token: &str,
+    user_id: Uuid,
+    status: &str,
+) -> Result<(Uuid, LedgerRole), LedgerError> {
+    query_as(
+        r#"
+            UPDATE ledger_invitation i
+            SET status = $3::ledger_invitation_status, responded_at = NOW()
+            FROM users u
+            WHERE i.token_hash = $1 AND i.status = 'pending'
+                AND u.id = $2 AND LOWER(u.email) = LOWER(i.email)
+            RETURNING i.ledger_id, i.role
+        "#,
+    )
+    .bind(hash_token(token))
+    .bind(user_id)
+    .bind(status)
+    .fetch_optional(conn)
+    .await
+    .map_err(LedgerError::internal)?
+    .ok_or(LedgerError::InvitationNotFound)
+}
+
+// recurring expenses are written as their author, who must still be allowed to write here
+async fn drop_schedules(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    user_id: Uuid,
+) -> Result<u64, LedgerError> {
+    let dropped = query(
+        r#"
+            DELETE FROM recurring_expense r
+            USING category c
+            WHERE c.id = r.category_id AND c.ledger_id = $1 AND r.user_id = $2
+        "#,
+    )
+    .bind(ledger_id)
+    .bind(user_id)
+    .execute(conn)
+    .await
+    .map_err(LedgerError::internal)?;
+
+    Ok(dropped.rows_affected())
+}
+
+// a membership change drops everything the user has cached, in every ledger
+pub async fn forget_cached(redis: &RedisService, user_id: Uuid) -> Result<(), LedgerError> {
+    redis
+        .pipeline::<()>(|pipe| {
+            pipe.incr(all_expenses_version_key(user_id), 1)
+                .incr(categories_version_key(user_id), 1);
+        })
+        .await
+        .map_err(LedgerError::internal)?;
+
+    for pattern in [
+        single_expenses_pattern(user_id),
+        single_categories_pattern(user_id),
+        category_filter_pattern(user_id),
+    ] {
+        redis
+            .delete_pattern(&pattern)
+            .await
+            .map_err(LedgerError::internal)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use anyhow::Context;
+use chrono::Utc;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::header::ContentType,
+};
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::utils::utils::create_uuid;
+
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// picked with MAILER, file and log are meant for local development
+#[derive(Clone)]
+enum MailTransport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(PathBuf),
+    Log,
+}
+
+#[derive(Clone)]
+pub struct MailerService {
+    app_url: String,
+    from: String,
+    transport: MailTransport,
+}
+
+impl MailerService {
+    pub fn smtp(url: &str, from: String, app_url: String) -> anyhow::Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+            .context("invalid SMTP_URL")?
+            .build();
+
+        Ok(Self {
+            app_url,
+            from,
+            transport: MailTransport::Smtp(transport),
+        })
+    }
+
+    pub fn file(dir: PathBuf, from: String, app_url: String) -> Self {
+        Self {
+            app_url,
+            from,
+            transport: MailTransport::File(dir),
+        }
+    }
+
+    pub fn log(from: String, app_url: String) -> Self {
+        Self {
+            app_url,
+            from,
+            transport: MailTransport::Log,
+        }
+    }
+
+    pub fn link(&self, path: &str, token: &str) -> String {
+        format!(
+            "{}{}?token={}",
+            self.app_url.trim_end_matches('/'),
+            path,
+            token
+        )
+    }
+
+    pub async fn send(&self, mail: Mail) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.parse().context("invalid MAIL_FROM")?)
+            .to(mail.to.parse().context("invalid recipient")?)
+            .subject(mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body)
+            .context("failed to build mail")?;
+
+        match &self.transport {
+            MailTransport::Smtp(transport) => {
+                transport
+                    .send(message)
+                    .await
+                    .context("failed to send mail")?;
+            }
+            MailTransport::File(dir) => {
+                let path = dir.join(format!("{}-{}.eml", Utc::now().timestamp(), create_uuid()));
+
+                actix_web::rt::task::spawn_blocking(move || -> anyhow::Result<()> {
+                    std::fs::create_dir_all(path.parent().context("invalid MAIL_DIR")?)?;
+                    std::fs::write(&path, message.formatted())?;
+
+                    info!(path = %path.display(), "Mail written to file.");
+
+                    Ok(())
+                })
+                .await
+                .context("mail writer panicked")??;
+            }
+            MailTransport::Log => {
+                info!(
+                    mail = %String::from_utf8_lossy(&message.formatted()),
+                    "Mail not sent, MAILER=log."
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,18 @@
+pub mod account_services;
+pub mod api_token_services;
 pub mod auth_services;
+pub mod budget_services;
 pub mod category_services;
+pub mod currency_services;
+pub mod data_export_services;
 pub mod expense_services;
+pub mod export_services;
+pub mod import_services;
+pub mod income_services;
 pub mod jwt_services;
+pub mod ledger_services;
+pub mod mailer_services;
+pub mod recurring_services;
 pub mod redis_services;
+pub mod report_services;
+pub mod trash_services;
@@ -0,0 +1,598 @@
+use chrono::{Days, NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool, query, query_as, query_scalar, types::Json};
+use std::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    errors::expense_errors::ExpenseError,
+    models::{
+        expense_model::{ExpenseHistoryAction, ExpenseResponse, ExpenseSplit, ExpenseSplitRequest},
+        recurring_models::{
+            RecurringCached, RecurringPagination, RecurringPath, RecurringRequest,
+            RecurringResponse, UpcomingOccurrence, UpcomingParams, occurrence,
+        },
+    },
+    services::{
+        expense_services::{ExpenseServices, ensure_convertible, record_history, save_splits},
+        redis_services::RedisService,
+    },
+    utils::utils::recurring_version_key,
+};
+
+// caps a single catch-up run, the next tick continues where this one stopped
+const MAX_OCCURRENCES_PER_RUN: i32 = 366;
+const MAX_UPCOMING_PER_SCHEDULE: usize = 100;
+
+#[derive(Clone)]
+pub struct RecurringService {
+    pool: PgPool,
+}
+
+impl RecurringService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn add_recurring(
+        &self,
+        body: RecurringRequest,
+        expenses: &ExpenseServices,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<RecurringResponse, ExpenseError> {
+        body.validate()?;
+
+        let day_of_month = body.day_of_month();
+        let next_occurrence = occurrence(
+            body.frequency,
+            body.start_date,
+            body.interval,
+            day_of_month,
+            0,
+        )
+        .filter(|date| body.end_date.is_none_or(|end| *date <= end));
+
+        let splits: Vec<ExpenseSplit> = body.split_lines().iter().map(Into::into).collect();
+        let split_categories: Vec<Uuid> = splits.iter().map(|s| s.category_id).collect();
+
+        let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+
+        let recurring = query_as::<_, RecurringResponse>(
+            r#"
+                INSERT INTO recurring_expense (user_id, category_id, amount, description,
+                    account_id, tags, frequency, repeat_interval, day_of_month,
+                    start_date, end_date, next_occurrence, currency, splits)
+                SELECT $1, id, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14 FROM category
+                WHERE id = $2 AND deleted_at IS NULL
+                    AND ledger_id IN (
+                        SELECT ledger_id FROM ledger_member
+                        WHERE user_id = $1 AND role <> 'viewer'
+                    )
+                    AND ($5::uuid IS NULL OR EXISTS (
+                        SELECT 1 FROM account WHERE id = $5 AND user_id = $1
+                    ))
+                    AND NOT EXISTS (
+                        SELECT 1 FROM unnest($15::uuid[]) AS split(category_id)
+                        WHERE NOT EXISTS (
+                            SELECT 1 FROM category c
+                            WHERE c.id = split.category_id AND c.ledger_id = category.ledger_id
+                                AND c.deleted_at IS NULL
+                        )
+                    )
+                RETURNING id, user_id, category_id, amount, currency, description, account_id, tags,
+                    frequency, repeat_interval, day_of_month, start_date, end_date,
+                    occurrence_count, next_occurrence, splits
+            "#,
+        )
+        .bind(user_id)
+        .bind(body.category_id)
+        .bind(body.amount)
+        .bind(body.description)
+        .bind(body.account_id)
+        .bind(body.tags)
+        .bind(body.frequency)
+        .bind(body.interval)
+        .bind(day_of_month)
+        .bind(body.start_date)
+        .bind(body.end_date)
+        .bind(next_occurrence)
+        .bind(body.currency)
+        .bind(Json(splits))
+        .bind(split_categories)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ExpenseError::internal)?
+        .ok_or(ExpenseError::ForeignKeyNotFound)?;
+
+        redis
+            .incr(&recurring_version_key(user_id))
+            .await
+            .map_err(ExpenseError::internal)?;
+
+        tx.commit().await.map_err(ExpenseError::internal)?;
+
+        // backfill right away instead of waiting for the next worker tick
+        self.materialize_schedule(recurring.id, Utc::now().date_naive(), expenses, redis)
+            .await?;
+
+        Ok(recurring)
+    }
+
+    // occurrences already generated stay as they are, the rest follow the new schedule
+    pub async fn edit_recurring(
+        &self,
+        body: RecurringRequest,
+        path: RecurringPath,
+        expenses: &ExpenseServices,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<RecurringResponse, ExpenseError> {
+        body.validate()?;
+
+        let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+
+        let count: i32 = query_scalar(
+            r#"
+                SELECT occurrence_count FROM recurring_expense
+                WHERE id = $1 AND user_id = $2
+                FOR UPDATE
+            "#,
+        )
+        .bind(path.recurring_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ExpenseError::internal)?
+        .ok_or(ExpenseError::RecurringNotFound)?;
+
+        let day_of_month = body.day_of_month();
+        let next_occurrence = occurrence(
+            body.frequency,
+            body.start_date,
+            body.interval,
+            day_of_month,
+            count,
+        )
+        .filter(|date| body.end_date.is_none_or(|end| *date <= end));
+
+        let splits: Vec<ExpenseSplit> = body.split_lines().iter().map(Into::into).collect();
+        let split_categories: Vec<Uuid> = splits.iter().map(|s| s.category_id).collect();
+
+        let recurring = query_as::<_, RecurringResponse>(
+            r#"
+                UPDATE recurring_expense
+                SET category_id = $3, amount = $4, description = $5, account_id = $6,
+                    tags = $7, frequency = $8, repeat_interval = $9, day_of_month = $10,
+                    start_date = $11, end_date = $12, next_occurrence = $13, currency = $14,
+                    splits = $15, updated_at = NOW()
+                WHERE id = $1 AND user_id = $2
+                    AND EXISTS (
+                        SELECT 1 FROM category
+                        WHERE id = $3 AND deleted_at IS NULL
+                            AND ledger_id IN (
+                                SELECT ledger_id FROM ledger_member
+                                WHERE user_id = $2 AND role <> 'viewer'
+                            )
+                            AND NOT EXISTS (
+                                SELECT 1 FROM unnest($16::uuid[]) AS split(category_id)
+                                WHERE NOT EXISTS (
+                                    SELECT 1 FROM category c
+                                    WHERE c.id = split.category_id
+                                        AND c.ledger_id = category.ledger_id
+                                        AND c.deleted_at IS NULL
+                                )
+                            )
+                    )
+                    AND ($6::uuid IS NULL OR EXISTS (
+                        SELECT 1 FROM account WHERE id = $6 AND user_id = $2
+                    ))
+                RETURNING id, user_id, category_id, amount, currency, description, account_id, tags,
+                    frequency, repeat_interval, day_of_month, start_date, end_date,
+                    occurrence_count, next_occurrence, splits
+            "#,
+        )
+        .bind(path.recurring_id)
+        .bind(user_id)
+        .bind(body.category_id)
+        .bind(body.amount)
+        .bind(body.description)
+        .bind(body.account_id)
+        .bind(body.tags)
+        .bind(body.frequency)
+        .bind(body.interval)
+        .bind(day_of_month)
+        .bind(body.start_date)
+        .bind(body.end_date)
+        .bind(next_occurrence)
+        .bind(body.currency)
+        .bind(Json(splits))
+        .bind(split_categories)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ExpenseError::internal)?
+        .ok_or(ExpenseError::ForeignKeyNotFound)?;
+
+        redis
+            .incr(&recurring_version_key(user_id))
+            .await
+            .map_err(ExpenseError::internal)?;
+
+        tx.commit().await.map_err(ExpenseError::internal)?;
+
+        // an earlier start date can make occurrences due right away
+        self.materialize_schedule(recurring.id, Utc::now().date_naive(), expenses, redis)
+            .await?;
+
+        Ok(recurring)
+    }
+
+    pub async fn get_user_recurring(
+        &self,
+        params: RecurringPagination,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<RecurringCached, ExpenseError> {
+        let limit: i64 = 10;
+        let page = params.page.max(1);
+        let offset = (page - 1) * limit;
+        let key = recurring_version_key(user_id);
+
+        let (_, v): (i64, String) = redis
+            .pipeline(|pipe| {
+                pipe.set_nx(&key, "1").get(&key);
+            })
+            .await
+            .map_err(ExpenseError::internal)?;
+
+        let key = format!("user:{}:recurring:v:{}:p:{}", user_id, v, page);
+
+        if let Some(cached) = redis.get(&key).await.ok().flatten() {
+            let recurring = serde_json::from_str(&cached).map_err(ExpenseError::internal)?;
+
+            return Ok(RecurringCached {
+                cached: true,
+                recurring,
+            });
+        }
+
+        let recurring = query_as::<_, RecurringResponse>(
+            r#"
+                SELECT id, user_id, category_id, amount, currency, description, account_id, tags,
+                    frequency, repeat_interval, day_of_month, start_date, end_date,
+                    occurrence_count, next_occurrence, splits
+                FROM recurring_expense
+                WHERE user_id = $1
+                ORDER BY updated_at DESC
+                LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(ExpenseError::internal)?;
+
+        let json = serde_json::to_string(&recurring).map_err(ExpenseError::internal)?;
+
+        redis
+            .set(key, json, 300)
+            .await
+            .map_err(ExpenseError::internal)?;
+
+        Ok(RecurringCached {
+            cached: false,
+            recurring,
+        })
+    }
+
+    // already generated expenses are kept, only the schedule goes away
+    pub async fn delete_recurring(
+        &self,
+        path: RecurringPath,
+        redis: &RedisService,
+        user_id: Uuid,
+    ) -> Result<String, ExpenseError> {
+        let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+
+        let id: Uuid = query_scalar(
+            r#"
+                DELETE FROM recurring_expense
+                WHERE id = $1 AND user_id = $2
+                RETURNING id
+            "#,
+        )
+        .bind(path.recurring_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ExpenseError::internal)?
+        .ok_or(ExpenseError::RecurringNotFound)?;
+
+        redis
+            .incr(&recurring_version_key(user_id))
+            .await
+            .map_err(ExpenseError::internal)?;
+
+        tx.commit().await.map_err(ExpenseError::internal)?;
+
+        Ok(id.to_string())
+    }
+
+    pub async fn get_upcoming(
+        &self,
+        params: UpcomingParams,
+        user_id: Uuid,
+    ) -> Result<Vec<UpcomingOccurrence>, ExpenseError> {
+        let today = Utc::now().date_naive();
+        let max_until = today.checked_add_days(Days::new(366)).unwrap_or(today);
+        let until = params
+            .until
+            .unwrap_or_else(|| today.checked_add_days(Days::new(30)).unwrap_or(today))
+            .min(max_until);
+
+        let schedules = query_as::<_, RecurringResponse>(
+            r#"
+                SELECT id, user_id, category_id, amount, currency, description, account_id, tags,
+                    frequency, repeat_interval, day_of_month, start_date, end_date,
+                    occurrence_count, next_occurrence, splits
+                FROM recurring_expense r
+                WHERE user_id = $1 AND next_occurrence IS NOT NULL AND next_occurrence <= $2
+                    AND NOT EXISTS (
+                        SELECT 1 FROM category c
+                        WHERE c.id = r.category_id AND c.deleted_at IS NOT NULL
+                    )
+            "#,
+        )
+        .bind(user_id)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(ExpenseError::internal)?;
+
+        let mut upcoming = Vec::new();
+
+        for schedule in &schedules {
+            let dates = (schedule.occurrence_count..)
+                .map_while(|n| schedule.occurrence(n))
+                .take_while(|date| *date <= until)
+                .take(MAX_UPCOMING_PER_SCHEDULE);
+
+            for date in dates {
+                upcoming.push(UpcomingOccurrence {
+                    recurring_id: schedule.id,
+                    category_id: schedule.category_id,
+                    amount: schedule.amount,
+                    currency: schedule.currency.clone(),
+                    description: schedule.description.clone(),
+                    date,
+                });
+            }
+        }
+
+        upcoming.sort_by_key(|o| o.date);
+
+        Ok(upcoming)
+    }
+
+    pub async fn materialize_due(
+        &self,
+        expenses: &ExpenseServices,
+        redis: &RedisService,
+    ) -> Result<i32, ExpenseError> {
+        let today = Utc::now().date_naive();
+
+        // schedules of a trashed category pause, restoring it catches them up
+        let due: Vec<Uuid> = query_scalar(
+            r#"
+                SELECT r.id FROM recurring_expense r
+                JOIN category c ON c.id = r.category_id
+                WHERE r.next_occurrence IS NOT NULL AND r.next_occurrence <= $1
+                    AND c.deleted_at IS NULL
+                    AND EXISTS (
+                        SELECT 1 FROM ledger_member m
+                        WHERE m.ledger_id = c.ledger_id AND m.user_id = r.user_id
+                            AND m.role <> 'viewer'
+                    )
+            "#,
+        )
+        .bind(today)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(ExpenseError::internal)?;
+
+        let mut generated = 0;
+
+        // one broken schedule must not hold back everyone else's
+        for id in due {
+            match self.materialize_schedule(id, today, expenses, redis).await {
+                Ok(count) => generated += count,
+                Err(e) => {
+                    error!(recurring_id = %id, error = ?e.to_string(), "Recurring schedule failed.")
+                }
+            }
+        }
+
+        Ok(generated)
+    }
+
+    async fn materialize_schedule(
+        &self,
+        id: Uuid,
+        today: NaiveDate,
+        expenses: &ExpenseServices,
+        redis: &RedisService,
+    ) -> Result<i32, ExpenseError> {
+        let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+
+        // another worker holding the row lock is already generating this schedule
+        let schedule = query_as::<_, RecurringResponse>(
+            r#"
+                SELECT id, user_id, category_id, amount, currency, description, account_id, tags,
+                    frequency, repeat_interval, day_of_month, start_date, end_date,
+                    occurrence_count, next_occurrence, splits
+                FROM recurring_expense
+                WHERE id = $1
+                FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ExpenseError::internal)?;
+
+        let Some(schedule) = schedule else {
+            return Ok(0);
+        };
+
+        let ledger_id: Uuid = query_scalar("SELECT ledger_id FROM category WHERE id = $1")
+            .bind(schedule.category_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(ExpenseError::internal)?;
+
+        let splits: Vec<ExpenseSplitRequest> = schedule.splits.iter().map(Into::into).collect();
+        let mut count = schedule.occurrence_count;
+        let mut next = schedule.occurrence(count);
+        let mut generated = 0;
+
+        while let Some(date) = next
+            && date <= today
+            && generated < MAX_OCCURRENCES_PER_RUN
+        {
+            let expense = query_as::<_, ExpenseResponse>(
+                r#"
+                    INSERT INTO expense (amount, description, user_id, category_id, date,
+                        account_id, is_recurring, tags, recurring_id, currency, ledger_id)
+                    SELECT $1, $2, $3, $4, $5, $6, true, $7, $8, COALESCE(
+                        $9,
+                        (SELECT currency FROM account WHERE id = $6),
+                        base_currency
+                    ), c.ledger_id
+                    FROM users u JOIN category c ON c.id = $4
+                    WHERE u.id = $3
+                        AND EXISTS (
+                            SELECT 1 FROM ledger_member m
+                            WHERE m.ledger_id = c.ledger_id AND m.user_id = $3
+                                AND m.role <> 'viewer'
+                        )
+                    ON CONFLICT (recurring_id, date) WHERE recurring_id IS NOT NULL DO NOTHING
+                    RETURNING id, amount, currency, description, user_id, category_id, date,
+                        account_id, is_recurring, tags
+                "#,
+            )
+            .bind(schedule.amount)
+            .bind(&schedule.description)
+            .bind(schedule.user_id)
+            .bind(schedule.category_id)
+            .bind(date)
+            .bind(schedule.account_id)
+            .bind(&schedule.tags)
+            .bind(schedule.id)
+            .bind(&schedule.currency)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(ExpenseError::internal)?;
+
+            match expense {
+                Some(expense) => {
+                    let expense = ExpenseResponse {
+                        splits: save_splits(&mut tx, expense.id, ledger_id, &splits).await?,
+                        ..expense
+                    };
+
+                    ensure_convertible(&mut tx, &expense).await?;
+
+                    record_history(
+                        &mut tx,
+                        ExpenseHistoryAction::Created,
+                        None,
+                        Some(&expense),
+                        schedule.user_id,
+                    )
+                    .await?;
+                }
+                // already generated by an earlier run, its history was written back then
+                None if already_generated(&mut tx, schedule.id, date).await? => {}
+                // the author can't write to the ledger anymore, the date stays due
+                None => break,
+            }
+
+            count += 1;
+            generated += 1;
+            next = schedule.occurrence(count);
+        }
+
+        if generated == 0 {
+            return Ok(0);
+        }
+
+        query(
+            r#"
+                UPDATE recurring_expense
+                SET occurrence_count = $2, next_occurrence = $3, updated_at = NOW()
+                WHERE id = $1
+            "#,
+        )
+        .bind(schedule.id)
+        .bind(count)
+        .bind(next)
+        .execute(&mut *tx)
+        .await
+        .map_err(ExpenseError::internal)?;
+
+        let mut categories = vec![schedule.category_id];
+        categories.extend(splits.iter().map(|s| s.category_id));
+        categories.sort();
+        categories.dedup();
+
+        expenses
+            .invalidate_expense_cache(redis, &categories, ledger_id, None)
+            .await?;
+
+        redis
+            .incr(&recurring_version_key(schedule.user_id))
+            .await
+            .map_err(ExpenseError::internal)?;
+
+        tx.commit().await.map_err(ExpenseError::internal)?;
+
+        Ok(generated)
+    }
+
+    pub fn spawn_worker(self, expenses: ExpenseServices, redis: RedisService, period: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(period);
+
+            loop {
+                interval.tick().await;
+
+                match self.materialize_due(&expenses, &redis).await {
+                    Ok(0) => {}
+                    Ok(generated) => info!(generated, "Materialized recurring expenses."),
+                    Err(e) => error!(error = ?e.to_string(), "Recurring expense worker failed."),
+                }
+            }
+        });
+    }
+}
+
+async fn already_generated(
+    conn: &mut PgConnection,
+    recurring_id: Uuid,
+    date: NaiveDate,
+) -> Result<bool, ExpenseError> {
+    query_scalar(
+        r#"
+            SELECT EXISTS (
+                SELECT 1 FROM expense WHERE recurring_id = $1 AND date = $2
+            )
+        "#,
+    )
+    .bind(recurring_id)
+    .bind(date)
+    .fetch_one(conn)
+    .await
+    .map_err(ExpenseError::internal)
+}
@@ -1,6 +1,12 @@
-use redis::{AsyncCommands, Client, FromRedisValue, RedisError, ToSingleRedisArg, pipe};
+use chrono::Utc;
+use redis::{
+    AsyncCommands, AsyncIter, Client, ExistenceCheck, FromRedisValue, RedisError, Script,
+    SetExpiry, SetOptions, ToSingleRedisArg, pipe,
+};
 use std::result::Result;
 
+use crate::utils::utils::create_uuid;
+
 #[derive(Clone)]
 pub struct RedisService {
     client: Client,
@@ -13,6 +19,41 @@ impl RedisService {
         Ok(Self { client })
     }
 
+    // SET EX only while the key still holds expected, checked and written in one script so
+    // of two racing writers only one can win
+    pub async fn compare_and_set(
+        &self,
+        k: &str,
+        expected: &str,
+        v: &str,
+        exp: u64,
+    ) -> Result<bool, RedisError> {
+        let mut con = self.client.get_multiplexed_async_connection().await?;
+        let set: i32 = Script::new(COMPARE_AND_SET)
+            .key(k)
+            .arg(expected)
+            .arg(v)
+            .arg(exp)
+            .invoke_async(&mut con)
+            .await?;
+
+        Ok(set == 1)
+    }
+
+    pub async fn delete_pattern(&self, pattern: &str) -> Result<usize, RedisError> {
+        let keys = self.keys_matching(pattern).await?;
+
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        let mut con = self.client.get_multiplexed_async_connection().await?;
+        let _: () = con.del(&keys).await?;
+
+        Ok(keys.len())
+    }
+
+    #[allow(unused)]
     pub async fn exists(&self, k: &str) -> Result<bool, RedisError> {
         let mut con = self.client.get_multiplexed_async_connection().await?;
         con.exists(k).await
@@ -29,6 +70,67 @@ impl RedisService {
         con.incr(k, 1).await
     }
 
+    // SCAN instead of KEYS so large keyspaces don't block the server
+    pub async fn keys_matching(&self, pattern: &str) -> Result<Vec<String>, RedisError> {
+        let mut con = self.client.get_multiplexed_async_connection().await?;
+        let mut iter: AsyncIter<String> = con.scan_match(pattern).await?;
+        let mut keys = Vec::new();
+
+        while let Some(key) = iter.next_item().await {
+            keys.push(key?);
+        }
+
+        Ok(keys)
+    }
+
+    // sliding window log in a sorted set, Some(seconds to wait) once the limit is reached
+    pub async fn sliding_window(
+        &self,
+        k: &str,
+        limit: u64,
+        window_secs: u64,
+    ) -> Result<Option<u64>, RedisError> {
+        let mut con = self.client.get_multiplexed_async_connection().await?;
+        let now = Utc::now().timestamp_millis();
+        let window = window_secs as i64 * 1000;
+
+        let (count, oldest): (u64, Vec<(String, i64)>) = pipe()
+            .atomic()
+            .zrembyscore(k, 0, now - window)
+            .ignore()
+            .zcard(k)
+            .zrange_withscores(k, 0, 0)
+            .query_async(&mut con)
+            .await?;
+
+        // rejected requests are not logged, hammering doesn't extend the wait
+        if count >= limit {
+            let oldest = oldest.first().map_or(now, |(_, at)| *at);
+            let wait = (oldest + window - now).max(0) as u64;
+
+            return Ok(Some(wait.div_ceil(1000).max(1)));
+        }
+
+        pipe()
+            .atomic()
+            .zadd(k, format!("{now}:{}", create_uuid()), now)
+            .ignore()
+            .pexpire(k, window)
+            .ignore()
+            .query_async::<()>(&mut con)
+            .await?;
+
+        Ok(None)
+    }
+
+    // None when the key is missing or has no expiry
+    pub async fn ttl(&self, k: &str) -> Result<Option<u64>, RedisError> {
+        let mut con = self.client.get_multiplexed_async_connection().await?;
+        let ttl: i64 = con.ttl(k).await?;
+
+        Ok((ttl > 0).then_some(ttl as u64))
+    }
+
     pub async fn pipeline<T: FromRedisValue>(
         &self,
         f: impl FnOnce(&mut redis::Pipeline),
@@ -44,6 +146,27 @@ impl RedisService {
         con.del(k).await
     }
 
+    // SET NX EX, false when the key already existed
+    pub async fn set_nx_ex<T>(&self, k: &str, v: T, exp: u64) -> Result<bool, RedisError>
+    where
+        T: ToSingleRedisArg + Send + Sync,
+    {
+        let mut con = self.client.get_multiplexed_async_connection().await?;
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(exp));
+
+        let set: Option<String> = con.set_options(k, v, options).await?;
+
+        Ok(set.is_some())
+    }
+
+    // GETDEL, for single use values
+    pub async fn take(&self, k: &str) -> Result<Option<String>, RedisError> {
+        let mut con = self.client.get_multiplexed_async_connection().await?;
+        con.get_del(k).await
+    }
+
     pub async fn set<T>(&self, k: String, v: T, exp: u64) -> Result<(), RedisError>
     where
         T: ToSingleRedisArg + Send + Sync,
@@ -61,3 +184,11 @@ impl RedisService {
         con.set_nx(k, v).await
     }
 }
+
+const COMPARE_AND_SET: &str = r#"
+    if redis.call('GET', KEYS[1]) == ARGV[1] then
+        redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+        return 1
+    end
+    return 0
+"#;
@@ -0,0 +1,282 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, query_as};
+use std::cmp::Reverse;
+
+use crate::{
+    errors::report_errors::ReportError,
+    models::{
+        ledger_models::LedgerAccess,
+        report_models::{
+            CashFlowMonth, CashFlowReport, CashFlowReportCached, CategoryMonthTotal, CategoryTotal,
+            MonthTotal, ReportParams, SpendingGroupQuery, SpendingReport, SpendingReportCached,
+        },
+    },
+    services::redis_services::RedisService,
+    utils::utils::{all_expenses_version_key, categories_version_key, incomes_version_key},
+};
+
+#[derive(Clone)]
+pub struct ReportService {
+    pool: PgPool,
+}
+
+impl ReportService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_spending_report(
+        &self,
+        params: ReportParams,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<SpendingReportCached, ReportError> {
+        let (from, to) = report_range(params)?;
+
+        let e_key = all_expenses_version_key(access.user_id);
+        let c_key = categories_version_key(access.user_id);
+
+        // category names are part of the report, so a rename must miss the cache too
+        let (_, ev, _, cv): (i64, String, i64, String) = redis
+            .pipeline(|pipe| {
+                pipe.set_nx(&e_key, "1")
+                    .get(&e_key)
+                    .set_nx(&c_key, "1")
+                    .get(&c_key);
+            })
+            .await
+            .map_err(ReportError::internal)?;
+
+        let key = format!(
+            "user:{}:ledger:{}:report:spending:e:{}:c:{}:from:{}:to:{}",
+            access.user_id, access.ledger_id, ev, cv, from, to
+        );
+
+        if let Some(cached) = redis.get(&key).await.ok().flatten() {
+            let report = serde_json::from_str(&cached).map_err(ReportError::internal)?;
+
+            return Ok(SpendingReportCached {
+                cached: true,
+                report,
+            });
+        }
+
+        // every member's spending in the ledger, in the reader's base currency
+        let rows = query_as::<_, SpendingGroupQuery>(
+            r#"
+                SELECT e.category_id, c.name AS category_name,
+                    date_trunc('month', e.date)::date AS month,
+                    ROUND(SUM(convert_amount(
+                        e.user_id, e.amount, e.currency, u.base_currency, e.date
+                    )), 2) AS total,
+                    COUNT(DISTINCT e.expense_id) AS count
+                FROM expense_portion e
+                JOIN category c ON c.id = e.category_id
+                JOIN users u ON u.id = $1
+                WHERE c.ledger_id = $4 AND e.date >= $2 AND e.date <= $3
+                    AND e.deleted_at IS NULL
+                GROUP BY GROUPING SETS (
+                    (e.category_id, c.name),
+                    (date_trunc('month', e.date)::date),
+                    (e.category_id, c.name, date_trunc('month', e.date)::date),
+                    ()
+                )
+                ORDER BY month NULLS FIRST, category_name NULLS FIRST
+            "#,
+        )
+        .bind(access.user_id)
+        .bind(from)
+        .bind(to)
+        .bind(access.ledger_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(ReportError::conversion)?;
+
+        let report = spending_report(rows, from, to);
+
+        let json = serde_json::to_string(&report).map_err(ReportError::internal)?;
+
+        redis
+            .set(key, json, 300)
+            .await
+            .map_err(ReportError::internal)?;
+
+        Ok(SpendingReportCached {
+            cached: false,
+            report,
+        })
+    }
+
+    pub async fn get_cash_flow(
+        &self,
+        params: ReportParams,
+        redis: &RedisService,
+        access: &LedgerAccess,
+    ) -> Result<CashFlowReportCached, ReportError> {
+        let (from, to) = report_range(params)?;
+
+        let e_key = all_expenses_version_key(access.user_id);
+        let i_key = incomes_version_key(access.user_id);
+
+        let (_, ev, _, iv): (i64, String, i64, String) = redis
+            .pipeline(|pipe| {
+                pipe.set_nx(&e_key, "1")
+                    .get(&e_key)
+                    .set_nx(&i_key, "1")
+                    .get(&i_key);
+            })
+            .await
+            .map_err(ReportError::internal)?;
+
+        let key = format!(
+            "user:{}:ledger:{}:report:cash_flow:e:{}:i:{}:from:{}:to:{}",
+            access.user_id, access.ledger_id, ev, iv, from, to
+        );
+
+        if let Some(cached) = redis.get(&key).await.ok().flatten() {
+            let report = serde_json::from_str(&cached).map_err(ReportError::internal)?;
+
+            return Ok(CashFlowReportCached {
+                cached: true,
+                report,
+            });
+        }
+
+        // income stays the reader's own, spending is the ledger's.
+        // both sides are converted to the base currency before they are netted
+        let by_month = query_as::<_, CashFlowMonth>(
+            r#"
+                WITH flow AS (
+                    SELECT i.date, convert_amount(
+                        i.user_id, i.amount, i.currency, u.base_currency, i.date
+                    ) AS income, 0 AS expense
+                    FROM income i
+                    JOIN users u ON u.id = i.user_id
+                    WHERE i.user_id = $1 AND i.date >= $2 AND i.date <= $3
+                    UNION ALL
+                    SELECT e.date, 0, convert_amount(
+                        e.user_id, e.amount, e.currency, u.base_currency, e.date
+                    )
+                    FROM expense e
+                    JOIN users u ON u.id = $1
+                    WHERE e.ledger_id = $4 AND e.date >= $2 AND e.date <= $3
+                        AND e.deleted_at IS NULL
+                ),
+                monthly AS (
+                    SELECT date_trunc('month', date)::date AS month,
+                        ROUND(SUM(income), 2) AS income,
+                        ROUND(SUM(expense), 2) AS expense
+                    FROM flow
+                    GROUP BY 1
+                )
+                SELECT m.month::date AS month,
+                    COALESCE(monthly.income, 0) AS income,
+                    COALESCE(monthly.expense, 0) AS expense,
+                    COALESCE(monthly.income, 0) - COALESCE(monthly.expense, 0) AS net
+                FROM generate_series(
+                    date_trunc('month', $2::date), $3::date, interval '1 month'
+                ) AS m(month)
+                LEFT JOIN monthly ON monthly.month = m.month::date
+                ORDER BY month
+            "#,
+        )
+        .bind(access.user_id)
+        .bind(from)
+        .bind(to)
+        .bind(access.ledger_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(ReportError::conversion)?;
+
+        let income: Decimal = by_month.iter().map(|m| m.income).sum();
+        let expense: Decimal = by_month.iter().map(|m| m.expense).sum();
+
+        let report = CashFlowReport {
+            from,
+            to,
+            income,
+            expense,
+            net: income - expense,
+            by_month,
+        };
+
+        let json = serde_json::to_string(&report).map_err(ReportError::internal)?;
+
+        redis
+            .set(key, json, 300)
+            .await
+            .map_err(ReportError::internal)?;
+
+        Ok(CashFlowReportCached {
+            cached: false,
+            report,
+        })
+    }
+}
+
+// defaults to the current year up to today
+fn report_range(params: ReportParams) -> Result<(NaiveDate, NaiveDate), ReportError> {
+    let today = Utc::now().date_naive();
+    let to = params.to.unwrap_or(today);
+    let from = params
+        .from
+        .unwrap_or_else(|| NaiveDate::from_ymd_opt(to.year(), 1, 1).unwrap_or(to));
+
+    if from > to {
+        return Err(ReportError::InvalidRange);
+    }
+
+    Ok((from, to))
+}
+
+fn spending_report(
+    rows: Vec<SpendingGroupQuery>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> SpendingReport {
+    let mut report = SpendingReport {
+        from,
+        to,
+        total: Decimal::ZERO,
+        count: 0,
+        by_category: Vec::new(),
+        by_month: Vec::new(),
+        by_category_month: Vec::new(),
+    };
+
+    for row in rows {
+        match (row.category_id, row.category_name, row.month) {
+            (Some(category_id), Some(category_name), Some(month)) => {
+                report.by_category_month.push(CategoryMonthTotal {
+                    category_id,
+                    category_name,
+                    month,
+                    total: row.total,
+                    count: row.count,
+                })
+            }
+            (Some(category_id), Some(category_name), None) => {
+                report.by_category.push(CategoryTotal {
+                    category_id,
+                    category_name,
+                    total: row.total,
+                    count: row.count,
+                })
+            }
+            (None, _, Some(month)) => report.by_month.push(MonthTotal {
+                month,
+                total: row.total,
+                count: row.count,
+            }),
+            _ => {
+                report.total = row.total;
+                report.count = row.count;
+            }
+        }
+    }
+
+    report.by_category.sort_by_key(|c| Reverse(c.total));
+
+    report
+}
@@ -0,0 +1,72 @@
+use sqlx::{PgPool, query};
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::errors::expense_errors::ExpenseError;
+
+#[derive(Clone)]
+pub struct TrashService {
+    pool: PgPool,
+    retention_days: i32,
+}
+
+impl TrashService {
+    pub fn new(pool: PgPool, retention_days: i32) -> Self {
+        Self {
+            pool,
+            retention_days,
+        }
+    }
+
+    // trashed rows are already hidden everywhere, so purging leaves the caches alone
+    pub async fn purge_expired(&self) -> Result<(u64, u64), ExpenseError> {
+        let mut tx = self.pool.begin().await.map_err(ExpenseError::internal)?;
+
+        let expenses = query(
+            r#"
+                DELETE FROM expense
+                WHERE deleted_at <= NOW() - make_interval(days => $1)
+            "#,
+        )
+        .bind(self.retention_days)
+        .execute(&mut *tx)
+        .await
+        .map_err(ExpenseError::internal)?
+        .rows_affected();
+
+        // budgets, schedules and any expenses left in the category go through ON DELETE CASCADE
+        let categories = query(
+            r#"
+                DELETE FROM category
+                WHERE deleted_at <= NOW() - make_interval(days => $1)
+            "#,
+        )
+        .bind(self.retention_days)
+        .execute(&mut *tx)
+        .await
+        .map_err(ExpenseError::internal)?
+        .rows_affected();
+
+        tx.commit().await.map_err(ExpenseError::internal)?;
+
+        Ok((expenses, categories))
+    }
+
+    pub fn spawn_worker(self, period: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(period);
+
+            loop {
+                interval.tick().await;
+
+                match self.purge_expired().await {
+                    Ok((0, 0)) => {}
+                    Ok((expenses, categories)) => {
+                        info!(expenses, categories, "Purged expired trash.")
+                    }
+                    Err(e) => error!(error = ?e.to_string(), "Trash retention worker failed."),
+                }
+            }
+        });
+    }
+}
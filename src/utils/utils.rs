@@ -1,14 +1,147 @@
+use chrono::{Datelike, NaiveDate};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 pub fn create_uuid() -> String {
     Uuid::new_v4().to_string()
 }
 
+// 244 random bits, for links sent by mail and other secrets handed to users
+pub fn create_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+// secrets are only stored hashed, a leaked keyspace can't be replayed
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+// ISO 4217 style, uppercase letters only
+pub fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+// eur_rate() raises XR001 when no rate covers a currency on a date
+pub fn missing_exchange_rate(e: &sqlx::Error) -> Option<String> {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("XR001") => {
+            Some(db_err.message().to_owned())
+        }
+        _ => None,
+    }
+}
+
+pub fn first_day_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+// AUTH KEYS
+pub fn refresh_token_key(user_id: Uuid, jti: &str) -> String {
+    format!("user:{}:refresh:{}", user_id, jti)
+}
+
+pub fn refresh_tokens_pattern(user_id: Uuid) -> String {
+    format!("user:{}:refresh:*", user_id)
+}
+
+pub fn session_key(user_id: Uuid, session_id: &str) -> String {
+    format!("user:{}:session:{}", user_id, session_id)
+}
+
+pub fn sessions_pattern(user_id: Uuid) -> String {
+    format!("user:{}:session:*", user_id)
+}
+
+pub fn user_keys_pattern(user_id: Uuid) -> String {
+    format!("user:{}:*", user_id)
+}
+
+pub fn password_reset_key(token_hash: &str) -> String {
+    format!("password_reset:{}", token_hash)
+}
+
+pub fn user_password_reset_key(user_id: Uuid) -> String {
+    format!("user:{}:password_reset", user_id)
+}
+
+pub fn verification_sent_key(user_id: Uuid) -> String {
+    format!("user:{}:verification_sent", user_id)
+}
+
+pub fn login_challenge_key(token_hash: &str) -> String {
+    format!("login_challenge:{}", token_hash)
+}
+
+pub fn login_challenge_attempts_key(token_hash: &str) -> String {
+    format!("login_challenge:{}:attempts", token_hash)
+}
+
+pub fn totp_last_step_key(user_id: Uuid) -> String {
+    format!("user:{}:totp_last_step", user_id)
+}
+
+// login limits are keyed by what the client sends, the account may not exist
+pub fn login_ip_rate_key(ip: &str) -> String {
+    format!("rate_limit:login:ip:{}", ip)
+}
+
+pub fn login_email_rate_key(email: &str) -> String {
+    format!("rate_limit:login:email:{}", email.to_lowercase())
+}
+
+pub fn login_failures_key(email: &str) -> String {
+    format!("login_failures:{}", email.to_lowercase())
+}
+
+pub fn login_lockout_key(email: &str) -> String {
+    format!("login_lockout:{}", email.to_lowercase())
+}
+
+pub fn user_rate_limit_key(user_id: Uuid) -> String {
+    format!("user:{}:rate_limit", user_id)
+}
+
+pub fn api_token_rate_limit_key(token_hash: &str) -> String {
+    format!("rate_limit:api_token:{}", token_hash)
+}
+
+pub fn denied_access_token_key(user_id: Uuid, jti: &str) -> String {
+    format!("user:{}:access:denied:{}", user_id, jti)
+}
+
+pub fn access_revoked_at_key(user_id: Uuid) -> String {
+    format!("user:{}:access:revoked_at", user_id)
+}
+
+// BUDGET KEYS
+pub fn budgets_version_key(user_id: Uuid) -> String {
+    format!("user:{}:budgets:version", user_id)
+}
+
 // CATEGORY KEYS
 pub fn categories_version_key(user_id: Uuid) -> String {
     format!("user:{}:categories:version", user_id)
 }
 
+pub fn single_category_key(category_id: Uuid, ledger_id: Uuid, user_id: Uuid) -> String {
+    format!(
+        "user:{}:ledger:{}:category:{}",
+        user_id, ledger_id, category_id
+    )
+}
+
+pub fn single_categories_pattern(user_id: Uuid) -> String {
+    format!("user:{}:ledger:*:category:*", user_id)
+}
+
+// INCOME KEYS
+pub fn incomes_version_key(user_id: Uuid) -> String {
+    format!("user:{}:incomes:version", user_id)
+}
+
 // EXPENSE KEYS
 pub fn all_expenses_version_key(user_id: Uuid) -> String {
     format!("user:{}:expenses:version", user_id)
@@ -28,10 +161,34 @@ pub fn category_filter_total_expense_key(category_id: Uuid, user_id: Uuid) -> St
     )
 }
 
-pub fn single_expense_key(expense_id: Uuid, user_id: Uuid) -> String {
-    format!("user:{}:expense:{}", user_id, expense_id)
+pub fn category_filter_totals_pattern(user_id: Uuid) -> String {
+    format!("user:{}:filter:category:*:total:expenses", user_id)
+}
+
+pub fn category_filter_pattern(user_id: Uuid) -> String {
+    format!("user:{}:filter:category:*", user_id)
+}
+
+pub fn recurring_version_key(user_id: Uuid) -> String {
+    format!("user:{}:recurring:version", user_id)
+}
+
+pub fn single_expense_key(expense_id: Uuid, ledger_id: Uuid, user_id: Uuid) -> String {
+    format!(
+        "user:{}:ledger:{}:expense:{}",
+        user_id, ledger_id, expense_id
+    )
+}
+
+pub fn single_expenses_pattern(user_id: Uuid) -> String {
+    format!("user:{}:ledger:*:expense:*", user_id)
+}
+
+// totals are in the viewer's base currency, so each member caches their own
+pub fn total_expense_key(ledger_id: Uuid, user_id: Uuid) -> String {
+    format!("user:{}:ledger:{}:total:expenses", user_id, ledger_id)
 }
 
-pub fn total_expense_key(user_id: Uuid) -> String {
-    format!("user:{}:total:expenses", user_id)
+pub fn total_expenses_pattern(user_id: Uuid) -> String {
+    format!("user:{}:ledger:*:total:expenses", user_id)
 }